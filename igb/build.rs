@@ -1,3 +1,7 @@
 fn main() {
     bare_test_macros::build_test_setup!();
+    // examples 同样由 bare-test 运行，需要相同的链接参数
+    println!("cargo::rustc-link-arg-examples=-Ttest_case_link.ld");
+    println!("cargo::rustc-link-arg-examples=-no-pie");
+    println!("cargo::rustc-link-arg-examples=-znostart-stop-gc");
 }
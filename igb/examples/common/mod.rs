@@ -0,0 +1,183 @@
+//! Shared bring-up for the example binaries: smoltcp device adapter, DHCP and
+//! an interface that answers pings.
+
+use core::{ptr::NonNull, time::Duration};
+
+use bare_test::time::{since_boot, spin_delay};
+use eth_igb::{Igb, Request, RxPacket, RxRing, TxRing, impl_trait, osal::Kernel};
+use log::*;
+use smoltcp::{
+    iface::{Config, Interface, SocketSet},
+    phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken},
+    socket::dhcpv4,
+    time::Instant,
+    wire::{EthernetAddress, HardwareAddress, IpCidr},
+};
+
+struct KernelImpl;
+
+impl_trait! {
+    impl Kernel for KernelImpl {
+        fn sleep(duration: Duration) {
+            spin_delay(duration);
+        }
+
+        fn now() -> Duration {
+            since_boot()
+        }
+    }
+}
+
+struct IgbDevice {
+    rx_ring: RxRing,
+    tx_ring: TxRing,
+}
+
+impl IgbDevice {
+    fn new(mut rx_ring: RxRing, tx_ring: TxRing) -> Self {
+        for _ in 0..rx_ring.request_max_count() {
+            let buff = alloc::vec![0u8; rx_ring.packet_size()];
+            rx_ring.submit(Request::new_rx(buff)).unwrap();
+        }
+        Self { rx_ring, tx_ring }
+    }
+}
+
+impl Device for IgbDevice {
+    type RxToken<'a> = IgbRxToken<'a>;
+    type TxToken<'a> = IgbTxToken<'a>;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        // 回复可能需要发送，发送队列满时先不取包
+        while self.tx_ring.next_finished().is_some() {}
+        if self.tx_ring.is_queue_full() {
+            return None;
+        }
+        self.rx_ring.next_pkt().map(|buff| {
+            (
+                IgbRxToken { buff },
+                IgbTxToken {
+                    ring: &mut self.tx_ring,
+                },
+            )
+        })
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        // 释放已完成的发送请求
+        while self.tx_ring.next_finished().is_some() {}
+        if self.tx_ring.is_queue_full() {
+            return None;
+        }
+        Some(IgbTxToken {
+            ring: &mut self.tx_ring,
+        })
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.max_transmission_unit = 1500;
+        caps.max_burst_size = Some(1);
+        caps.medium = Medium::Ethernet;
+        caps
+    }
+}
+
+struct IgbRxToken<'a> {
+    buff: RxPacket<'a>,
+}
+
+impl RxToken for IgbRxToken<'_> {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        let r = f(&self.buff);
+        self.buff.re_submit().unwrap();
+        r
+    }
+}
+
+struct IgbTxToken<'a> {
+    ring: &'a mut TxRing,
+}
+
+impl TxToken for IgbTxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut buffer = alloc::vec![0u8; len];
+        let result = f(&mut buffer);
+        if let Err(e) = self.ring.send(Request::new_tx(buffer)) {
+            // 和丢包一样处理，由上层协议重传
+            warn!("tx dropped: {e:?}");
+        }
+        result
+    }
+}
+
+fn now() -> Instant {
+    Instant::from_millis(since_boot().as_millis() as i64)
+}
+
+/// Bring up the NIC mapped at `mmio_base`, lease an address over DHCP and
+/// answer pings until the machine is reset.
+///
+/// The device is polled, no interrupt is registered.
+pub fn serve(mmio_base: NonNull<u8>) -> ! {
+    let mut igb = Igb::new(mmio_base).unwrap();
+    igb.open().unwrap();
+
+    let mac = igb.read_mac();
+    info!("igb opened, mac {mac:?}");
+
+    info!("waiting for link up...");
+    while let Err(e) = igb.wait_link_up_timeout(Duration::from_secs(10)) {
+        info!("{e}, still waiting...");
+    }
+    info!("link up: {:?}", igb.status());
+
+    let (tx_ring, rx_ring) = igb.new_ring().unwrap();
+    let mut device = IgbDevice::new(rx_ring, tx_ring);
+
+    let config = Config::new(HardwareAddress::Ethernet(EthernetAddress::from_bytes(
+        &mac.bytes(),
+    )));
+    let mut iface = Interface::new(config, &mut device, now());
+
+    let mut sockets = SocketSet::new(alloc::vec![]);
+    let dhcp = sockets.add(dhcpv4::Socket::new());
+
+    // ICMP echo 由 smoltcp 的接口层直接应答，只需不断 poll
+    loop {
+        iface.poll(now(), &mut device, &mut sockets);
+
+        match sockets.get_mut::<dhcpv4::Socket>(dhcp).poll() {
+            Some(dhcpv4::Event::Configured(lease)) => {
+                info!("DHCP lease {}, answering pings", lease.address);
+                iface.update_ip_addrs(|addrs| {
+                    addrs.clear();
+                    addrs.push(IpCidr::Ipv4(lease.address)).unwrap();
+                });
+                iface.routes_mut().remove_default_ipv4_route();
+                if let Some(router) = lease.router {
+                    iface.routes_mut().add_default_ipv4_route(router).unwrap();
+                }
+            }
+            Some(dhcpv4::Event::Deconfigured) => {
+                warn!("DHCP lease lost");
+                iface.update_ip_addrs(|addrs| addrs.clear());
+                iface.routes_mut().remove_default_ipv4_route();
+            }
+            None => {}
+        }
+
+        let delay = iface
+            .poll_delay(now(), &sockets)
+            .map_or(Duration::from_millis(10), |d| {
+                Duration::from_millis(d.total_millis().min(10))
+            });
+        spin_delay(delay);
+    }
+}
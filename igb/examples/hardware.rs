@@ -0,0 +1,71 @@
+//! DHCP + ping responder on a board whose firmware already enumerated the
+//! NIC, e.g. through U-Boot `pci enum`.
+//!
+//! The BAR0 physical address is fixed at build time; the example fails at
+//! run time when it was built without one:
+//!
+//! ```bash
+//! IGB_MMIO_BASE=0x40000000 IGB_MMIO_SIZE=0x20000 \
+//!     cargo test -p eth-igb --example hardware --features examples -- --uboot
+//! ```
+//!
+//! Firmware must have enabled memory decoding and bus mastering.
+
+#![no_std]
+#![no_main]
+#![feature(used_with_arg)]
+
+extern crate alloc;
+
+mod common;
+
+/// Parse a `0x` prefixed or decimal build-time parameter.
+const fn parse(value: &str) -> usize {
+    let bytes = value.as_bytes();
+    let (radix, mut i) = if bytes.len() > 2 && bytes[0] == b'0' && (bytes[1] | 0x20) == b'x' {
+        (16, 2)
+    } else {
+        (10, 0)
+    };
+    let mut n = 0;
+    while i < bytes.len() {
+        let digit = match bytes[i] {
+            b'0'..=b'9' => bytes[i] - b'0',
+            b'a'..=b'f' => bytes[i] - b'a' + 10,
+            b'A'..=b'F' => bytes[i] - b'A' + 10,
+            b'_' => {
+                i += 1;
+                continue;
+            }
+            _ => panic!("invalid number"),
+        } as usize;
+        assert!(digit < radix, "invalid number");
+        n = n * radix + digit;
+        i += 1;
+    }
+    n
+}
+
+const MMIO_BASE: Option<usize> = match option_env!("IGB_MMIO_BASE") {
+    Some(base) => Some(parse(base)),
+    None => None,
+};
+const MMIO_SIZE: usize = match option_env!("IGB_MMIO_SIZE") {
+    Some(size) => parse(size),
+    None => 0x20000,
+};
+
+#[bare_test::tests]
+mod example {
+    use bare_test::mem::iomap;
+
+    use super::{MMIO_BASE, MMIO_SIZE, common};
+
+    #[test]
+    fn dhcp_ping() {
+        let Some(base) = MMIO_BASE else {
+            panic!("IGB_MMIO_BASE was not set when building the example");
+        };
+        common::serve(iomap(base.into(), MMIO_SIZE));
+    }
+}
@@ -0,0 +1,87 @@
+//! DHCP + ping responder on QEMU `virt` with `-device igb`.
+//!
+//! ```bash
+//! cargo test -p eth-igb --example qemu --features examples
+//! ```
+//!
+//! The NIC is found by walking the ECAM PCIe host from the device tree, BARs
+//! are assigned here, and the BAR0 mapping is handed to [`common::serve`].
+
+#![no_std]
+#![no_main]
+#![feature(used_with_arg)]
+
+extern crate alloc;
+
+mod common;
+
+#[bare_test::tests]
+mod example {
+    use core::ptr::NonNull;
+
+    use bare_test::{
+        fdt_parser::PciSpace,
+        globals::{PlatformInfoKind, global_val},
+        mem::iomap,
+    };
+    use eth_igb::Igb;
+    use pcie::{CommandRegister, RootComplexGeneric, SimpleBarAllocator};
+
+    use super::common;
+
+    #[test]
+    fn dhcp_ping() {
+        common::serve(find_igb().expect("no igb device on the PCIe bus"));
+    }
+
+    fn find_igb() -> Option<NonNull<u8>> {
+        let PlatformInfoKind::DeviceTree(fdt) = &global_val().platform_info;
+        let fdt = fdt.get();
+        let pcie = fdt
+            .find_compatible(&["pci-host-ecam-generic"])
+            .next()?
+            .into_pci()?;
+
+        let ecam = pcie.node.reg()?.next()?;
+        let base_vaddr = iomap((ecam.address as usize).into(), ecam.size?);
+
+        let mut bar_alloc = SimpleBarAllocator::default();
+        for range in pcie.ranges().ok()? {
+            match range.space {
+                PciSpace::Memory32 => bar_alloc.set_mem32(range.cpu_address as _, range.size as _),
+                PciSpace::Memory64 => bar_alloc.set_mem64(range.cpu_address, range.size),
+                _ => {}
+            }
+        }
+
+        let mut root = RootComplexGeneric::new(base_vaddr);
+        // 第一次枚举分配 BAR，之后保留分配结果
+        for _ in root.enumerate(None, Some(bar_alloc)) {}
+
+        for header in root.enumerate_keep_bar(None) {
+            let pcie::Header::Endpoint(mut endpoint) = header.header else {
+                continue;
+            };
+            if !Igb::check_vid_did(endpoint.vendor_id, endpoint.device_id) {
+                continue;
+            }
+            endpoint.update_command(header.root, |cmd| {
+                cmd | CommandRegister::MEMORY_ENABLE | CommandRegister::BUS_MASTER_ENABLE
+            });
+
+            let (bar_addr, bar_size) = match endpoint.bar {
+                pcie::BarVec::Memory32(bars) => {
+                    let bar0 = bars[0].as_ref()?;
+                    (bar0.address as usize, bar0.size as usize)
+                }
+                pcie::BarVec::Memory64(bars) => {
+                    let bar0 = bars[0].as_ref()?;
+                    (bar0.address as usize, bar0.size as usize)
+                }
+                pcie::BarVec::Io(_) => return None,
+            };
+            return Some(iomap(bar_addr.into(), bar_size));
+        }
+        None
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{DeviceFamily, irq::MAX_MSIX_VECTORS};
+
+/// Largest frame including L2 header and FCS the MAC accepts.
+const JUMBO_FRAME_MAX: usize = 9216;
+
+/// What the controller supports, see [`crate::Igb::capabilities`].
+///
+/// These are hardware capabilities; whether the driver makes use of them is
+/// up to the respective API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceCaps {
+    /// PCI device ID from the NVM, `None` when the NVM could not be read.
+    pub device_id: Option<u16>,
+    /// RX/TX queue pairs.
+    pub queues: usize,
+    /// MSI-X table entries.
+    pub msix_vectors: usize,
+    /// Energy Efficient Ethernet on the copper PHY.
+    pub eee: bool,
+    /// IEEE 1588 timestamping.
+    pub ptp: bool,
+    /// SR-IOV virtual functions, 0 when unsupported.
+    pub sriov_vfs: usize,
+    /// Largest frame in bytes including L2 header and FCS.
+    pub jumbo_max: usize,
+    /// TCP segmentation offload.
+    pub tso: bool,
+    /// 802.1Qbb priority flow control. None of the supported controllers
+    /// pause individual traffic classes; use link-level
+    /// [`crate::Igb::set_flow_control`] instead.
+    pub pfc: bool,
+}
+
+impl DeviceCaps {
+    pub(crate) fn new(family: DeviceFamily, device_id: Option<u16>) -> Self {
+        let (msix_vectors, sriov_vfs) = match family {
+            DeviceFamily::I82576 => (MAX_MSIX_VECTORS as usize, 8),
+            DeviceFamily::I350 => (10, 8),
+            DeviceFamily::I210 => (5, 0),
+        };
+        // 只有铜口 PHY 支持 EEE；82576 不支持
+        let eee = match device_id {
+            Some(did) => matches!(did, 0x1521 | 0x1533 | 0x1539 | 0x157B),
+            None => family != DeviceFamily::I82576,
+        };
+        Self {
+            device_id,
+            queues: family.queue_count(),
+            msix_vectors,
+            eee,
+            ptp: true,
+            sriov_vfs,
+            jumbo_max: JUMBO_FRAME_MAX,
+            tso: true,
+            pfc: false,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{
+    ETYPE_FILTER_COUNT, EtherTypeFilter, FlowControl, IrqMode, LLI_FILTER_COUNT, LliFilter,
+    MAC_FILTER_COUNT, MacAddr6, PacketBuffers, PowerPolicy, RxLatencyProfile, StormPolicy,
+    TUPLE_FILTER_COUNT, TupleFilter, TxScheduling,
+};
+
+/// Device wide settings, as dumped by [`crate::Igb::config`] and applied in
+/// bulk by [`crate::Igb::apply_config`].
+///
+/// With the `serde` feature this can be persisted, e.g. in a settings store.
+/// Per-queue settings live in [`crate::RingConfig`]. Flexible filters and
+/// ARP/ND proxying aren't included: the driver keeps no copy of what was
+/// written to the hardware, so re-install them after applying a config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IgbConfig {
+    pub irq_mode: IrqMode,
+    pub irq_storm_limit: Option<u32>,
+    pub flow_control: FlowControl,
+    pub rx_pause_frames: bool,
+    pub rx_mac_control_frames: bool,
+    pub smart_speed: bool,
+    pub power_policy: PowerPolicy,
+    pub storm_control: StormPolicy,
+    pub tx_scheduling: TxScheduling,
+    /// `None` for the default (or automatic jumbo) split.
+    pub packet_buffers: Option<PacketBuffers>,
+    /// `None` for no interrupt throttling, as after reset.
+    pub rx_latency: Option<RxLatencyProfile>,
+    /// Index `n` shares its hardware slot with `tuple_filters[n]`; only one
+    /// of the two can be set.
+    pub lli_filters: [Option<LliFilter>; LLI_FILTER_COUNT],
+    pub lli_vlan_priority: Option<u8>,
+    pub lli_moderation: Option<u8>,
+    pub etype_filters: [Option<EtherTypeFilter>; ETYPE_FILTER_COUNT],
+    pub tuple_filters: [Option<TupleFilter>; TUPLE_FILTER_COUNT],
+    /// `None` for the address from the NVM.
+    pub mac_address: Option<MacAddr6>,
+    /// Entry `n` is receive address slot `n + 1`, see
+    /// [`crate::Igb::add_mac_filter`]. Entries beyond the family's
+    /// [`crate::DeviceFamily::rar_count`] must be `None`.
+    pub mac_filters: [Option<MacAddr6>; MAC_FILTER_COUNT],
+}
+
+impl Default for IgbConfig {
+    /// The settings of a newly created [`crate::Igb`].
+    fn default() -> Self {
+        Self {
+            irq_mode: IrqMode::default(),
+            irq_storm_limit: None,
+            flow_control: FlowControl::default(),
+            rx_pause_frames: false,
+            rx_mac_control_frames: false,
+            smart_speed: true,
+            power_policy: PowerPolicy::default(),
+            storm_control: StormPolicy::default(),
+            tx_scheduling: TxScheduling::default(),
+            packet_buffers: None,
+            rx_latency: None,
+            lli_filters: [None; LLI_FILTER_COUNT],
+            lli_vlan_priority: None,
+            lli_moderation: None,
+            etype_filters: [None; ETYPE_FILTER_COUNT],
+            tuple_filters: [None; TUPLE_FILTER_COUNT],
+            mac_address: None,
+            mac_filters: [None; MAC_FILTER_COUNT],
+        }
+    }
+}
@@ -2,7 +2,11 @@
 
 use tock_registers::register_bitfields;
 
-pub trait Descriptor {}
+pub trait Descriptor: 'static {
+    /// Offset of queue 0's [`crate::ring::QueueRegs`] for rings of this
+    /// descriptor type.
+    const QUEUE_BASE: usize;
+}
 
 register_bitfields! [
     u64,
@@ -84,6 +88,7 @@ register_bitfields! [
             Data = 0b11,                    // Data descriptor
             Context = 0b10,                 // Context descriptor
         ],
+        MAC_TSTAMP OFFSET(19) NUMBITS(1)[], // IEEE 1588 Timestamp Packet
         CMD_EOP OFFSET(24) NUMBITS(1)[],    // End of Packet
         CMD_IFCS OFFSET(25) NUMBITS(1)[],   // Insert FCS
         CMD_IC OFFSET(26) NUMBITS(1)[],     // Insert Checksum
@@ -114,6 +119,7 @@ pub enum TxAdvDescCmd {
     DEXT,
     VLE,
     IDE,
+    TSTAMP,
 }
 
 /// RSS类型枚举
@@ -178,7 +184,9 @@ pub union AdvTxDesc {
     pub write: AdvTxDescWB,
 }
 
-impl Descriptor for AdvTxDesc {}
+impl Descriptor for AdvTxDesc {
+    const QUEUE_BASE: usize = 0xE000;
+}
 
 impl AdvTxDesc {
     /// 创建新的发送描述符
@@ -207,6 +215,7 @@ impl AdvTxDesc {
                 TxAdvDescCmd::DEXT => cmd_type_len += TX_DESC_CMD_TYPE_LEN::CMD_DEXT::SET,
                 TxAdvDescCmd::VLE => cmd_type_len += TX_DESC_CMD_TYPE_LEN::CMD_VLE::SET,
                 TxAdvDescCmd::IDE => cmd_type_len += TX_DESC_CMD_TYPE_LEN::CMD_IDE::SET,
+                TxAdvDescCmd::TSTAMP => cmd_type_len += TX_DESC_CMD_TYPE_LEN::MAC_TSTAMP::SET,
             }
         }
 
@@ -259,7 +268,9 @@ pub union AdvRxDesc {
     pub write: AdvRxDescWB,
 }
 
-impl Descriptor for AdvRxDesc {}
+impl Descriptor for AdvRxDesc {
+    const QUEUE_BASE: usize = 0xC000;
+}
 
 /// Advanced Receive Descriptor Read Format (软件写入格式)
 ///
@@ -498,6 +509,23 @@ impl AdvRxDescWB {
         unsafe { (self.hi_dword.fields.error_type_status & RX_DESC_EXT_STATUS::VP.mask) != 0 }
     }
 
+    /// 匹配的 EtherType 过滤器编号：包类型第 11 位置位时低 3 位为 ETQF 索引
+    pub fn etype_filter(&self) -> Option<u8> {
+        let status = unsafe { self.hi_dword.fields.error_type_status };
+        let packet_type = RX_DESC_WB_HI_ERROR_STATUS::PKT_TYPE.read(status);
+        (packet_type & (1 << 11) != 0).then_some((packet_type & 0x7) as u8)
+    }
+
+    /// 检查是否只通过了非精确（哈希）地址过滤 (PIF bit)
+    pub fn passed_inexact_filter(&self) -> bool {
+        unsafe { RX_DESC_EXT_STATUS::PIF.is_set(self.hi_dword.fields.error_type_status) }
+    }
+
+    /// 检查是否触发了低延迟中断
+    pub fn is_llint(&self) -> bool {
+        unsafe { RX_DESC_EXT_STATUS::LLINT.is_set(self.hi_dword.fields.error_type_status) }
+    }
+
     /// 检查是否为回环包
     pub fn is_loopback_packet(&self) -> bool {
         unsafe { (self.hi_dword.fields.error_type_status & RX_DESC_EXT_STATUS::LB.mask) != 0 }
@@ -0,0 +1,332 @@
+use core::{mem::size_of, ops::Index};
+
+use alloc::vec::Vec;
+
+use crate::{err::DError, osal::Direction};
+
+#[cfg(not(feature = "dma-api"))]
+use backend_custom as backend;
+#[cfg(feature = "dma-api")]
+use backend_dma_api as backend;
+
+/// 描述符环和数据包缓冲区的 DMA 内存：堆上分配，或调用者提供的静态区域
+pub(crate) struct DmaMem<T: 'static>(backend::Mem<T>);
+
+impl<T> DmaMem<T> {
+    /// Allocate `len` zeroed elements, 4 KB aligned, below `dma_mask`.
+    pub fn zeros(dma_mask: u64, len: usize, direction: Direction) -> Result<Self, DError> {
+        backend::Mem::zeros(dma_mask, len, direction).map(Self)
+    }
+
+    /// Map a heap buffer, which must be reachable below `dma_mask`.
+    pub fn from_vec(dma_mask: u64, value: Vec<T>, direction: Direction) -> Result<Self, DError> {
+        backend::Mem::from_vec(dma_mask, value, direction).map(Self)
+    }
+
+    /// Map a caller supplied static buffer as is.
+    pub fn from_static(value: &'static mut [T], direction: Direction) -> Self {
+        Self(backend::Mem::from_static(value, direction))
+    }
+
+    /// Use the first `len` elements of a caller supplied region, zeroed.
+    ///
+    /// The region must be `align` aligned and large enough.
+    pub fn from_region(
+        region: &'static mut [u8],
+        len: usize,
+        align: usize,
+        direction: Direction,
+    ) -> Result<Self, DError> {
+        let align = align.max(align_of::<T>());
+        if !(region.as_ptr() as usize).is_multiple_of(align) {
+            return Err(DError::Misaligned);
+        }
+        if region.len() < len * size_of::<T>() {
+            return Err(DError::InvalidParameter);
+        }
+        let region = &mut region[..len * size_of::<T>()];
+        region.fill(0);
+        // 描述符都是纯数据，全零是合法值
+        let slice =
+            unsafe { core::slice::from_raw_parts_mut(region.as_mut_ptr().cast::<T>(), len) };
+        Ok(Self::from_static(slice, direction))
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.0.is_static()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn bus_addr(&self) -> u64 {
+        self.0.bus_addr()
+    }
+
+    pub fn set(&mut self, index: usize, value: T) {
+        self.0.set(index, value)
+    }
+}
+
+/// 数据包缓冲区的整体缓存同步；描述符由 `set` 和索引逐个同步
+impl DmaMem<u8> {
+    /// Hand the buffer to the device: write back CPU data for TX, drop stale
+    /// lines for RX so no dirty line is evicted over DMA data.
+    pub fn sync_for_device(&self) {
+        self.0.sync_for_device()
+    }
+
+    /// Take the buffer back from the device before the CPU reads it.
+    pub fn sync_for_cpu(&self) {
+        self.0.sync_for_cpu()
+    }
+}
+
+impl<T> Index<usize> for DmaMem<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.0.index(index)
+    }
+}
+
+impl<T> AsRef<[T]> for DmaMem<T> {
+    fn as_ref(&self) -> &[T] {
+        self.0.as_ref()
+    }
+}
+
+#[cfg(feature = "dma-api")]
+mod backend_dma_api {
+    use super::*;
+    use dma_api::{DSliceMut, DVec};
+
+    fn direction(direction: Direction) -> dma_api::Direction {
+        match direction {
+            Direction::ToDevice => dma_api::Direction::ToDevice,
+            Direction::FromDevice => dma_api::Direction::FromDevice,
+            Direction::Bidirectional => dma_api::Direction::Bidirectional,
+        }
+    }
+
+    pub enum Mem<T: 'static> {
+        Heap(DVec<T>),
+        Static(DSliceMut<'static, T>),
+    }
+
+    impl<T> Mem<T> {
+        pub fn zeros(dma_mask: u64, len: usize, dir: Direction) -> Result<Self, DError> {
+            let vec =
+                DVec::zeros(dma_mask, len, 0x1000, direction(dir)).map_err(|_| DError::NoMemory)?;
+            Ok(Self::Heap(vec))
+        }
+
+        pub fn from_vec(dma_mask: u64, value: Vec<T>, dir: Direction) -> Result<Self, DError> {
+            let vec =
+                DVec::from_vec(dma_mask, value, direction(dir)).map_err(|_| DError::NoMemory)?;
+            Ok(Self::Heap(vec))
+        }
+
+        pub fn from_static(value: &'static mut [T], dir: Direction) -> Self {
+            Self::Static(DSliceMut::from(value, direction(dir)))
+        }
+
+        pub fn is_static(&self) -> bool {
+            matches!(self, Self::Static(_))
+        }
+
+        pub fn len(&self) -> usize {
+            match self {
+                Self::Heap(v) => v.len(),
+                Self::Static(s) => s.len(),
+            }
+        }
+
+        pub fn bus_addr(&self) -> u64 {
+            match self {
+                Self::Heap(v) => v.bus_addr(),
+                Self::Static(s) => s.bus_addr(),
+            }
+        }
+
+        pub fn set(&mut self, index: usize, value: T) {
+            match self {
+                Self::Heap(v) => v.set(index, value),
+                Self::Static(s) => s.set(index, value),
+            }
+        }
+
+        pub fn index(&self, index: usize) -> &T {
+            match self {
+                Self::Heap(v) => &v[index],
+                Self::Static(s) => &s[index],
+            }
+        }
+
+        pub fn as_ref(&self) -> &[T] {
+            match self {
+                Self::Heap(v) => v.as_ref(),
+                Self::Static(s) => s.as_ref(),
+            }
+        }
+    }
+
+    // DSliceMut 的整体同步长度按元素大小又乘了一次，只对 u8 正确
+    impl Mem<u8> {
+        /// dma-api 按方向决定：写回只对 ToDevice/Bidirectional 生效，失效只对
+        /// FromDevice/Bidirectional 生效
+        pub fn sync_for_device(&self) {
+            match self {
+                Self::Heap(v) => {
+                    v.confirm_write_all();
+                    v.prepare_read_all();
+                }
+                Self::Static(s) => {
+                    s.confirm_write_all();
+                    s.prepare_read_all();
+                }
+            }
+        }
+
+        pub fn sync_for_cpu(&self) {
+            match self {
+                Self::Heap(v) => v.prepare_read_all(),
+                Self::Static(s) => s.prepare_read_all(),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "dma-api"))]
+mod backend_custom {
+    use core::{alloc::Layout, ptr::NonNull};
+
+    use super::*;
+    use crate::osal::dma_alloc;
+
+    enum Owner<T> {
+        Alloc(Layout),
+        Vec(Vec<T>),
+        Static,
+    }
+
+    /// 通过用户实现的 [`crate::osal::DmaAlloc`] 分配和同步
+    pub struct Mem<T: 'static> {
+        ptr: NonNull<T>,
+        len: usize,
+        bus_addr: u64,
+        direction: Direction,
+        owner: Owner<T>,
+    }
+
+    impl<T> Mem<T> {
+        fn size(&self) -> usize {
+            self.len * size_of::<T>()
+        }
+
+        fn map(
+            ptr: NonNull<T>,
+            len: usize,
+            dma_mask: u64,
+            direction: Direction,
+            owner: Owner<T>,
+        ) -> Result<Self, DError> {
+            let size = len * size_of::<T>();
+            let bus_addr = dma_alloc::map(ptr.cast(), size, direction);
+            let mem = Self {
+                ptr,
+                len,
+                bus_addr,
+                direction,
+                owner,
+            };
+            if bus_addr.saturating_add(size.max(1) as u64 - 1) & !dma_mask != 0 {
+                return Err(DError::NoMemory);
+            }
+            dma_alloc::sync_for_device(mem.ptr.cast(), size, direction);
+            Ok(mem)
+        }
+
+        pub fn zeros(dma_mask: u64, len: usize, direction: Direction) -> Result<Self, DError> {
+            let layout = Layout::from_size_align(len * size_of::<T>(), 0x1000)
+                .map_err(|_| DError::InvalidParameter)?;
+            let (ptr, bus_addr) = dma_alloc::alloc(dma_mask, layout).ok_or(DError::NoMemory)?;
+            Ok(Self {
+                ptr: ptr.cast(),
+                len,
+                bus_addr,
+                direction,
+                owner: Owner::Alloc(layout),
+            })
+        }
+
+        pub fn from_vec(
+            dma_mask: u64,
+            mut value: Vec<T>,
+            direction: Direction,
+        ) -> Result<Self, DError> {
+            let ptr = NonNull::new(value.as_mut_ptr()).ok_or(DError::InvalidParameter)?;
+            let len = value.len();
+            Self::map(ptr, len, dma_mask, direction, Owner::Vec(value))
+        }
+
+        pub fn from_static(value: &'static mut [T], direction: Direction) -> Self {
+            let ptr = NonNull::new(value.as_mut_ptr()).unwrap();
+            // 静态区域的范围由调用者保证，环和请求会再检查掩码
+            Self::map(ptr, value.len(), u64::MAX, direction, Owner::Static).unwrap()
+        }
+
+        pub fn is_static(&self) -> bool {
+            matches!(self.owner, Owner::Static)
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
+        }
+
+        pub fn bus_addr(&self) -> u64 {
+            self.bus_addr
+        }
+
+        pub fn set(&mut self, index: usize, value: T) {
+            assert!(index < self.len);
+            unsafe {
+                let ptr = self.ptr.add(index);
+                ptr.write_volatile(value);
+                dma_alloc::sync_for_device(ptr.cast(), size_of::<T>(), self.direction);
+            }
+        }
+
+        pub fn sync_for_device(&self) {
+            dma_alloc::sync_for_device(self.ptr.cast(), self.size(), self.direction);
+        }
+
+        pub fn sync_for_cpu(&self) {
+            dma_alloc::sync_for_cpu(self.ptr.cast(), self.size(), self.direction);
+        }
+
+        pub fn index(&self, index: usize) -> &T {
+            assert!(index < self.len);
+            let ptr = unsafe { self.ptr.add(index) };
+            dma_alloc::sync_for_cpu(ptr.cast(), size_of::<T>(), self.direction);
+            unsafe { ptr.as_ref() }
+        }
+
+        pub fn as_ref(&self) -> &[T] {
+            self.sync_for_cpu();
+            unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+        }
+    }
+
+    impl<T> Drop for Mem<T> {
+        fn drop(&mut self) {
+            match &self.owner {
+                Owner::Alloc(layout) => dma_alloc::dealloc(self.ptr.cast(), *layout),
+                // 先解除映射，Vec 随后自行释放
+                Owner::Vec(vec) => dma_alloc::unmap(self.ptr.cast(), size_of_val(vec.as_slice())),
+                Owner::Static => dma_alloc::unmap(self.ptr.cast(), self.size()),
+            }
+        }
+    }
+}
@@ -1,11 +1,32 @@
+use core::time::Duration;
+
 #[derive(Debug, thiserror::Error)]
 pub enum DError {
     #[error("Unknown error occurred: {0}")]
     Unknown(&'static str),
     #[error("Operation timed out")]
     Timeout,
+    /// A wait for the hardware gave up; `op` names what was awaited and
+    /// `elapsed` is the time measured by the kernel clock.
+    #[error("{op} timed out after {elapsed:?}")]
+    TimedOut { op: &'static str, elapsed: Duration },
     #[error("No memory available")]
     NoMemory,
     #[error("Invalid parameter")]
     InvalidParameter,
+    /// The device family lacks the hardware for the requested feature.
+    #[error("Not supported by this device")]
+    Unsupported,
+    #[error("DMA memory is not suitably aligned")]
+    Misaligned,
+    /// Register reads return all ones: no device behind the mapping, or it
+    /// fell off the bus.
+    #[error("No device responds at the register mapping")]
+    NoDevice,
+    /// A TX frame is longer than the ring's MTU allows.
+    #[error("Frame of {len} bytes exceeds the {max}-byte limit")]
+    FrameTooLong { len: usize, max: usize },
+    /// The mapped BAR is smaller than the register space the driver uses.
+    #[error("BAR maps {len:#x} bytes, at least {required:#x} required")]
+    BarTooSmall { len: usize, required: usize },
 }
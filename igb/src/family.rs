@@ -0,0 +1,135 @@
+const RXPBS_TABLE_82580: [u32; 11] = [36, 72, 144, 1, 2, 4, 8, 16, 35, 70, 140];
+
+/// 包缓冲区大小字段的编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PbsEncoding {
+    /// 字段直接是 KB 数
+    Kb { mask: u32 },
+    /// 字段是 `table` 的索引，表项是 KB 数
+    Table { mask: u32, table: &'static [u32] },
+}
+
+impl PbsEncoding {
+    pub fn mask(&self) -> u32 {
+        match *self {
+            Self::Kb { mask } | Self::Table { mask, .. } => mask,
+        }
+    }
+
+    /// 寄存器值换算成 KB，无效编码为 0
+    pub fn decode(&self, reg: u32) -> u32 {
+        let field = reg & self.mask();
+        match *self {
+            Self::Kb { .. } => field,
+            Self::Table { table, .. } => table.get(field as usize).copied().unwrap_or(0),
+        }
+    }
+
+    /// KB 换算成字段值，无法表示时为 None
+    pub fn encode(&self, kb: u32) -> Option<u32> {
+        match *self {
+            Self::Kb { mask } => (kb <= mask).then_some(kb),
+            Self::Table { table, .. } => {
+                table.iter().position(|&size| size == kb).map(|i| i as u32)
+            }
+        }
+    }
+
+    /// 不超过 `kb` 的最大可编码大小
+    pub fn floor(&self, kb: u32) -> u32 {
+        match *self {
+            Self::Kb { mask } => kb.min(mask),
+            Self::Table { table, .. } => table
+                .iter()
+                .copied()
+                .filter(|&size| size <= kb)
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Controller generation, where register layouts or semantics differ.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeviceFamily {
+    #[default]
+    I82576,
+    I350,
+    I210,
+}
+
+impl DeviceFamily {
+    pub fn from_device_id(did: u16) -> Option<Self> {
+        Some(match did {
+            0x10C9 | 0x10E6 | 0x10E7 | 0x10E8 | 0x150A | 0x150D | 0x1518 | 0x1526 => Self::I82576,
+            0x1521..=0x1524 => Self::I350,
+            0x1533 | 0x1536..=0x1539 | 0x157B | 0x157C => Self::I210,
+            _ => return None,
+        })
+    }
+
+    /// Number of receive address (RAL/RAH) entries, RAR0 included.
+    pub fn rar_count(&self) -> usize {
+        match self {
+            Self::I82576 => 24,
+            Self::I350 => 32,
+            Self::I210 => 16,
+        }
+    }
+
+    /// Number of RX and TX queues.
+    pub fn queue_count(&self) -> usize {
+        match self {
+            Self::I82576 => 16,
+            Self::I350 => 8,
+            Self::I210 => 4,
+        }
+    }
+
+    /// Maximum PCIe link width in lanes.
+    pub fn pcie_lanes(&self) -> u8 {
+        match self {
+            Self::I82576 | Self::I350 => 4,
+            Self::I210 => 1,
+        }
+    }
+
+    // 以下是各代寄存器布局的差异，新增型号时只需在这里补充
+
+    /// RXPBS 的 RX 大小字段：82576 是 7 位 KB；I210 是 6 位 KB，高位是 BMC
+    /// 缓冲区；I350 沿用 82580 的 4 位表索引，参考 igb_rxpbs_adjust_82580
+    pub(crate) fn rxpbs_encoding(&self) -> PbsEncoding {
+        match self {
+            Self::I82576 => PbsEncoding::Kb { mask: 0x7F },
+            Self::I350 => PbsEncoding::Table {
+                mask: 0xF,
+                table: &RXPBS_TABLE_82580,
+            },
+            Self::I210 => PbsEncoding::Kb { mask: 0x3F },
+        }
+    }
+
+    /// EITR 的间隔字段和 `us` 微秒对应的值。82575 的格式不同（高 16 位重复），
+    /// 这里支持的型号都是 [14:2]、单位 1 µs
+    pub(crate) fn eitr_interval(&self, us: u32) -> (u32, u32) {
+        match self {
+            Self::I82576 | Self::I350 | Self::I210 => (0x7FFC, (us << 2) & 0x7FFC),
+        }
+    }
+
+    /// 有 802.1Qav 发送整形（TQAVCTRL 等寄存器）
+    pub(crate) fn has_qav(&self) -> bool {
+        match self {
+            Self::I82576 | Self::I350 => false,
+            Self::I210 => true,
+        }
+    }
+
+    /// PCS_CFG0 有 IGN_SD 位，可以忽略 SerDes 信号检测
+    pub(crate) fn has_pcs_ignore_sd(&self) -> bool {
+        match self {
+            Self::I82576 => false,
+            Self::I350 | Self::I210 => true,
+        }
+    }
+}
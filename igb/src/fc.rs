@@ -0,0 +1,151 @@
+use crate::{DError, family::DeviceFamily, mac::Mac, pba};
+
+const FCAL: usize = 0x28; // Flow Control Address Low
+const FCAH: usize = 0x2C; // Flow Control Address High
+const FCT: usize = 0x30; // Flow Control Type
+const FCTTV: usize = 0x170; // Flow Control Transmit Timer Value
+const FCRTL: usize = 0x2160; // Flow Control Receive Threshold Low
+const FCRTH: usize = 0x2168; // Flow Control Receive Threshold High
+const FCRTV: usize = 0x2460; // Flow Control Refresh Threshold Value
+
+// 802.3x PAUSE 帧的目的地址 01:80:C2:00:00:01 和 EtherType
+const FC_ADDR_LOW: u32 = 0x00C2_8001;
+const FC_ADDR_HIGH: u32 = 0x0100;
+const FC_TYPE: u32 = 0x8808;
+
+const FCRTL_XONE: u32 = 1 << 31;
+/// Watermarks are in 16-byte units.
+const WATERMARK_UNIT: u32 = 16;
+const MAX_FRAME: u32 = 1522;
+
+/// IEEE 802.3x flow control, see [`crate::Igb::set_flow_control`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowControl {
+    /// Stop transmitting when the link partner sends XOFF.
+    pub rx_pause: bool,
+    /// Send XOFF when the RX packet buffer fills past `high_water`.
+    pub tx_pause: bool,
+    /// RX packet buffer fill level in bytes that triggers XOFF, `None` for
+    /// the value derived from the packet buffer size.
+    pub high_water: Option<u32>,
+    /// Fill level in bytes below which XON is sent, `None` for
+    /// `high_water - 16`.
+    pub low_water: Option<u32>,
+    /// Pause time in 512-bit-time quanta carried in XOFF frames.
+    pub pause_time: u16,
+    /// Send XON once the buffer drains below `low_water`.
+    pub send_xon: bool,
+}
+
+impl Default for FlowControl {
+    fn default() -> Self {
+        Self {
+            rx_pause: true,
+            tx_pause: true,
+            high_water: None,
+            low_water: None,
+            pause_time: 0xFFFF,
+            send_xon: true,
+        }
+    }
+}
+
+/// Where the PAUSE settings in [`crate::MacStatus`] come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FcSource {
+    /// [`FlowControl`] as configured, the link wasn't auto-negotiated.
+    #[default]
+    Config,
+    /// Configuration limited by what both ends advertised.
+    Autoneg,
+    /// PAUSE is off on a half duplex link.
+    HalfDuplex,
+}
+
+impl FlowControl {
+    /// PAUSE and ASM_DIR bits to advertise during auto-negotiation.
+    ///
+    /// Receive-only can't be advertised as such; both bits are set and
+    /// [`FlowControl::resolve`] drops the TX direction again.
+    pub fn advertisement(&self) -> (bool, bool) {
+        match (self.rx_pause, self.tx_pause) {
+            (false, false) => (false, false),
+            (false, true) => (false, true),
+            (true, _) => (true, true),
+        }
+    }
+
+    /// Directions `(rx_pause, tx_pause)` enabled once a link partner
+    /// advertising `partner_pause` and `partner_asym_pause` accepted
+    /// [`FlowControl::advertisement`].
+    pub fn resolve(&self, partner_pause: bool, partner_asym_pause: bool) -> (bool, bool) {
+        let (rx, tx) = resolve(self.advertisement(), (partner_pause, partner_asym_pause));
+        (rx && self.rx_pause, tx && self.tx_pause)
+    }
+
+    /// 参考 igb_reset：高水位取包缓冲区的 90%，且至少留出两个最大帧
+    fn watermarks(&self, mac: &Mac, family: DeviceFamily) -> Result<(u32, u32), DError> {
+        let pba = pba::read(mac, family).rx_kb * 1024;
+        let default_high = (pba * 9 / 10).min(pba.saturating_sub(2 * MAX_FRAME));
+        let high = self.high_water.unwrap_or(default_high) & !(WATERMARK_UNIT - 1);
+        let low = self
+            .low_water
+            .unwrap_or(high.saturating_sub(WATERMARK_UNIT))
+            & !(WATERMARK_UNIT - 1);
+        if high == 0 || high >= pba || low >= high {
+            return Err(DError::InvalidParameter);
+        }
+        Ok((high, low))
+    }
+}
+
+/// 检查水位能否用于当前的包缓冲区，不写寄存器
+pub(crate) fn validate(mac: &Mac, family: DeviceFamily, fc: &FlowControl) -> Result<(), DError> {
+    fc.watermarks(mac, family).map(drop)
+}
+
+pub(crate) fn apply(mac: &mut Mac, family: DeviceFamily, fc: &FlowControl) -> Result<(), DError> {
+    let (high, low) = fc.watermarks(mac, family)?;
+
+    mac.write_reg(FCAL, FC_ADDR_LOW);
+    mac.write_reg(FCAH, FC_ADDR_HIGH);
+    mac.write_reg(FCT, FC_TYPE);
+    mac.write_reg(FCTTV, fc.pause_time as u32);
+    // 暂停时间过半时刷新 XOFF
+    mac.write_reg(FCRTV, fc.pause_time as u32 / 2);
+
+    // 先写低水位，避免短暂出现低水位高于高水位
+    if fc.tx_pause {
+        let xone = if fc.send_xon { FCRTL_XONE } else { 0 };
+        mac.write_reg(FCRTL, low | xone);
+        mac.write_reg(FCRTH, high);
+    } else {
+        mac.write_reg(FCRTL, 0);
+        mac.write_reg(FCRTH, 0);
+    }
+
+    mac.set_flow_control(fc.rx_pause, fc.tx_pause);
+    Ok(())
+}
+
+/// IEEE 802.3 Annex 28B 的协商结果 (rx_pause, tx_pause)，参考 e1000_config_fc_after_link_up
+pub(crate) fn resolve(local: (bool, bool), partner: (bool, bool)) -> (bool, bool) {
+    match (local.0, local.1, partner.0, partner.1) {
+        (true, _, true, _) => (true, true),
+        // 只有我们发 PAUSE，对端响应
+        (false, true, true, true) => (false, true),
+        // 只响应对端的 PAUSE
+        (true, true, false, true) => (true, false),
+        _ => (false, false),
+    }
+}
+
+/// A PAUSE frame with pause time 0 resumes the link partner immediately.
+pub(crate) fn send_xon(mac: &mut Mac) -> Result<(), DError> {
+    let pause_time = mac.read_reg(FCTTV);
+    mac.write_reg(FCTTV, 0);
+    let result = mac.send_xoff();
+    mac.write_reg(FCTTV, pause_time);
+    result
+}
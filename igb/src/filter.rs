@@ -0,0 +1,174 @@
+use core::net::{Ipv4Addr, Ipv6Addr};
+
+use alloc::vec::Vec;
+
+use crate::{DError, mac::Mac};
+
+const WUC: usize = 0x5800; // Wake Up Control
+const WUFC: usize = 0x5808; // Wake Up Filter Control
+const IP4AT: usize = 0x5840; // IPv4 Address Table, 8 bytes per entry
+const IP6AT: usize = 0x5880; // IPv6 Address Table
+const PROXYFC: usize = 0x5F60; // Proxying Filter Control (I350/I210)
+const FHFT: usize = 0x9000; // Flexible Host Filter Table, 0x100 per filter
+
+const WUC_PME_EN: u32 = 1 << 1;
+const WUFC_FLEX_HQ: u32 = 1 << 14; // 匹配的帧在 D0 下按 FHFT 中的队列接收
+const WUFC_FLX0: u32 = 1 << 16;
+
+const PROXYFC_D0: u32 = 1 << 0; // Proxy also while in D0
+const PROXYFC_ARP_DIRECTED: u32 = 1 << 4;
+const PROXYFC_NS: u32 = 1 << 9;
+const PROXYFC_NS_DIRECTED: u32 = 1 << 10;
+const PROXYFC_ARP: u32 = 1 << 11;
+
+/// Number of IPv6 addresses the NIC can answer Neighbor Solicitations for.
+pub const PROXY_IPV6_TARGETS: usize = 1;
+
+/// Number of flexible filters.
+pub const FLEX_FILTER_COUNT: usize = 4;
+/// Number of frame bytes a flexible filter can look at.
+pub const FLEX_FILTER_LEN: usize = 128;
+
+const FHFT_LENGTH_OFFSET: usize = 0xFC;
+const FHFT_QUEUE_SHIFT: u32 = 8;
+const FHFT_QUEUE_MASK: u32 = 0x7;
+
+/// Program flexible filter `index` and enable it as a wake/proxy filter.
+///
+/// Byte `offsets[i]` of the frame is compared with `pattern[i]` when
+/// `masks[i]` is `0xFF` and ignored when it is `0x00`; the hardware compares
+/// whole bytes, so other mask values are rejected.
+pub(crate) fn write_flex_filter(
+    mac: &mut Mac,
+    index: usize,
+    offsets: &[usize],
+    masks: &[u8],
+    pattern: &[u8],
+) -> Result<(), DError> {
+    if index >= FLEX_FILTER_COUNT
+        || offsets.is_empty()
+        || offsets.len() != masks.len()
+        || offsets.len() != pattern.len()
+    {
+        return Err(DError::InvalidParameter);
+    }
+
+    let mut bytes = [0u8; FLEX_FILTER_LEN];
+    let mut enable = [0u8; FLEX_FILTER_LEN / 8];
+    let mut len = 0;
+    for ((&offset, &mask), &value) in offsets.iter().zip(masks).zip(pattern) {
+        if offset >= FLEX_FILTER_LEN || !matches!(mask, 0x00 | 0xFF) {
+            return Err(DError::InvalidParameter);
+        }
+        if mask == 0 {
+            continue;
+        }
+        bytes[offset] = value;
+        enable[offset / 8] |= 1 << (offset % 8);
+        len = len.max(offset + 1);
+    }
+
+    // 写表期间先关闭该过滤器
+    let wufc = mac.read_reg(WUFC);
+    mac.write_reg(WUFC, wufc & !(WUFC_FLX0 << index));
+
+    // 每行 16 字节：两个 DW 的模式字节，一个 DW 的字节使能，一个保留 DW
+    let base = FHFT + index * 0x100;
+    let (rows, _) = bytes.as_chunks::<8>();
+    for (row, chunk) in rows.iter().enumerate() {
+        let (lo, hi) = chunk.split_at(4);
+        let row_base = base + row * 16;
+        mac.write_reg(row_base, u32::from_le_bytes(lo.try_into().unwrap()));
+        mac.write_reg(row_base + 4, u32::from_le_bytes(hi.try_into().unwrap()));
+        mac.write_reg(row_base + 8, enable[row] as u32);
+    }
+    mac.write_reg(base + FHFT_LENGTH_OFFSET, len as u32);
+
+    mac.write_reg(WUFC, wufc | (WUFC_FLX0 << index));
+    let wuc = mac.read_reg(WUC);
+    mac.write_reg(WUC, wuc | WUC_PME_EN);
+    Ok(())
+}
+
+/// Disable flexible filter `index`.
+pub(crate) fn disable_flex_filter(mac: &mut Mac, index: usize) -> Result<(), DError> {
+    if index >= FLEX_FILTER_COUNT {
+        return Err(DError::InvalidParameter);
+    }
+    let wufc = mac.read_reg(WUFC);
+    mac.write_reg(WUFC, wufc & !(WUFC_FLX0 << index));
+    Ok(())
+}
+
+/// Receive frames matching flexible filter `index` on `queue`.
+///
+/// `host_queueing` enables queue assignment for all flexible filters;
+/// filters without a queue of their own use queue 0.
+pub(crate) fn set_flex_filter_queue(
+    mac: &mut Mac,
+    index: usize,
+    queue: Option<u8>,
+    host_queueing: bool,
+) -> Result<(), DError> {
+    if index >= FLEX_FILTER_COUNT || queue.is_some_and(|q| q as u32 > FHFT_QUEUE_MASK) {
+        return Err(DError::InvalidParameter);
+    }
+    let reg = FHFT + index * 0x100 + FHFT_LENGTH_OFFSET;
+    let length = mac.read_reg(reg) & !(FHFT_QUEUE_MASK << FHFT_QUEUE_SHIFT);
+    mac.write_reg(
+        reg,
+        length | (queue.unwrap_or(0) as u32) << FHFT_QUEUE_SHIFT,
+    );
+
+    let wufc = mac.read_reg(WUFC) & !WUFC_FLEX_HQ;
+    let hq = if host_queueing { WUFC_FLEX_HQ } else { 0 };
+    mac.write_reg(WUFC, wufc | hq);
+    Ok(())
+}
+
+/// Whether any wake-up filter is enabled, so the link must stay up in D3.
+pub(crate) fn wake_enabled(mac: &Mac) -> bool {
+    mac.read_reg(WUFC) & !WUFC_FLEX_HQ != 0
+}
+
+/// ARP / IPv6 Neighbor Discovery proxy offload configuration (I350/I210).
+///
+/// While proxying is active the NIC answers ARP requests for `ipv4` and
+/// Neighbor Solicitations for `ipv6_targets` on behalf of a sleeping host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProxyConfig {
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6_targets: Vec<Ipv6Addr>,
+    /// Also proxy while the device is in D0 instead of only in D3.
+    pub in_d0: bool,
+}
+
+pub(crate) fn write_proxy(mac: &mut Mac, config: &ProxyConfig) -> Result<(), DError> {
+    if config.ipv6_targets.len() > PROXY_IPV6_TARGETS {
+        return Err(DError::InvalidParameter);
+    }
+
+    // 先关闭代理再更新地址表
+    mac.write_reg(PROXYFC, 0);
+
+    let mut proxyfc = 0;
+    if let Some(ip) = config.ipv4 {
+        mac.write_reg(IP4AT, u32::from_le_bytes(ip.octets()));
+        proxyfc |= PROXYFC_ARP | PROXYFC_ARP_DIRECTED;
+    }
+    for ip in &config.ipv6_targets {
+        let octets = ip.octets();
+        let (dwords, _) = octets.as_chunks::<4>();
+        for (i, dw) in dwords.iter().enumerate() {
+            mac.write_reg(IP6AT + i * 4, u32::from_le_bytes(*dw));
+        }
+        proxyfc |= PROXYFC_NS | PROXYFC_NS_DIRECTED;
+    }
+    if proxyfc != 0 && config.in_d0 {
+        proxyfc |= PROXYFC_D0;
+    }
+
+    mac.write_reg(PROXYFC, proxyfc);
+    Ok(())
+}
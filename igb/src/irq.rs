@@ -0,0 +1,123 @@
+use crate::mac::{IrqMsg, LegacyIrqMsg};
+
+/// Maximum number of MSI-X vectors on 82576.
+pub const MAX_MSIX_VECTORS: u8 = 25;
+
+/// How the device signals interrupts to the host.
+///
+/// Select the mode with [`Igb::set_irq_mode`](crate::Igb::set_irq_mode)
+/// before [`Igb::open`](crate::Igb::open); it must match what the OS has
+/// enabled in the PCI configuration space.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IrqMode {
+    /// Legacy INTx: causes are read from ICR.
+    Legacy,
+    /// Single-vector MSI: causes are read from EICR in non-MSI-X layout.
+    #[default]
+    Msi,
+    /// MSI-X with `vectors` vectors. Queue `n` uses vector `n`, the last
+    /// vector carries link and other causes.
+    MsiX { vectors: u8 },
+}
+
+/// How EICR cause bits are acknowledged, see
+/// [`Igb::irq_ack_policy`](crate::Igb::irq_ack_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqAckPolicy {
+    /// Reading EICR clears it (GPIE.NSICR set), used in legacy and MSI mode.
+    ClearOnRead,
+    /// Reading EICR only clears it while the interrupt is asserted
+    /// (GPIE.NSICR clear); the driver writes the bits back as well.
+    ClearOnReadWhenAsserted,
+    /// EICR bits are cleared by writing 1s or by EIAC auto-clear, used in
+    /// MSI-X mode.
+    WriteToClear,
+}
+
+/// Decoded interrupt causes, independent of the interrupt mode in use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IrqEvents {
+    /// Bit `n` is set when queue `n` has RX or TX activity.
+    pub queues: u16,
+    /// Link status changed.
+    pub link_changed: bool,
+    /// TCP timer expired.
+    pub tcp_timer: bool,
+    /// The RX packet buffer overflowed and frames were dropped.
+    pub rx_overrun: bool,
+    /// A receive queue ran below its free descriptor minimum threshold.
+    pub rx_desc_low: bool,
+    /// Receive sequence error, i.e. a framing error on the link.
+    pub rx_seq_error: bool,
+    /// An MDIO transaction completed.
+    pub mdio_done: bool,
+    /// Bit `n` is set when PTP target time `n` was reached.
+    pub target_time: u8,
+    /// Any other cause not decoded above.
+    pub other: bool,
+    /// Too many spurious interrupts arrived in one stats interval and the
+    /// device's interrupts were masked, see
+    /// [`Igb::set_irq_storm_limit`](crate::Igb::set_irq_storm_limit).
+    pub storm: bool,
+}
+
+/// What [`Igb::handle_vector_interrupt`](crate::Igb::handle_vector_interrupt)
+/// found on one MSI-X vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorEvent {
+    /// The rings of queue `n` were woken.
+    Queue(u8),
+    /// The vector carrying link and other non-queue causes.
+    Other(IrqEvents),
+    /// The vector is not in use in the current interrupt mode.
+    Spurious,
+}
+
+/// What an interrupt vector carries, see [`Igb::vectors`](crate::Igb::vectors).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorCause {
+    /// RX and TX of queue `n`.
+    Queue(u8),
+    /// Link and the other non-queue causes (the last MSI-X vector).
+    Other,
+    /// Every cause, in legacy and MSI mode.
+    All,
+}
+
+impl From<IrqMsg> for IrqEvents {
+    fn from(msg: IrqMsg) -> Self {
+        Self {
+            queues: msg.queue_idx,
+            link_changed: msg.lsc,
+            tcp_timer: msg.tcp_timer,
+            rx_overrun: msg.rxo,
+            rx_desc_low: msg.rxdmt0,
+            rx_seq_error: msg.rxseq,
+            mdio_done: msg.mdac,
+            target_time: 0,
+            other: msg.other
+                && !(msg.lsc || msg.mdac || msg.rxo || msg.rxdmt0 || msg.rxseq || msg.time_sync),
+            storm: false,
+        }
+    }
+}
+
+impl From<LegacyIrqMsg> for IrqEvents {
+    fn from(msg: LegacyIrqMsg) -> Self {
+        // 传统模式下只有队列 0 的中断原因
+        let queue0 = msg.rxt0 || msg.rxdmt0 || msg.rxo || msg.txdw || msg.txqe;
+        Self {
+            queues: queue0 as u16,
+            link_changed: msg.lsc,
+            tcp_timer: false,
+            rx_overrun: msg.rxo,
+            rx_desc_low: msg.rxdmt0,
+            rx_seq_error: msg.rxseq,
+            mdio_done: msg.mdac,
+            target_time: 0,
+            other: msg.rxcfg,
+            storm: false,
+        }
+    }
+}
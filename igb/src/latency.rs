@@ -0,0 +1,61 @@
+use crate::{family::DeviceFamily, irq::IrqMode, mac::Mac, ring::DescThresholds};
+
+const EITR: usize = 0x1680; // Extended Interrupt Throttle, one per vector
+const RDTR: usize = 0x2820; // RX Delay Timer
+const EITR_CNT_IGNR: u32 = 1 << 31;
+
+/// A consistent set of RX write-back and interrupt moderation settings,
+/// see [`crate::Igb::set_rx_latency_profile`] and
+/// [`crate::RxRing::set_latency_profile`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RxLatencyProfile {
+    /// Write back every descriptor and interrupt for every packet.
+    LowLatency,
+    /// Write back every descriptor, at most 20000 interrupts per second.
+    #[default]
+    Balanced,
+    /// Write back descriptors in groups of four, at most 5000 interrupts
+    /// per second.
+    Throughput,
+}
+
+impl RxLatencyProfile {
+    /// RXDCTL thresholds of the profile, e.g. for [`crate::RingConfig::rx_thresholds`].
+    pub fn rx_thresholds(self) -> DescThresholds {
+        let writeback = match self {
+            Self::LowLatency | Self::Balanced => 1,
+            Self::Throughput => 4,
+        };
+        DescThresholds {
+            writeback,
+            ..DescThresholds::RX_DEFAULT
+        }
+    }
+
+    /// Minimum gap between interrupts of one vector (EITR), in µs.
+    pub fn irq_interval_us(self) -> u32 {
+        match self {
+            Self::LowLatency => 0,
+            Self::Balanced => 50,
+            Self::Throughput => 200,
+        }
+    }
+}
+
+/// 写入所用中断向量的 EITR 间隔，保留 LLI_EN 等其它位
+pub(crate) fn apply(mac: &mut Mac, family: DeviceFamily, mode: IrqMode, profile: RxLatencyProfile) {
+    let vectors = match mode {
+        IrqMode::MsiX { vectors } => vectors as usize,
+        IrqMode::Legacy | IrqMode::Msi => 1,
+    };
+    let (mask, interval) = family.eitr_interval(profile.irq_interval_us());
+    for vector in 0..vectors {
+        let reg = EITR + vector * 4;
+        let eitr = mac.read_reg(reg) & !mask;
+        // 写间隔时不要清零正在运行的计数器
+        mac.write_reg(reg, eitr | interval | EITR_CNT_IGNR);
+    }
+    // RDTR 只对传统描述符的写回计时，保持 0 以免再推迟写回
+    mac.write_reg(RDTR, 0);
+}
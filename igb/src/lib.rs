@@ -1,126 +1,1042 @@
 #![no_std]
 
-use core::{ops::Deref, ptr::NonNull};
+use core::{
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::Duration,
+};
 
-use alloc::vec::Vec;
-use dma_api::{DVec, Direction};
-use log::debug;
-pub use mac::{MacAddr6, MacStatus};
+use alloc::{sync::Arc, vec::Vec};
+pub use caps::DeviceCaps;
+pub use config::IgbConfig;
+use dma::DmaMem;
+pub use fc::{FcSource, FlowControl};
+pub use filter::{FLEX_FILTER_COUNT, FLEX_FILTER_LEN, PROXY_IPV6_TARGETS, ProxyConfig};
+use futures::task::AtomicWaker;
+pub use irq::{IrqAckPolicy, IrqEvents, IrqMode, VectorCause, VectorEvent};
+pub use latency::RxLatencyProfile;
+pub use lli::{LLI_FILTER_COUNT, LLI_MAX_INTERVAL, LliFilter};
+use log::{debug, warn};
+pub use loopback::LoopbackMode;
+pub use mac::{
+    LinkEvent, MAC_FILTER_COUNT, MacAddr6, MacStatus, ManageabilityMode, ManageabilityStatus,
+    REG_SPAN,
+};
+pub use nvm::{FwVersion, PartInfo};
+use osal::Direction;
+pub use pba::PacketBuffers;
+pub use pcie::{PcieLink, PcieTuning, serial_from_dsn_capability};
+pub use pcs::SerdesStatus;
+pub use phy::{LinkPartnerInfo, LinkSpeeds, MasterSlave, MdioRead, PowerPolicy};
+pub use ptp::{TARGET_TIME_COUNT, max_adjust_ppb};
 pub use trait_ffi::impl_extern_trait;
 
 pub use crate::err::DError;
-use crate::ring::DEFAULT_RING_SIZE;
+pub use crate::family::DeviceFamily;
 
 extern crate alloc;
 
+#[macro_use]
+mod telemetry;
+mod caps;
+mod config;
 mod err;
+mod family;
+mod fc;
+mod filter;
+mod irq;
+mod latency;
+mod lli;
+mod loopback;
 mod mac;
+mod nvm;
 #[macro_use]
 pub mod osal;
 mod descriptor;
+mod dma;
+mod pba;
+mod pcie;
+mod pcs;
 mod phy;
+mod ptp;
 mod ring;
+mod sched;
+mod sdp;
+mod stats;
+mod steer;
+mod storm;
 
 pub use futures::{Stream, StreamExt};
-pub use ring::{RxPacket, RxRing, TxRing};
+pub use ring::{
+    DescThresholds, HeaderMode, HeaderSplit, MAX_TX_WAITERS, NapiLike, OneBuffer, QueuePair,
+    RefillPolicy, RingConfig, RxLayout, RxMeta, RxPacket, RxRing, TxCompletion, TxRing,
+    dma_bit_mask, forward,
+};
+pub use sched::{SHAPED_QUEUES, TxScheduling};
+pub use sdp::{SDP_COUNT, Sdp, SdpFunction};
+pub use stats::{HwStats, QUEUE_STATS_COUNT, QueueStats, RingStats, STATS_POLL_INTERVAL};
+pub use steer::{ETYPE_FILTER_COUNT, EtherTypeFilter, TUPLE_FILTER_COUNT, TupleFilter};
+pub use storm::{StormAction, StormEvents, StormPolicy};
+#[cfg(feature = "telemetry")]
+pub use telemetry::Telemetry;
 
 pub struct Request {
-    buff: DVec<u8>,
+    buff: DmaMem<u8>,
+    /// 发送时使用的长度，默认是整个缓冲区
+    len: usize,
+    /// 映射方向，转发要求 Bidirectional
+    dir: Direction,
 }
 
 impl Request {
-    fn new(buff: Vec<u8>, dir: Direction) -> Self {
-        let buff = DVec::from_vec(u64::MAX, buff, dir).unwrap();
-        Self { buff }
+    fn from_mem(buff: DmaMem<u8>, dir: Direction) -> Self {
+        let len = buff.len();
+        Self { buff, len, dir }
+    }
+
+    fn new(buff: Vec<u8>, dir: Direction, dma_mask: u64) -> Result<Self, DError> {
+        let buff = DmaMem::from_vec(dma_mask, buff, dir)?;
+        Ok(Self::from_mem(buff, dir))
     }
     pub fn new_rx(buff: Vec<u8>) -> Self {
-        Self::new(buff, Direction::FromDevice)
+        Self::new(buff, Direction::FromDevice, u64::MAX).unwrap()
     }
 
     pub fn new_tx(buff: Vec<u8>) -> Self {
-        Self::new(buff, Direction::ToDevice)
+        Self::new(buff, Direction::ToDevice, u64::MAX).unwrap()
+    }
+
+    /// Map an RX buffer that must be reachable below `dma_mask`, see
+    /// [`RingConfig::dma_mask`].
+    pub fn new_rx_with_mask(buff: Vec<u8>, dma_mask: u64) -> Result<Self, DError> {
+        Self::new(buff, Direction::FromDevice, dma_mask)
+    }
+
+    /// Map a TX buffer that must be reachable below `dma_mask`, see
+    /// [`RingConfig::dma_mask`].
+    pub fn new_tx_with_mask(buff: Vec<u8>, dma_mask: u64) -> Result<Self, DError> {
+        Self::new(buff, Direction::ToDevice, dma_mask)
+    }
+
+    /// Map a buffer for both directions, so it can be received into and then
+    /// transmitted as is with [`forward`].
+    pub fn new_forward(buff: Vec<u8>) -> Self {
+        Self::new(buff, Direction::Bidirectional, u64::MAX).unwrap()
+    }
+
+    /// [`Request::new_forward`] for a buffer that must be reachable below
+    /// `dma_mask`, see [`RingConfig::dma_mask`].
+    pub fn new_forward_with_mask(buff: Vec<u8>, dma_mask: u64) -> Result<Self, DError> {
+        Self::new(buff, Direction::Bidirectional, dma_mask)
+    }
+
+    /// Use a caller supplied static buffer for RX, without heap allocation.
+    pub fn new_rx_in(buff: &'static mut [u8]) -> Self {
+        Self::from_mem(
+            DmaMem::from_static(buff, Direction::FromDevice),
+            Direction::FromDevice,
+        )
+    }
+
+    /// Use a caller supplied static buffer for TX, without heap allocation.
+    ///
+    /// The whole buffer is sent unless limited with [`Request::set_len`].
+    pub fn new_tx_in(buff: &'static mut [u8]) -> Self {
+        Self::from_mem(
+            DmaMem::from_static(buff, Direction::ToDevice),
+            Direction::ToDevice,
+        )
+    }
+
+    /// Use a caller supplied static buffer for [`forward`], without
+    /// heap allocation.
+    pub fn new_forward_in(buff: &'static mut [u8]) -> Self {
+        Self::from_mem(
+            DmaMem::from_static(buff, Direction::Bidirectional),
+            Direction::Bidirectional,
+        )
+    }
+
+    /// Whether the buffer is mapped for both directions and may be passed to
+    /// [`forward`].
+    pub fn is_forwardable(&self) -> bool {
+        self.dir == Direction::Bidirectional
     }
 
     pub fn bus_addr(&self) -> u64 {
         self.buff.bus_addr()
     }
+
+    /// Only use the first `len` bytes, e.g. to transmit a received frame
+    /// from its RX buffer. RX rings always reset this to the whole buffer.
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len.min(self.buff.len());
+    }
+
+    /// Size of the underlying DMA buffer.
+    pub fn capacity(&self) -> usize {
+        self.buff.len()
+    }
 }
 
 impl Deref for Request {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        self.buff.as_ref()
+        &self.buff.as_ref()[..self.len]
     }
 }
 
 pub struct Igb {
     mac: mac::Mac,
     phy: phy::Phy,
-    _rx_ring_addrs: [usize; 16],
-    _tx_ring_addrs: [usize; 16],
+    rx_wakers: [Option<Arc<AtomicWaker>>; 16],
+    rx_low_wakers: [Option<Arc<AtomicWaker>>; 16],
+    /// 在 RX 中断里按阈值补充描述符
+    rx_refills: [Option<ring::RxRefill>; 16],
+    tx_wakers: [Option<Arc<AtomicWaker>>; 16],
+    tx_waiters: [Option<Arc<ring::WaitList>>; 16],
+    /// 环上 disable_irq 屏蔽的向量
+    masked_vectors: Arc<AtomicU32>,
+    /// irq_mode 为 Legacy，环据此选择 IMS 或 EIMS 屏蔽自己的中断
+    legacy_irq: Arc<AtomicBool>,
+    irq_mode: IrqMode,
+    /// apply_irq_mode 之后 GPIE 决定的 EICR 清除方式
+    irq_ack: IrqAckPolicy,
+    queue_counters: [stats::QueueCounters; QUEUE_STATS_COUNT],
+    hw_counters: stats::HwCounters,
+    flex_filters: u8,
+    flex_queues: [Option<u8>; FLEX_FILTER_COUNT],
+    /// 软件保存的 RAR1.. 过滤地址，复位后重新写入
+    mac_filters: Vec<Option<MacAddr6>>,
+    /// set_mac_address 设置的地址，复位后代替 NVM 中的地址
+    mac_addr: Option<MacAddr6>,
+    /// open 复位后 RAR0 中 NVM 的地址
+    perm_addr: Option<MacAddr6>,
+    family: DeviceFamily,
+    /// NVM 中的设备 ID，读取失败时为 None
+    device_id: Option<u16>,
+    smart_speed: bool,
+    downshifted: bool,
+    /// 上次看到的速率和双工，None 表示链路断开
+    link: Option<(Speed, bool)>,
+    open_tracer: Option<fn(OpenPhase)>,
+    mdio_waker: Arc<AtomicWaker>,
+    rx_overruns: u64,
+    link_errors: u64,
+    spurious_irqs: u64,
+    /// 本次 stats_tick 间隔内的无原因中断数
+    spurious_window: u32,
+    irq_storm_limit: Option<u32>,
+    refill_on_rx_pressure: bool,
+    rx_pause_frames: bool,
+    rx_mac_control_frames: bool,
+    flow_control: FlowControl,
+    fc_source: FcSource,
+    lli_filters: [Option<LliFilter>; LLI_FILTER_COUNT],
+    lli_vlan_priority: Option<u8>,
+    lli_moderation: Option<u8>,
+    rx_latency: Option<RxLatencyProfile>,
+    etype_filters: [Option<EtherTypeFilter>; ETYPE_FILTER_COUNT],
+    tuple_filters: [Option<TupleFilter>; TUPLE_FILTER_COUNT],
+    loopback: LoopbackMode,
+    power_policy: PowerPolicy,
+    /// 链路持续断开的时间，由 power_watchdog 累计
+    link_down_for: Duration,
+    /// 已打开 PHY 的 Smart Power Down
+    energy_detect: bool,
+    /// suspend 之前的 (D0, D3) LPLU 设置，下次 open 时恢复
+    saved_lplu: Option<(bool, bool)>,
+    storm: storm::StormControl,
+    storm_callback: Option<fn(StormEvents)>,
+    tx_sched: TxScheduling,
+    packet_buffers: Option<PacketBuffers>,
+    /// 首次 open 时读到的默认包缓冲区划分
+    default_pb: Option<PacketBuffers>,
+    /// 已创建的环中最大的帧长
+    max_frame: usize,
+    /// 映射的寄存器空间长度，raw 访问的边界
+    #[cfg(feature = "raw-regs")]
+    bar_len: usize,
+    #[cfg(feature = "telemetry")]
+    telemetry: Telemetry,
 }
 
 impl Igb {
+    /// Like [`Igb::new`], but first check that the `bar_len` bytes mapped at
+    /// `iobase` cover the whole register space, catching a wrong BAR before
+    /// it faults.
+    pub fn new_with_bar(iobase: NonNull<u8>, bar_len: usize) -> Result<Self, DError> {
+        if bar_len < mac::REG_SPAN {
+            return Err(DError::BarTooSmall {
+                len: bar_len,
+                required: mac::REG_SPAN,
+            });
+        }
+        let igb = Self::new(iobase)?;
+        #[cfg(feature = "raw-regs")]
+        let igb = Self { bar_len, ..igb };
+        Ok(igb)
+    }
+
+    /// Bind to the registers mapped at `iobase`, failing with
+    /// [`DError::NoDevice`] if nothing answers there. Prefer
+    /// [`Igb::new_with_bar`] when the BAR size is known.
+    ///
+    /// The [`DeviceFamily`] is detected from the device ID in the NVM.
     pub fn new(iobase: NonNull<u8>) -> Result<Self, DError> {
-        let mac = mac::Mac::new(iobase);
+        let mut mac = mac::Mac::new(iobase);
+        mac.probe()?;
         let phy = phy::Phy::new(mac);
+        let device_id = nvm::read_word(&mut mac, nvm::WORD_DEVICE_ID)
+            .inspect_err(|e| warn!("NVM read failed: {e:?}"))
+            .ok();
+        let family = match device_id.map(|did| (did, DeviceFamily::from_device_id(did))) {
+            Some((_, Some(family))) => family,
+            Some((did, None)) => {
+                warn!(
+                    "unknown device ID {did:#06x}, assuming {:?}",
+                    DeviceFamily::default()
+                );
+                DeviceFamily::default()
+            }
+            None => DeviceFamily::default(),
+        };
 
         Ok(Self {
             mac,
             phy,
-            _rx_ring_addrs: [0; 16],
-            _tx_ring_addrs: [0; 16],
+            rx_wakers: [const { None }; 16],
+            rx_low_wakers: [const { None }; 16],
+            rx_refills: [const { None }; 16],
+            tx_wakers: [const { None }; 16],
+            tx_waiters: [const { None }; 16],
+            masked_vectors: Arc::new(AtomicU32::new(0)),
+            legacy_irq: Arc::new(AtomicBool::new(IrqMode::default() == IrqMode::Legacy)),
+            irq_mode: IrqMode::default(),
+            irq_ack: IrqAckPolicy::ClearOnRead,
+            queue_counters: Default::default(),
+            hw_counters: Default::default(),
+            flex_filters: 0,
+            flex_queues: [None; FLEX_FILTER_COUNT],
+            mac_filters: Vec::new(),
+            mac_addr: None,
+            perm_addr: None,
+            family,
+            device_id,
+            smart_speed: true,
+            downshifted: false,
+            link: None,
+            open_tracer: None,
+            mdio_waker: Arc::new(AtomicWaker::new()),
+            rx_overruns: 0,
+            link_errors: 0,
+            spurious_irqs: 0,
+            spurious_window: 0,
+            irq_storm_limit: None,
+            refill_on_rx_pressure: true,
+            rx_pause_frames: false,
+            rx_mac_control_frames: false,
+            flow_control: FlowControl::default(),
+            fc_source: FcSource::default(),
+            lli_filters: [None; LLI_FILTER_COUNT],
+            lli_vlan_priority: None,
+            lli_moderation: None,
+            rx_latency: None,
+            etype_filters: [None; ETYPE_FILTER_COUNT],
+            tuple_filters: [None; TUPLE_FILTER_COUNT],
+            loopback: LoopbackMode::None,
+            power_policy: PowerPolicy::AlwaysOn,
+            link_down_for: Duration::ZERO,
+            energy_detect: false,
+            saved_lplu: None,
+            storm: Default::default(),
+            storm_callback: None,
+            tx_sched: TxScheduling::RoundRobin,
+            packet_buffers: None,
+            default_pb: None,
+            max_frame: 0,
+            #[cfg(feature = "raw-regs")]
+            bar_len: mac::REG_SPAN,
+            #[cfg(feature = "telemetry")]
+            telemetry: Telemetry::default(),
         })
     }
 
     pub fn open(&mut self) -> Result<(), DError> {
         self.mac.disable_interrupts();
+        self.mac.disable_legacy_interrupts();
 
-        self.mac.reset()?;
+        {
+            // 复位同时会复位 PHY，需要先从固件拿到 PHY 的所有权
+            let _sync = phy::Synced::new(self.mac, mac::SW_FW_SYNC::SW_PHY_SM0::SET)?;
+            // 复位前必须等待 DMA 停止，超时也继续复位
+            if let Err(e) = self.quiesce_dma() {
+                debug!("PCIe master disable timeout: {e:?}");
+            }
+            self.mac.reset()?;
+        }
+
+        // 复位后 NVM 会异步重新加载 RAR0，读 MAC 之前需要等待
+        if self.mac.wait_auto_read().is_err() {
+            warn!("NVM auto-read not done after reset");
+        }
+        self.perm_addr = Some(self.mac.init_rar0());
+        if let Some(addr) = self.mac_addr {
+            self.mac.write_rar(0, addr.bytes(), true);
+        }
+        self.restore_mac_filters();
 
         self.mac.disable_interrupts();
+        self.mac.disable_legacy_interrupts();
+        // 复位会清除 GPIE，需要重新配置
+        self.apply_irq_mode();
+        self.restore_lli();
+        self.restore_queue_filters();
+        // 会改变包缓冲区大小，须在流控计算水位之前
+        let default_pb = *self
+            .default_pb
+            .get_or_insert_with(|| pba::read(&self.mac, self.family));
+        sched::apply(&mut self.mac, self.family, &self.tx_sched);
+        if self.tx_sched == TxScheduling::RoundRobin {
+            let pb = match self.packet_buffers {
+                // 首次 open 之前设置的划分还没和默认总量比较过
+                Some(pb) if pba::validate(self.family, &pb, Some(&default_pb)).is_ok() => pb,
+                Some(pb) => {
+                    warn!("packet buffer split {pb:?} exceeds {default_pb:?}, using the default");
+                    default_pb
+                }
+                // 巨帧放不下时按 datasheet 给 TX 留出两个帧
+                None if !default_pb.fits(self.max_frame) => {
+                    PacketBuffers::for_max_frame(self.family, &default_pb, self.max_frame)
+                }
+                None => default_pb,
+            };
+            pba::apply(&mut self.mac, self.family, &pb);
+        }
+
+        self.mac.init_manageability();
+        self.mac.set_driver_loaded(true);
 
         debug!("reset done");
+        self.trace_open(OpenPhase::ResetDone);
 
         let link_mode = self.mac.link_mode().unwrap();
         debug!("link mode: {link_mode:?}");
         self.phy.power_up()?;
 
         self.setup_phy_and_the_link()?;
+        self.trace_open(OpenPhase::PhyUp);
 
         self.mac.set_link_up();
 
         self.phy.wait_for_auto_negotiation_complete()?;
         debug!("Auto-negotiation complete");
+        self.trace_open(OpenPhase::AutonegComplete);
         self.config_fc_after_link_up()?;
+        self.trace_open(OpenPhase::FlowControlResolved);
 
         self.init_stat();
+        self.downshifted = false;
+        let status = self.mac.status();
+        self.link = status.link_up.then_some((status.speed, status.full_duplex));
+        self.loopback = LoopbackMode::None;
+        // 复位后 PHY 回到全功率
+        self.link_down_for = Duration::ZERO;
+        self.energy_detect = false;
+        self.mac
+            .set_control_frames(self.rx_pause_frames, self.rx_mac_control_frames);
+        ptp::init(&mut self.mac, self.family);
 
-        self.mac.enable_interrupts();
+        self.enable_irqs();
 
         self.mac.enable_rx();
         self.mac.enable_tx();
+        self.trace_open(OpenPhase::RxTxEnabled);
+
+        Ok(())
+    }
+
+    /// Call `tracer` as [`Igb::open`] passes each [`OpenPhase`], e.g. to log
+    /// where link bring-up stalls on a headless system. `None` stops it.
+    pub fn set_open_tracer(&mut self, tracer: Option<fn(OpenPhase)>) {
+        self.open_tracer = tracer;
+    }
+
+    fn trace_open(&self, phase: OpenPhase) {
+        if let Some(tracer) = self.open_tracer {
+            tracer(phase);
+        }
+    }
+
+    /// Stop RX/TX, interrupts and DMA and hand the device back to the firmware.
+    ///
+    /// The PHY is powered down unless a BMC shares the port.
+    pub fn close(&mut self) {
+        self.stop();
+        if !self.mac.manageability_status().is_shared()
+            && let Err(e) = self.phy.power_down()
+        {
+            debug!("PHY power down failed: {e:?}");
+        }
+        self.mac.release_manageability();
+        self.mac.set_driver_loaded(false);
+    }
 
+    /// Prepare for the OS to put the device into D3.
+    ///
+    /// With wake-up filters installed the link is kept at its lowest speed
+    /// (D3 LPLU) so wake packets still arrive; otherwise the PHY is powered
+    /// down like in [`Igb::close`]. Resume with [`Igb::open`], which restores
+    /// the LPLU settings from before the suspend.
+    pub fn suspend(&mut self) -> Result<(), DError> {
+        self.stop();
+        if filter::wake_enabled(&self.mac) {
+            let (d0, d3) = self.phy.lplu(self.family)?;
+            self.saved_lplu = Some((d0, d3));
+            self.phy.set_lplu(self.family, d0, true)?;
+        } else if !self.mac.manageability_status().is_shared() {
+            self.phy.power_down()?;
+        }
+        self.mac.release_manageability();
+        self.mac.set_driver_loaded(false);
         Ok(())
     }
 
+    /// Link at the lowest common speed instead of the highest while in D0
+    /// and/or D3, trading throughput for power.
+    pub fn set_lplu(&mut self, d0: bool, d3: bool) -> Result<(), DError> {
+        self.saved_lplu = None;
+        self.phy.set_lplu(self.family, d0, d3)
+    }
+
+    /// Save PHY power on unplugged ports, see [`PowerPolicy`].
+    ///
+    /// The link-down time is measured by [`Igb::power_watchdog`], which the
+    /// caller has to run periodically.
+    pub fn set_power_policy(&mut self, policy: PowerPolicy) -> Result<(), DError> {
+        self.power_policy = policy;
+        if policy == PowerPolicy::AlwaysOn && self.energy_detect {
+            self.phy.set_smart_power_down(self.family, false)?;
+            self.energy_detect = false;
+        }
+        self.link_down_for = Duration::ZERO;
+        Ok(())
+    }
+
+    pub fn power_policy(&self) -> PowerPolicy {
+        self.power_policy
+    }
+
+    /// Apply the [`PowerPolicy`]; call periodically with the time elapsed
+    /// since the previous call, e.g. once per second.
+    ///
+    /// It accesses the PHY and shouldn't run in interrupt context.
+    pub fn power_watchdog(&mut self, elapsed: Duration) -> Result<(), DError> {
+        let PowerPolicy::EnergyDetect { after } = self.power_policy else {
+            return Ok(());
+        };
+        if self.mac.status().link_up {
+            // 链路恢复后重新计时，下次断开满 `after` 才再进入节能
+            self.link_down_for = Duration::ZERO;
+            if self.energy_detect {
+                self.phy.set_smart_power_down(self.family, false)?;
+                self.energy_detect = false;
+            }
+            return Ok(());
+        }
+        self.link_down_for = self.link_down_for.saturating_add(elapsed);
+        if !self.energy_detect && self.link_down_for >= after {
+            debug!(
+                "link down for {:?}, PHY to energy-detect mode",
+                self.link_down_for
+            );
+            self.phy.set_smart_power_down(self.family, true)?;
+            self.energy_detect = true;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.mac.disable_interrupts();
+        self.mac.disable_legacy_interrupts();
+        self.mac.disable_rx();
+        self.mac.disable_tx();
+        if let Err(e) = self.quiesce_dma() {
+            debug!("PCIe master disable timeout: {e:?}");
+        }
+    }
+
+    /// Stop the device from starting new DMA and wait for in-flight requests.
+    ///
+    /// Call this before freeing ring memory that the device may still access.
+    /// DMA stays disabled until the next [`Igb::open`].
+    pub fn quiesce_dma(&mut self) -> Result<(), DError> {
+        self.mac.disable_pcie_master()
+    }
+
+    /// Quiesce the device before the OS issues a Function Level Reset.
+    ///
+    /// Interrupts and queues are stopped and PCIe mastering is disabled, so
+    /// no DMA is in flight when the function is reset. All rings must be
+    /// dropped before calling [`Igb::after_flr`].
+    pub fn prepare_for_flr(&mut self) -> Result<(), DError> {
+        self.mac.disable_interrupts();
+        self.mac.disable_legacy_interrupts();
+        self.mac.disable_rx();
+        self.mac.disable_tx();
+        self.quiesce_dma()
+    }
+
+    /// Rebuild device state after a Function Level Reset.
+    ///
+    /// FLR returns every register to its power-on value, so this runs the
+    /// full [`Igb::open`] sequence again; rings have to be created anew with
+    /// [`Igb::new_ring`].
+    pub fn after_flr(&mut self) -> Result<(), DError> {
+        self.rx_wakers = [const { None }; 16];
+        self.rx_low_wakers = [const { None }; 16];
+        self.rx_refills = [const { None }; 16];
+        self.tx_wakers = [const { None }; 16];
+        self.tx_waiters = [const { None }; 16];
+        self.masked_vectors.store(0, Ordering::Relaxed);
+        self.flex_filters = 0;
+        // FLR 已清除 GIO master disable，这里防止 FLR 未真正发生
+        self.mac.enable_pcie_master();
+        self.open()
+    }
+
     pub fn new_ring(&mut self) -> Result<(TxRing, RxRing), DError> {
-        let tx_ring = TxRing::new(0, self.mac.iobase(), DEFAULT_RING_SIZE)?;
-        let rx_ring = RxRing::new(0, self.mac.iobase(), DEFAULT_RING_SIZE)?;
+        self.new_ring_with(0, RingConfig::default())
+    }
+
+    /// Create the TX and RX rings of queue `queue` with `config`.
+    ///
+    /// Buffers submitted to the RX ring must be at least
+    /// [`RingConfig::buffer_size`] long. `config.header_mode` must be
+    /// [`HeaderMode::None`], use [`Igb::new_split_ring_with`] for a header
+    /// buffer.
+    ///
+    /// On 82576 in MSI-X mode the write-back thresholds must be 0 or 1,
+    /// otherwise completions could stay unreported until more arrive.
+    pub fn new_ring_with(
+        &mut self,
+        queue: usize,
+        config: RingConfig,
+    ) -> Result<(TxRing, RxRing), DError> {
+        self.validate_ring(queue, &config)?;
+        self.build_ring(queue, config, None)
+    }
+
+    /// Like [`Igb::new_ring_with`], for a `config.header_mode` other than
+    /// [`HeaderMode::None`]. Packets of the returned ring have
+    /// [`RxPacket::header`].
+    pub fn new_split_ring_with(
+        &mut self,
+        queue: usize,
+        config: RingConfig,
+    ) -> Result<(TxRing, RxRing<HeaderSplit>), DError> {
+        self.validate_ring(queue, &config)?;
+        self.build_ring(queue, config, None)
+    }
+
+    fn validate_ring(&self, queue: usize, config: &RingConfig) -> Result<(), DError> {
+        if queue >= self.family.queue_count() {
+            return Err(DError::InvalidParameter);
+        }
+        config.validate_with(self.max_writeback())?;
+        // 队列 n 使用向量 n，最后一个向量留给 other 中断
+        if let IrqMode::MsiX { vectors } = self.irq_mode
+            && queue + 1 >= vectors as usize
+        {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(())
+    }
+
+    fn max_writeback(&self) -> u8 {
+        let msix = matches!(self.irq_mode, IrqMode::MsiX { .. });
+        ring::max_writeback(self.family, msix)
+    }
+
+    /// Like [`Igb::new_ring_with`], but place the descriptor rings in caller
+    /// supplied static memory instead of allocating them.
+    ///
+    /// Each region must be 128-byte aligned ([`DError::Misaligned`]
+    /// otherwise), DMA reachable under [`RingConfig::dma_mask`] and hold
+    /// `config.size * 16` bytes. Such rings can't be resized. Together with
+    /// [`Request::new_rx_in`] and [`Request::new_tx_in`] no DMA memory comes
+    /// from the heap.
+    ///
+    /// This is not a heap-free mode: the ring handles (`Arc`), the
+    /// per-descriptor bookkeeping and the RX buffer pool are still
+    /// allocated, so a global allocator is required.
+    pub fn new_ring_with_regions(
+        &mut self,
+        queue: usize,
+        config: RingConfig,
+        tx_region: &'static mut [u8],
+        rx_region: &'static mut [u8],
+    ) -> Result<(TxRing, RxRing), DError> {
+        self.validate_ring(queue, &config)?;
+        self.build_ring(queue, config, Some((tx_region, rx_region)))
+    }
+
+    /// [`Igb::new_split_ring_with`] with caller supplied descriptor memory,
+    /// see [`Igb::new_ring_with_regions`].
+    pub fn new_split_ring_with_regions(
+        &mut self,
+        queue: usize,
+        config: RingConfig,
+        tx_region: &'static mut [u8],
+        rx_region: &'static mut [u8],
+    ) -> Result<(TxRing, RxRing<HeaderSplit>), DError> {
+        self.validate_ring(queue, &config)?;
+        self.build_ring(queue, config, Some((tx_region, rx_region)))
+    }
+
+    fn build_ring<L: RxLayout>(
+        &mut self,
+        queue: usize,
+        config: RingConfig,
+        regions: Option<(&'static mut [u8], &'static mut [u8])>,
+    ) -> Result<(TxRing, RxRing<L>), DError> {
+        // RCTL.BSIZE 是全局的，取所有环中最小的缓冲区
+        if config.buffer_size < self.mac.rx_buffer_size() {
+            self.mac.set_rx_buffer_size(config.buffer_size);
+        }
+        let frame = config.frame_size();
+        if frame > self.max_frame {
+            self.max_frame = frame;
+            if self.tx_sched == TxScheduling::RoundRobin
+                && !pba::read(&self.mac, self.family).fits(frame)
+            {
+                warn!("packet buffers too small for {frame}-byte frames until the next open");
+            }
+        }
+        // 队列 n 的收发中断都映射到 EICR bit n
+        self.mac.map_queue_vector(queue, queue as u8, false)?;
+        self.mac.map_queue_vector(queue, queue as u8, true)?;
+        let (tx_region, rx_region) = regions.unzip();
+        let max_writeback = self.max_writeback();
+        let mut tx_ring = TxRing::new(
+            queue,
+            self.mac.iobase(),
+            &config,
+            self.family,
+            tx_region,
+            max_writeback,
+        )?;
+        let mut rx_ring = RxRing::new(
+            queue,
+            self.mac.iobase(),
+            &config,
+            self.family,
+            rx_region,
+            max_writeback,
+        )?;
+        // 新建的环从中断使能开始
+        self.masked_vectors
+            .fetch_and(!(1 << queue), Ordering::Relaxed);
+        tx_ring.set_irq_state(self.masked_vectors.clone(), self.legacy_irq.clone());
+        rx_ring.set_irq_state(self.masked_vectors.clone(), self.legacy_irq.clone());
+
+        self.rx_wakers[queue] = Some(rx_ring.waker());
+        self.rx_low_wakers[queue] = Some(rx_ring.low_waker());
+        self.rx_refills[queue] = Some(rx_ring.refill_handle());
+        self.tx_wakers[queue] = Some(tx_ring.waker());
+        self.tx_waiters[queue] = Some(tx_ring.waiters());
 
         Ok((tx_ring, rx_ring))
     }
 
+    /// Check that RCTL.BSIZE and SRRCTL of `queue` agree on the RX buffer size.
+    ///
+    /// Returns the buffer size the hardware uses for `queue`. Fails when
+    /// RCTL claims larger buffers than the queue has, which would let a
+    /// legacy-descriptor queue overrun its buffers.
+    pub fn check_rx_buffer_size(&self, queue: usize) -> Result<usize, DError> {
+        if queue >= self.family.queue_count() {
+            return Err(DError::InvalidParameter);
+        }
+        let srrctl = self.mac.read_reg(ring::srrctl_offset(queue));
+        let srrctl_bytes = (srrctl & 0x7F) as usize * 1024;
+        let rctl_bytes = self.mac.rx_buffer_size();
+        if srrctl_bytes == 0 {
+            return Ok(rctl_bytes);
+        }
+        if rctl_bytes > srrctl_bytes {
+            warn!("queue {queue}: RCTL.BSIZE {rctl_bytes} exceeds SRRCTL buffer {srrctl_bytes}");
+            return Err(DError::InvalidParameter);
+        }
+        Ok(srrctl_bytes)
+    }
+
+    /// The current device wide settings, for [`Igb::apply_config`].
+    pub fn config(&self) -> IgbConfig {
+        IgbConfig {
+            irq_mode: self.irq_mode,
+            irq_storm_limit: self.irq_storm_limit,
+            flow_control: self.flow_control,
+            rx_pause_frames: self.rx_pause_frames,
+            rx_mac_control_frames: self.rx_mac_control_frames,
+            smart_speed: self.smart_speed,
+            power_policy: self.power_policy,
+            storm_control: self.storm.policy,
+            tx_scheduling: self.tx_sched,
+            packet_buffers: self.packet_buffers,
+            rx_latency: self.rx_latency,
+            lli_filters: self.lli_filters,
+            lli_vlan_priority: self.lli_vlan_priority,
+            lli_moderation: self.lli_moderation,
+            etype_filters: self.etype_filters,
+            tuple_filters: self.tuple_filters,
+            mac_address: self.mac_addr,
+            mac_filters: core::array::from_fn(|slot| self.mac_filters.get(slot).copied().flatten()),
+        }
+    }
+
+    /// Apply all settings of `config` through their setters, e.g. to restore
+    /// a configuration saved from [`Igb::config`]. `None` settings go back
+    /// to their defaults.
+    ///
+    /// Settings the setters defer take effect at the next [`Igb::open`]. The
+    /// whole configuration is validated before any register is written; a
+    /// setter failing on a hardware error later leaves the settings before
+    /// it applied.
+    pub fn apply_config(&mut self, config: &IgbConfig) -> Result<(), DError> {
+        self.validate_config(config)?;
+
+        self.set_irq_mode(config.irq_mode)?;
+        self.set_irq_storm_limit(config.irq_storm_limit)?;
+        self.set_flow_control(config.flow_control)?;
+        self.set_rx_pause_frames(config.rx_pause_frames);
+        self.set_rx_mac_control_frames(config.rx_mac_control_frames);
+        self.set_smart_speed(config.smart_speed);
+        self.set_power_policy(config.power_policy)?;
+        self.set_storm_control(config.storm_control);
+        // 调度和包缓冲区划分互斥，先清掉旧的划分
+        self.set_packet_buffers(None)?;
+        self.set_tx_scheduling(config.tx_scheduling)?;
+        self.set_packet_buffers(config.packet_buffers)?;
+        match config.rx_latency {
+            Some(profile) => self.set_rx_latency_profile(profile),
+            None => self.clear_rx_latency_profile(),
+        }
+        self.set_lli_moderation(config.lli_moderation)?;
+        self.set_lli_vlan_priority(config.lli_vlan_priority)?;
+        for (index, filter) in config.etype_filters.iter().enumerate() {
+            self.set_etype_filter(index, *filter)?;
+        }
+        for (index, (lli, tuple)) in config
+            .lli_filters
+            .iter()
+            .zip(&config.tuple_filters)
+            .enumerate()
+        {
+            match tuple {
+                Some(_) => self.set_tuple_filter(index, *tuple)?,
+                None => self.set_lli_filter(index, *lli)?,
+            }
+        }
+        // 先清空过滤地址，换站地址时才有空闲槽位过渡
+        for index in 1..self.family.rar_count() {
+            self.set_mac_filter(index, None);
+        }
+        match config.mac_address {
+            Some(addr) => self.set_mac_address(addr)?,
+            None => self.reset_mac_address()?,
+        }
+        for (slot, addr) in config.mac_filters.iter().enumerate() {
+            if addr.is_some() {
+                self.set_mac_filter(slot + 1, *addr);
+            }
+        }
+        Ok(())
+    }
+
+    /// 按各 setter 的规则检查整个配置，不写寄存器
+    fn validate_config(&self, config: &IgbConfig) -> Result<(), DError> {
+        self.check_irq_mode(config.irq_mode)?;
+        if config.irq_storm_limit == Some(0) {
+            return Err(DError::InvalidParameter);
+        }
+        fc::validate(&self.mac, self.family, &config.flow_control)?;
+        config.tx_scheduling.validate(self.family)?;
+        if let Some(pb) = &config.packet_buffers {
+            self.check_packet_buffers(config.tx_scheduling, pb)?;
+        }
+        lli::check_moderation(config.lli_moderation)?;
+        lli::check_vlan_priority(config.lli_vlan_priority)?;
+        for filter in config.etype_filters.iter().flatten() {
+            steer::check_etype_filter(self.family, filter)?;
+        }
+        for (lli, tuple) in config.lli_filters.iter().zip(&config.tuple_filters) {
+            match (lli, tuple) {
+                // LLI 和 2-tuple 过滤器共用硬件槽位
+                (Some(_), Some(_)) => return Err(DError::InvalidParameter),
+                (Some(lli), None) => lli::check_filter(lli)?,
+                (None, Some(tuple)) => steer::check_tuple_filter(self.family, tuple)?,
+                (None, None) => {}
+            }
+        }
+        if let Some(addr) = config.mac_address {
+            mac::check_station_address(addr)?;
+        }
+        if config.mac_filters[self.family.rar_count() - 1..]
+            .iter()
+            .any(Option::is_some)
+        {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(())
+    }
+
     fn config_fc_after_link_up(&mut self) -> Result<(), DError> {
-        // TODO 参考 drivers/net/ethernet/intel/igb/e1000_mac.c
-        // igb_config_fc_after_link_up
+        self.mac.set_collision_distance();
+        let status = self.mac.status();
+        // 半双工没有 PAUSE 帧
+        if status.link_up && !status.full_duplex {
+            self.mac.set_flow_control(false, false);
+            self.fc_source = FcSource::HalfDuplex;
+            return Ok(());
+        }
+        self.fc_source = FcSource::Config;
+        // 水位无效时不影响打开设备
+        if let Err(e) = fc::apply(&mut self.mac, self.family, &self.flow_control) {
+            warn!("flow control not configured: {e:?}");
+            return Ok(());
+        }
+        self.apply_negotiated_pause();
+        Ok(())
+    }
+
+    /// 自协商过的链路只启用双方都支持的方向；SerDes 等读不到 PHY 时按配置
+    fn apply_negotiated_pause(&mut self) {
+        if let Ok(Some((rx, tx))) = self.negotiated_pause() {
+            let fc = self.flow_control;
+            self.mac
+                .set_flow_control(fc.rx_pause && rx, fc.tx_pause && tx);
+            self.fc_source = FcSource::Autoneg;
+        }
+    }
+
+    fn negotiated_pause(&mut self) -> Result<Option<(bool, bool)>, DError> {
+        if !self.phy.is_auto_negotiation_complete()? {
+            return Ok(None);
+        }
+        let partner = self.phy.link_partner()?;
+        if !partner.autoneg {
+            return Ok(None);
+        }
+        let local = self.phy.pause_advertised()?;
+        Ok(Some(fc::resolve(
+            local,
+            (partner.pause, partner.asym_pause),
+        )))
+    }
+
+    /// Configure 802.3x flow control and its RX packet buffer watermarks.
+    ///
+    /// Takes effect immediately and is re-applied by [`Igb::open`]. The
+    /// PAUSE advertisement follows the configuration; on an auto-negotiated
+    /// link only the directions the link partner supports are enabled, see
+    /// [`MacStatus::fc_source`]. A changed advertisement restarts
+    /// auto-negotiation on an open link. XON/XOFF frames sent and received
+    /// are counted in [`HwStats`].
+    pub fn set_flow_control(&mut self, config: FlowControl) -> Result<(), DError> {
+        fc::apply(&mut self.mac, self.family, &config)?;
+        self.flow_control = config;
+        if self.link.is_some() {
+            let (pause, asym_pause) = config.advertisement();
+            if self.phy.set_pause_advertisement(pause, asym_pause)? {
+                // 重新协商后由 link_event 按新的结果配置
+                return self.phy.enable_auto_negotiation();
+            }
+        }
+        if self.fc_source == FcSource::HalfDuplex {
+            self.mac.set_flow_control(false, false);
+        } else {
+            self.fc_source = FcSource::Config;
+            self.apply_negotiated_pause();
+        }
         Ok(())
     }
 
+    pub fn flow_control(&self) -> FlowControl {
+        self.flow_control
+    }
+
+    /// Arbitration between TX queues, see [`TxScheduling`].
+    ///
+    /// Takes effect at the next [`Igb::open`], since the hardware only
+    /// allows repartitioning the packet buffers with RX and TX stopped.
+    /// Fails with [`DError::Unsupported`] for modes the device lacks, e.g.
+    /// anything but [`TxScheduling::RoundRobin`] on 82576 and I350.
+    pub fn set_tx_scheduling(&mut self, sched: TxScheduling) -> Result<(), DError> {
+        sched.validate(self.family)?;
+        // Qav 模式有自己的包缓冲区划分
+        if sched != TxScheduling::RoundRobin && self.packet_buffers.is_some() {
+            return Err(DError::InvalidParameter);
+        }
+        self.tx_sched = sched;
+        Ok(())
+    }
+
+    pub fn tx_scheduling(&self) -> TxScheduling {
+        self.tx_sched
+    }
+
+    /// Repartition the on-chip packet buffer between RX and TX, `None` for
+    /// the default split.
+    ///
+    /// Takes effect at the next [`Igb::open`], like [`Igb::set_tx_scheduling`]
+    /// with which it can't be combined. Without an explicit split, `open`
+    /// gives TX room for two of the largest frames of the rings created so
+    /// far, as the datasheet requires for jumbo frames. The total can't
+    /// exceed the default split, and the split must hold the frames of the
+    /// rings created so far.
+    pub fn set_packet_buffers(&mut self, pb: Option<PacketBuffers>) -> Result<(), DError> {
+        if let Some(pb) = &pb {
+            self.check_packet_buffers(self.tx_sched, pb)?;
+        }
+        self.packet_buffers = pb;
+        Ok(())
+    }
+
+    fn check_packet_buffers(&self, sched: TxScheduling, pb: &PacketBuffers) -> Result<(), DError> {
+        if sched != TxScheduling::RoundRobin || !pb.fits(self.max_frame) {
+            return Err(DError::InvalidParameter);
+        }
+        pba::validate(self.family, pb, self.default_pb.as_ref())
+    }
+
+    /// The packet buffer split currently programmed.
+    pub fn packet_buffers(&self) -> PacketBuffers {
+        pba::read(&self.mac, self.family)
+    }
+
+    /// Send an XOFF frame now, pausing the link partner for
+    /// [`FlowControl::pause_time`] regardless of the watermarks.
+    pub fn send_xoff(&mut self) -> Result<(), DError> {
+        self.mac.send_xoff()
+    }
+
+    /// Send an XON frame now, letting the link partner resume early.
+    pub fn send_xon(&mut self) -> Result<(), DError> {
+        fc::send_xon(&mut self.mac)
+    }
+
     fn setup_phy_and_the_link(&mut self) -> Result<(), DError> {
         self.phy.power_up()?;
         debug!("PHY powered up");
+        self.phy.set_downshift(self.family, self.smart_speed)?;
+        if let Some((d0, d3)) = self.saved_lplu.take() {
+            self.phy.set_lplu(self.family, d0, d3)?;
+        }
+        let (pause, asym_pause) = self.flow_control.advertisement();
+        self.phy.set_pause_advertisement(pause, asym_pause)?;
         self.phy.enable_auto_negotiation()?;
 
         Ok(())
@@ -130,46 +1046,1146 @@ impl Igb {
         self.mac.read_mac().into()
     }
 
+    /// Change the station address (RAR0) while the device is running.
+    ///
+    /// The new address is first installed in a free receive address slot so
+    /// no frame to either address is dropped while RAR0 is rewritten; fails
+    /// with [`DError::NoMemory`] when [`Igb::add_mac_filter`] took them all.
+    /// The address is kept across [`Igb::open`].
+    ///
+    /// Manageability firmware sharing the port takes the station address
+    /// from RAR0. It is rewritten holding the SW/FW MAC CSR semaphore, so the
+    /// firmware picks up the new address whole.
+    pub fn set_mac_address(&mut self, addr: MacAddr6) -> Result<(), DError> {
+        mac::check_station_address(addr)?;
+        let bytes = addr.bytes();
+        // 过渡期间用空闲槽位接收新地址
+        let spare = (1..self.family.rar_count())
+            .rev()
+            .find(|&i| self.mac_filters.get(i - 1).is_none_or(Option::is_none))
+            .ok_or(DError::NoMemory)?;
+        let _sync = if self.mac.manageability_status().is_shared() {
+            Some(phy::Synced::new(
+                self.mac,
+                mac::SW_FW_SYNC::SW_MAC_CSR_SM::SET,
+            )?)
+        } else {
+            None
+        };
+        self.mac.write_rar(spare, bytes, true);
+        self.mac.write_rar(0, bytes, true);
+        self.mac.write_rar(spare, [0; 6], false);
+        self.mac_addr = Some(addr);
+        Ok(())
+    }
+
+    /// Go back to the address from the NVM, undoing [`Igb::set_mac_address`].
+    ///
+    /// Before the first [`Igb::open`] the NVM address is only restored by
+    /// it.
+    pub fn reset_mac_address(&mut self) -> Result<(), DError> {
+        if let Some(addr) = self.perm_addr {
+            self.set_mac_address(addr)?;
+        }
+        self.mac_addr = None;
+        Ok(())
+    }
+
+    /// Accept unicast frames to `addr` in addition to the station address.
+    ///
+    /// Returns the receive address slot used. Filters survive
+    /// [`Igb::open`] and other resets.
+    pub fn add_mac_filter(&mut self, addr: MacAddr6) -> Result<usize, DError> {
+        let count = self.family.rar_count() - 1;
+        self.mac_filters.resize(count, None);
+        let slot = self
+            .mac_filters
+            .iter()
+            .position(Option::is_none)
+            .ok_or(DError::NoMemory)?;
+        let index = slot + 1;
+        self.set_mac_filter(index, Some(addr));
+        Ok(index)
+    }
+
+    /// Remove the filter in receive address slot `index`.
+    pub fn remove_mac_filter(&mut self, index: usize) -> Result<(), DError> {
+        if !(1..self.family.rar_count()).contains(&index) {
+            return Err(DError::InvalidParameter);
+        }
+        self.set_mac_filter(index, None);
+        Ok(())
+    }
+
+    /// 写入 RAR1.. 中的一个槽位，`index` 已检查过
+    fn set_mac_filter(&mut self, index: usize, addr: Option<MacAddr6>) {
+        self.mac_filters.resize(self.family.rar_count() - 1, None);
+        self.mac_filters[index - 1] = addr;
+        match addr {
+            Some(addr) => self.mac.write_rar(index, addr.bytes(), true),
+            None => self.mac.write_rar(index, [0; 6], false),
+        }
+    }
+
+    /// Receive address slots as programmed in hardware:
+    /// `(index, address, enabled)`, RAR0 first.
+    pub fn mac_filters(&self) -> impl Iterator<Item = (usize, MacAddr6, bool)> + '_ {
+        (0..self.family.rar_count()).map(|i| {
+            let (addr, valid) = self.mac.read_rar(i);
+            (i, addr.into(), valid)
+        })
+    }
+
+    fn restore_mac_filters(&mut self) {
+        for (slot, filter) in self.mac_filters.iter().enumerate() {
+            if let Some(addr) = filter {
+                self.mac.write_rar(slot + 1, addr.bytes(), true);
+            }
+        }
+    }
+
+    /// Whether the PCI vendor and device ID belong to a supported
+    /// [`DeviceFamily`].
     pub fn check_vid_did(vid: u16, did: u16) -> bool {
-        // This is a placeholder for actual VID/DID checking logic.
-        // In a real implementation, this would check the device's
-        // vendor ID and device ID against the provided values.
-        vid == 0x8086 && [0x10C9, 0x1533].contains(&did)
+        vid == 0x8086 && DeviceFamily::from_device_id(did).is_some()
     }
 
     pub fn status(&self) -> MacStatus {
-        self.mac.status()
+        let mut status = self.mac.status();
+        status.downshifted = self.downshifted;
+        status.fc_source = self.fc_source;
+        status
+    }
+
+    /// Re-read the link state, including whether the PHY downshifted.
+    ///
+    /// Call this after [`IrqEvents::link_changed`]; it accesses the PHY and
+    /// shouldn't run in interrupt context.
+    pub fn refresh_link_status(&mut self) -> Result<MacStatus, DError> {
+        let status = self.mac.status();
+        self.downshifted =
+            status.link_up && status.speed != Speed::Mb1000 && self.phy.gigabit_possible()?;
+        if self.downshifted {
+            warn!("link downshifted to {:?}", status.speed);
+        }
+        Ok(self.status())
+    }
+
+    /// Compare the link with the state seen by the previous call or
+    /// [`Igb::open`], and re-apply the collision distance and flow control
+    /// when the link comes up or its speed or duplex changed.
+    ///
+    /// Call this after [`IrqEvents::link_changed`] instead of
+    /// [`Igb::refresh_link_status`]; it accesses the PHY and shouldn't run
+    /// in interrupt context.
+    pub fn link_event(&mut self) -> Result<Option<LinkEvent>, DError> {
+        let status = self.refresh_link_status()?;
+        let now = status.link_up.then_some((status.speed, status.full_duplex));
+        let prev = core::mem::replace(&mut self.link, now);
+        if prev == now {
+            return Ok(None);
+        }
+        let Some((speed, full_duplex)) = now else {
+            return Ok(Some(LinkEvent::Down));
+        };
+        self.config_fc_after_link_up()?;
+        Ok(Some(match prev {
+            None => LinkEvent::Up { speed, full_duplex },
+            Some(_) => {
+                debug!("link renegotiated to {speed:?}, full duplex: {full_duplex}");
+                LinkEvent::SpeedChanged { speed, full_duplex }
+            }
+        }))
+    }
+
+    /// Wait until the link comes up, handling it like [`Igb::link_event`],
+    /// and return its status.
+    ///
+    /// Fails with [`DError::TimedOut`] once `timeout` has passed, measured
+    /// with [`Kernel::now`](osal::Kernel::now). It sleeps between polls of
+    /// the PHY and shouldn't run in interrupt context.
+    pub fn wait_link_up_timeout(&mut self, timeout: Duration) -> Result<MacStatus, DError> {
+        let start = osal::kernel::now();
+        loop {
+            self.link_event()?;
+            if self.link.is_some() {
+                return Ok(self.status());
+            }
+            let elapsed = osal::kernel::now().saturating_sub(start);
+            if elapsed >= timeout {
+                return Err(DError::TimedOut {
+                    op: "link up",
+                    elapsed,
+                });
+            }
+            osal::kernel::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Signal detect, sync and fault indications of a SerDes or SGMII port,
+    /// to tell a missing signal from a faulted link.
+    ///
+    /// Fails with [`DError::InvalidParameter`] on copper ports.
+    pub fn serdes_status(&self) -> Result<SerdesStatus, DError> {
+        match self.mac.link_mode() {
+            Some(mac::LinkMode::Sgmii | mac::LinkMode::InternalSerdes) => {
+                Ok(pcs::status(&self.mac))
+            }
+            _ => Err(DError::InvalidParameter),
+        }
+    }
+
+    /// Allow falling back from 1000 Mb/s on marginal cabling (SmartSpeed /
+    /// downshift). Enabled by default; applied by [`Igb::open`].
+    pub fn set_smart_speed(&mut self, enable: bool) {
+        self.smart_speed = enable;
+    }
+
+    /// Reset the PHY and run link setup again.
+    ///
+    /// Needed for some PHY settings, such as forced speed or changed
+    /// advertisement, to take effect. Fails if a BMC blocks PHY resets.
+    pub fn reset_phy(&mut self) -> Result<(), DError> {
+        self.phy.reset()?;
+        self.setup_phy_and_the_link()
+    }
+
+    /// Talk to an external PHY at MDIO address `addr`; SGMII link mode only.
+    ///
+    /// Call before [`Igb::open`] so link setup goes to the external PHY.
+    pub fn use_external_phy(&mut self, addr: u32) -> Result<(), DError> {
+        if self.mac.link_mode() != Some(mac::LinkMode::Sgmii) {
+            return Err(DError::InvalidParameter);
+        }
+        self.phy.use_external(self.family, addr)
+    }
+
+    /// Read PHY register `offset` without busy-waiting on MDIC.
+    ///
+    /// Completion is signalled by the MDAC interrupt, so
+    /// [`Igb::handle_interrupt`] must be called from the interrupt handler.
+    /// The returned future doesn't borrow the driver.
+    pub fn read_phy_async(&mut self, offset: u32) -> Result<MdioRead, DError> {
+        self.mac.enable_mdac_interrupt();
+        self.phy.read_mdic_async(offset, self.mdio_waker.clone())
+    }
+
+    /// Read Clause 45 register `reg` of MMD `dev` (e.g. 7 for EEE
+    /// auto-negotiation) of the PHY in use.
+    pub fn read_phy_mmd(&mut self, dev: u8, reg: u16) -> Result<u16, DError> {
+        self.phy.read_mmd(dev, reg)
+    }
+
+    pub fn write_phy_mmd(&mut self, dev: u8, reg: u16, data: u16) -> Result<(), DError> {
+        self.phy.write_mmd(dev, reg, data)
+    }
+
+    /// Abilities the link partner advertised during auto-negotiation.
+    ///
+    /// Compare with [`Igb::status`] to see why a link resolved to a lower
+    /// speed than expected.
+    pub fn link_partner(&mut self) -> Result<LinkPartnerInfo, DError> {
+        self.phy.link_partner()
+    }
+
+    /// Program PCIe completion timeout, relaxed ordering and no-snoop.
+    ///
+    /// `PcieTuning::default()` gives the datasheet recommendation.
+    pub fn pcie_tuning(&mut self, tuning: PcieTuning) {
+        pcie::apply_tuning(&mut self.mac, &tuning);
+    }
+
+    /// Software-definable pin `pin` as a GPIO.
+    pub fn sdp(&mut self, pin: u8) -> Result<Sdp<'_>, DError> {
+        Sdp::new(&mut self.mac, pin)
     }
 
+    /// Override the controller generation detected by [`Igb::new`], e.g. for
+    /// a device whose NVM is blank or reports an unknown ID.
+    ///
+    /// Set it before [`Igb::open`].
+    pub fn set_family(&mut self, family: DeviceFamily) {
+        self.family = family;
+    }
+
+    pub fn family(&self) -> DeviceFamily {
+        self.family
+    }
+
+    /// Hardware capabilities of this controller, for the family the rest of
+    /// the driver uses, see [`Igb::family`].
+    pub fn capabilities(&self) -> DeviceCaps {
+        DeviceCaps::new(self.family, self.device_id)
+    }
+
+    /// NVM image version and EEPROM tracking ID.
+    pub fn fw_version(&mut self) -> Result<FwVersion, DError> {
+        nvm::read_fw_version(&mut self.mac)
+    }
+
+    /// PBA number, PCI IDs and base Ethernet address from the NVM.
+    pub fn part_info(&mut self) -> Result<PartInfo, DError> {
+        nvm::read_part_info(&mut self.mac)
+    }
+
+    /// 64-bit device serial number as the NVM loads it into the PCIe Device
+    /// Serial Number capability.
+    ///
+    /// It is unique per adapter, not per port. When the OS can read
+    /// configuration space, [`serial_from_dsn_capability`] gives the same
+    /// value without touching the NVM.
+    pub fn serial_number(&mut self) -> Result<u64, DError> {
+        nvm::read_serial_number(&mut self.mac)
+    }
+
+    /// Current SYSTIM value in nanoseconds.
+    pub fn systim_now(&self) -> u64 {
+        ptp::read_systim(&self.mac, self.family)
+    }
+
+    pub fn set_systim(&mut self, ns: u64) {
+        ptp::set_systim(&mut self.mac, self.family, ns);
+    }
+
+    /// Speed SYSTIM up (positive) or slow it down (negative) by `ppb` parts
+    /// per billion relative to its nominal rate.
+    ///
+    /// Fails with [`DError::InvalidParameter`] beyond [`max_adjust_ppb`].
+    pub fn adjust_systim_ppb(&mut self, ppb: i32) -> Result<(), DError> {
+        ptp::adjust_ppb(&mut self.mac, self.family, ppb)
+    }
+
+    /// Enable hardware timestamping of received packets and of packets sent
+    /// with [`TxRing::send_timestamped`] (I210).
+    pub fn set_timestamping(&mut self, rx: bool, tx: bool) {
+        ptp::set_timestamping(&mut self.mac, rx, tx);
+    }
+
+    /// Arm PTP target time `timer` to fire at SYSTIM `time_ns`, in the
+    /// nanoseconds of [`Igb::systim_now`].
+    ///
+    /// When it fires, [`IrqEvents::target_time`] reports it and, if `pin` is
+    /// given, that SDP pin toggles. Target times are one-shot.
+    pub fn set_target_time(
+        &mut self,
+        timer: usize,
+        time_ns: u64,
+        pin: Option<u8>,
+    ) -> Result<(), DError> {
+        ptp::set_target_time(&mut self.mac, self.family, timer, time_ns, pin)
+    }
+
+    pub fn clear_target_time(&mut self, timer: usize) -> Result<(), DError> {
+        ptp::clear_target_time(&mut self.mac, timer)
+    }
+
+    /// Output a pulse-per-second on SDP `pin` aligned to SYSTIM seconds (I210).
+    ///
+    /// Uses frequency clock 0 and target time 0, so target time 0 isn't
+    /// available while PPS is enabled. Fails with
+    /// [`DError::InvalidParameter`] on other parts.
+    pub fn enable_pps(&mut self, pin: u8) -> Result<(), DError> {
+        ptp::enable_pps(&mut self.mac, self.family, pin)
+    }
+
+    pub fn disable_pps(&mut self) {
+        ptp::disable_pps(&mut self.mac);
+    }
+
+    /// Whether this port is shared with a BMC, and how.
+    pub fn manageability_status(&self) -> ManageabilityStatus {
+        self.mac.manageability_status()
+    }
+
+    /// MAC loopback, see [`Igb::set_loopback`].
     pub fn enable_loopback(&mut self) {
-        self.mac.enable_loopback();
+        if let Err(e) = self.set_loopback(LoopbackMode::Mac) {
+            warn!("MAC loopback not enabled: {e:?}");
+        }
     }
 
     pub fn disable_loopback(&mut self) {
-        self.mac.disable_loopback();
+        if let Err(e) = self.set_loopback(LoopbackMode::None) {
+            warn!("loopback not disabled: {e:?}");
+        }
+    }
+
+    /// Loop transmitted frames back to the RX path at the given point.
+    ///
+    /// [`LoopbackMode::Phy`] needs a copper port and [`LoopbackMode::Serdes`]
+    /// a SerDes or SGMII port, otherwise [`DError::InvalidParameter`] is
+    /// returned. Both force 1000 Mb/s full duplex; returning to
+    /// [`LoopbackMode::None`] restarts auto-negotiation. [`Igb::open`] resets
+    /// the device to normal operation.
+    pub fn set_loopback(&mut self, mode: LoopbackMode) -> Result<(), DError> {
+        let link_mode = self.mac.link_mode();
+        let supported = match mode {
+            LoopbackMode::None | LoopbackMode::Mac => true,
+            LoopbackMode::Phy => link_mode == Some(mac::LinkMode::DirectCooper),
+            LoopbackMode::Serdes => matches!(
+                link_mode,
+                Some(mac::LinkMode::Sgmii | mac::LinkMode::InternalSerdes)
+            ),
+        };
+        if !supported {
+            return Err(DError::InvalidParameter);
+        }
+
+        // 先撤销当前模式
+        match self.loopback {
+            LoopbackMode::Phy if mode != LoopbackMode::Phy => {
+                self.phy.set_loopback(self.family, false)?;
+                self.mac.force_link_1000_full(false);
+            }
+            LoopbackMode::Serdes if mode != LoopbackMode::Serdes => {
+                loopback::disable_serdes(&mut self.mac, self.family);
+                self.config_fc_after_link_up()?;
+            }
+            _ => {}
+        }
+
+        match mode {
+            LoopbackMode::None => self.mac.disable_loopback(),
+            LoopbackMode::Mac => self.mac.enable_loopback(),
+            LoopbackMode::Phy => {
+                self.mac.disable_loopback();
+                self.phy.set_loopback(self.family, true)?;
+                self.mac.force_link_1000_full(true);
+            }
+            LoopbackMode::Serdes => loopback::enable_serdes(&mut self.mac, self.family),
+        }
+        self.loopback = mode;
+        Ok(())
+    }
+
+    pub fn loopback(&self) -> LoopbackMode {
+        self.loopback
+    }
+
+    /// Also deliver received PAUSE frames to the RX rings, e.g. for
+    /// monitoring. Flow control still acts on them; they are counted in
+    /// [`HwStats`] either way.
+    pub fn set_rx_pause_frames(&mut self, enable: bool) {
+        self.rx_pause_frames = enable;
+        self.mac
+            .set_control_frames(self.rx_pause_frames, self.rx_mac_control_frames);
+    }
+
+    /// Deliver MAC control frames other than PAUSE to the RX rings instead of
+    /// dropping them in the MAC.
+    pub fn set_rx_mac_control_frames(&mut self, enable: bool) {
+        self.rx_mac_control_frames = enable;
+        self.mac
+            .set_control_frames(self.rx_pause_frames, self.rx_mac_control_frames);
     }
 
     fn init_stat(&mut self) {
-        //TODO
+        // 复位后硬件计数器归零
+        self.queue_counters = Default::default();
+        self.hw_counters = Default::default();
+        self.storm.forget();
+    }
+
+    /// Accumulate the global MAC counters and return a copy of the totals.
+    ///
+    /// The hardware counters are 32 bits wide, so call this or
+    /// [`Igb::stats_tick`] at least every [`STATS_POLL_INTERVAL`].
+    pub fn stats_snapshot(&mut self) -> HwStats {
+        self.hw_counters.update(&self.mac)
+    }
+
+    /// Counter increase since `prev`, an earlier [`Igb::stats_snapshot`].
+    ///
+    /// Use [`HwStats::per_second`] on the result to turn it into rates.
+    pub fn stats_delta(&mut self, prev: &HwStats) -> HwStats {
+        self.stats_snapshot().delta(prev)
+    }
+
+    /// Fold every hardware counter into its 64-bit software total.
+    ///
+    /// Call it from a periodic task at least every [`STATS_POLL_INTERVAL`]
+    /// so long-running byte counts stay correct even if nobody reads the
+    /// statistics in between.
+    ///
+    /// It also starts a new interval for [`Igb::set_irq_storm_limit`].
+    pub fn stats_tick(&mut self) {
+        self.spurious_window = 0;
+        self.hw_counters.update(&self.mac);
+        let count = self.queue_stats_count();
+        for (queue, counters) in self.queue_counters[..count].iter_mut().enumerate() {
+            counters.update(&self.mac, queue);
+        }
+    }
+
+    /// Clear all statistics: the global clear-on-read counters, the per-queue
+    /// totals and the software interrupt counters all restart from zero.
+    pub fn reset_stats(&mut self) {
+        self.hw_counters.reset(&self.mac);
+        let count = self.queue_stats_count();
+        for (queue, counters) in self.queue_counters[..count].iter_mut().enumerate() {
+            counters.reset(&self.mac, queue);
+        }
+        self.rx_overruns = 0;
+        self.link_errors = 0;
+        self.spurious_irqs = 0;
+        self.storm.rebase();
+    }
+
+    /// Report broadcast and multicast storms and optionally drop them, see
+    /// [`StormPolicy`].
+    ///
+    /// Rates are measured by [`Igb::storm_watchdog`]. Filters tightened by
+    /// [`StormAction::Drop`] are restored before the new policy applies.
+    pub fn set_storm_control(&mut self, policy: StormPolicy) {
+        let stats = self.stats_snapshot();
+        self.storm.reset(&mut self.mac, &stats);
+        self.storm.policy = policy;
+    }
+
+    /// Call `callback` from [`Igb::storm_watchdog`] whenever a storm starts or
+    /// ends. `None` stops it.
+    pub fn set_storm_callback(&mut self, callback: Option<fn(StormEvents)>) {
+        self.storm_callback = callback;
+    }
+
+    pub fn storm_control(&self) -> StormPolicy {
+        self.storm.policy
+    }
+
+    /// Check the [`StormPolicy`] thresholds; call periodically with the time elapsed
+    /// since the previous call, e.g. once per second.
+    ///
+    /// Accumulates the statistics like [`Igb::stats_snapshot`], applies the
+    /// [`StormAction`] and passes any events to [`Igb::set_storm_callback`].
+    pub fn storm_watchdog(&mut self, elapsed: Duration) -> StormEvents {
+        let stats = self.stats_snapshot();
+        let events = self.storm.update(&mut self.mac, &stats, elapsed);
+        if !events.is_empty() {
+            warn!("storm control: {events:?}");
+            if let Some(callback) = self.storm_callback {
+                callback(events);
+            }
+        }
+        events
+    }
+
+    /// Number of RX overrun interrupts since [`Igb::new`] or [`Igb::reset_stats`].
+    pub fn rx_overruns(&self) -> u64 {
+        self.rx_overruns
+    }
+
+    /// Number of receive sequence (link framing) errors since [`Igb::new`] or
+    /// [`Igb::reset_stats`].
+    pub fn link_errors(&self) -> u64 {
+        self.link_errors
+    }
+
+    /// Number of interrupts that carried no cause for this device, e.g. from
+    /// another device sharing the INTx line, since [`Igb::new`] or
+    /// [`Igb::reset_stats`].
+    pub fn spurious_irq_count(&self) -> u64 {
+        self.spurious_irqs
+    }
+
+    /// Mask all of the device's interrupts once `limit` spurious interrupts
+    /// arrive within one [`Igb::stats_tick`] interval, reporting
+    /// [`IrqEvents::storm`]. `None` disables the protection, which is the
+    /// default.
+    ///
+    /// The limit is a rate: run `stats_tick` at a fixed period and scale
+    /// `limit` to it. Valid interrupts in between don't restart the count.
+    /// The driver has no timer of its own: back off by polling the rings,
+    /// then call [`Igb::rearm_interrupts`].
+    pub fn set_irq_storm_limit(&mut self, limit: Option<u32>) -> Result<(), DError> {
+        if limit == Some(0) {
+            return Err(DError::InvalidParameter);
+        }
+        self.irq_storm_limit = limit;
+        self.spurious_window = 0;
+        Ok(())
+    }
+
+    /// Unmask the interrupts of the current [`IrqMode`] again, e.g. after an
+    /// interrupt storm.
+    pub fn rearm_interrupts(&mut self) {
+        self.spurious_window = 0;
+        self.enable_irqs();
+    }
+
+    fn enable_irqs(&mut self) {
+        let masked = self.masked_vectors.load(Ordering::Relaxed);
+        match self.irq_mode {
+            IrqMode::Legacy => {
+                self.mac.enable_legacy_interrupts();
+                // 各队列共用中断原因，有环屏蔽时一起屏蔽
+                if masked != 0 {
+                    self.mac.mask_legacy_queue_causes();
+                }
+            }
+            IrqMode::Msi => {
+                self.mac.enable_link_interrupt();
+                self.mac.enable_rx_error_interrupts();
+                self.mac.enable_vectors(!masked);
+            }
+            IrqMode::MsiX { vectors } => {
+                self.mac.enable_link_interrupt();
+                self.mac.enable_rx_error_interrupts();
+                self.mac
+                    .enable_vectors(((1u64 << vectors) - 1) as u32 & !masked);
+            }
+        }
+    }
+
+    /// 记录无原因的中断，一个 stats_tick 间隔内超过上限时屏蔽设备中断
+    fn account_spurious(&mut self, spurious: bool) -> bool {
+        if !spurious {
+            return false;
+        }
+        self.spurious_irqs += 1;
+        self.spurious_window = self.spurious_window.saturating_add(1);
+        let Some(limit) = self.irq_storm_limit else {
+            return false;
+        };
+        if self.spurious_window < limit {
+            return false;
+        }
+        warn!(
+            "interrupt storm: {} spurious interrupts in one stats interval, masking",
+            self.spurious_window
+        );
+        self.mac.disable_interrupts();
+        self.mac.disable_legacy_interrupts();
+        self.spurious_window = 0;
+        true
+    }
+
+    /// Interrupt handler counters; ring counters come from
+    /// [`RxRing::telemetry`] and [`TxRing::telemetry`].
+    #[cfg(feature = "telemetry")]
+    pub fn telemetry(&self) -> Telemetry {
+        self.telemetry
+    }
+
+    /// Read the register at byte `offset` directly, for experimenting with
+    /// registers the driver doesn't model. Without [`Igb::new_with_bar`] the
+    /// bound is [`REG_SPAN`].
+    #[cfg(feature = "raw-regs")]
+    pub fn read_reg(&self, offset: usize) -> Result<u32, DError> {
+        self.check_reg(offset)?;
+        Ok(self.mac.read_reg(offset))
+    }
+
+    /// Write the register at byte `offset` directly. The driver doesn't see
+    /// the change and may overwrite it, e.g. on the next [`Igb::open`].
+    #[cfg(feature = "raw-regs")]
+    pub fn write_reg(&mut self, offset: usize, value: u32) -> Result<(), DError> {
+        self.check_reg(offset)?;
+        self.mac.write_reg(offset, value);
+        Ok(())
+    }
+
+    #[cfg(feature = "raw-regs")]
+    fn check_reg(&self, offset: usize) -> Result<(), DError> {
+        if !offset.is_multiple_of(4) {
+            return Err(DError::Misaligned);
+        }
+        if offset.checked_add(4).is_none_or(|end| end > self.bar_len) {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(())
+    }
+
+    /// Wake every RX ring on overrun or low descriptor interrupts so their
+    /// tasks refill buffers. Enabled by default.
+    pub fn set_refill_on_rx_pressure(&mut self, enable: bool) {
+        self.refill_on_rx_pressure = enable;
+    }
+
+    /// Hardware traffic counters of queue `queue`, accumulated to 64 bits.
+    ///
+    /// Call this or [`Igb::stats_tick`] at least every
+    /// [`STATS_POLL_INTERVAL`] so the 32-bit hardware counters can't wrap
+    /// twice between reads.
+    ///
+    /// Returns [`DError::InvalidParameter`] if `queue` is not below the
+    /// device's queue count and [`QUEUE_STATS_COUNT`].
+    pub fn queue_stats(&mut self, queue: usize) -> Result<QueueStats, DError> {
+        if queue >= self.queue_stats_count() {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(self.queue_counters[queue].update(&self.mac, queue))
+    }
+
+    /// 有每队列计数器的队列数，I210 只有 4 个队列
+    fn queue_stats_count(&self) -> usize {
+        QUEUE_STATS_COUNT.min(self.family.queue_count())
     }
 
     /// # Safety
     /// This function should only be called from the interrupt handler.
-    /// It will handle the interrupt by acknowledging
-    pub unsafe fn handle_interrupt(&mut self) {
-        let msg = self.mac.interrupts_ack();
-        debug!("Interrupt message: {msg:?}");
-        if msg.queue_idx & 0x1 != 0 {
-            // let rx_ring = unsafe { &mut *(self.rx_ring_addrs[0] as *mut Ring<AdvRxDesc>) };
-            // rx_ring.clean();
+    /// It will handle the interrupt by acknowledging the causes, waking the
+    /// rings that have activity and returning the decoded events.
+    pub unsafe fn handle_interrupt(&mut self) -> IrqEvents {
+        telemetry!(self.telemetry.irqs += 1);
+        let (events, time_sync): (IrqEvents, bool) = match self.irq_mode {
+            IrqMode::Legacy => {
+                let msg = self.mac.legacy_interrupts_ack();
+                let time_sync = msg.time_sync;
+                (msg.into(), time_sync)
+            }
+            IrqMode::Msi => {
+                let msg = self.mac.interrupts_ack(self.irq_ack);
+                let time_sync = msg.time_sync;
+                (msg.into(), time_sync)
+            }
+            IrqMode::MsiX { vectors } => {
+                let msg = self.mac.msix_interrupts_ack(vectors - 1, self.irq_ack);
+                let time_sync = msg.time_sync;
+                (msg.into(), time_sync)
+            }
+        };
+        let spurious = !time_sync && events == IrqEvents::default();
+        if self.account_spurious(spurious) {
+            return IrqEvents {
+                storm: true,
+                ..events
+            };
+        }
+        self.dispatch_events(events, time_sync)
+    }
+
+    /// Handle one MSI-X vector, touching only its EICR bit.
+    ///
+    /// Queue vectors wake just the rings of that queue; the last vector
+    /// decodes the non-queue causes like [`Igb::handle_interrupt`].
+    ///
+    /// # Safety
+    /// This function should only be called from the handler of `vector`.
+    pub unsafe fn handle_vector_interrupt(&mut self, vector: u8) -> VectorEvent {
+        telemetry!(self.telemetry.vector_irqs += 1);
+        let IrqMode::MsiX { vectors } = self.irq_mode else {
+            self.account_spurious(true);
+            return VectorEvent::Spurious;
+        };
+        if vector >= vectors {
+            self.account_spurious(true);
+            return VectorEvent::Spurious;
+        }
+        if vector == vectors - 1 {
+            let msg = self.mac.msix_vector_ack(vector, true);
+            let time_sync = msg.time_sync;
+            return VectorEvent::Other(self.dispatch_events(msg.into(), time_sync));
+        }
+
+        self.mac.msix_vector_ack(vector, false);
+        // 队列 n 固定映射到向量 n
+        let queue = vector as usize;
+        if let Some(refill) = self.rx_refills.get(queue).and_then(Option::as_ref) {
+            refill.run();
+        }
+        if let Some(waker) = self.rx_wakers.get(queue).and_then(Option::as_ref) {
+            waker.wake();
+        }
+        if let Some(waker) = self.tx_wakers.get(queue).and_then(Option::as_ref) {
+            waker.wake();
+        }
+        self.wake_tx_waiters(queue);
+        VectorEvent::Queue(vector)
+    }
+
+    fn dispatch_events(&mut self, mut events: IrqEvents, time_sync: bool) -> IrqEvents {
+        if time_sync {
+            events.target_time = ptp::ack_interrupt(&mut self.mac);
+        }
+        datapath_debug!("Interrupt events: {events:?}");
+        if events.mdio_done {
+            self.mdio_waker.wake();
+        }
+        if events.rx_overrun {
+            self.rx_overruns += 1;
+        }
+        if events.rx_seq_error {
+            self.link_errors += 1;
+        }
+        if events.rx_overrun || events.rx_desc_low {
+            for refill in self.rx_refills.iter().flatten() {
+                refill.run();
+            }
+        }
+        if events.rx_desc_low {
+            for waker in self.rx_low_wakers.iter().flatten() {
+                waker.wake();
+            }
+        }
+        // 缓冲区不足时唤醒所有接收任务尽快补充描述符
+        if self.refill_on_rx_pressure && (events.rx_overrun || events.rx_desc_low) {
+            for waker in self.rx_wakers.iter().flatten() {
+                waker.wake();
+            }
+        }
+        for i in 0..16 {
+            if events.queues & (1 << i) == 0 {
+                continue;
+            }
+            if let Some(refill) = &self.rx_refills[i] {
+                refill.run();
+            }
+            if let Some(waker) = &self.rx_wakers[i] {
+                waker.wake();
+            }
+            if let Some(waker) = &self.tx_wakers[i] {
+                waker.wake();
+            }
+            self.wake_tx_waiters(i);
+        }
+        events
+    }
+
+    /// 唤醒发送完成的等待者；还有等待者时重新打开可能被自动屏蔽的向量，
+    /// 否则未完成的 [`TxCompletion`] 收不到下一次中断
+    fn wake_tx_waiters(&mut self, queue: usize) {
+        let Some(waiters) = self.tx_waiters.get(queue).and_then(Option::as_ref) else {
+            return;
+        };
+        waiters.wake();
+        let bit = 1 << queue;
+        if waiters.has_waiters() && self.masked_vectors.load(Ordering::Relaxed) & bit == 0 {
+            self.mac.enable_vectors(bit);
+        }
+    }
+
+    /// Auto-clear and auto-mask the vectors in `vectors` on assertion.
+    ///
+    /// A masked vector stays silent until it is re-armed, either by the ring
+    /// poll path once the ring is drained or explicitly via [`Igb::rearm_vector`].
+    /// Auto-clear (EIAC) only takes effect in MSI-X mode.
+    pub fn set_interrupt_auto_mask(&mut self, vectors: u32) {
+        self.mac.set_auto_mask(vectors, vectors);
+    }
+
+    /// Unmask interrupt vector `vector` again after it was auto-masked.
+    ///
+    /// `vector` must be below the MSI-X vector count, or the number of
+    /// queues in legacy and MSI mode where each queue has its own EICR bit.
+    pub fn rearm_vector(&mut self, vector: u8) -> Result<(), DError> {
+        let count = match self.irq_mode {
+            IrqMode::MsiX { vectors } => vectors as usize,
+            IrqMode::Legacy | IrqMode::Msi => self.family.queue_count(),
+        };
+        if vector as usize >= count {
+            return Err(DError::InvalidParameter);
+        }
+        self.mac.enable_vectors(1 << vector);
+        Ok(())
+    }
+
+    /// Select the interrupt mode. Call before [`Igb::open`].
+    ///
+    /// MSI-X needs one vector per queue in use plus one for the "other"
+    /// causes, so `vectors` must be at least 2 and exceed the highest queue
+    /// index of the existing rings.
+    pub fn set_irq_mode(&mut self, mode: IrqMode) -> Result<(), DError> {
+        self.check_irq_mode(mode)?;
+        self.irq_mode = mode;
+        self.legacy_irq
+            .store(mode == IrqMode::Legacy, Ordering::Relaxed);
+        self.apply_irq_mode();
+        Ok(())
+    }
+
+    fn check_irq_mode(&self, mode: IrqMode) -> Result<(), DError> {
+        if let IrqMode::MsiX { vectors } = mode {
+            let queues = (0..self.rx_refills.len())
+                .rposition(|q| self.has_live_ring(q))
+                .map_or(1, |q| q + 1);
+            if vectors > irq::MAX_MSIX_VECTORS || (vectors as usize) < queues + 1 {
+                return Err(DError::InvalidParameter);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn irq_mode(&self) -> IrqMode {
+        self.irq_mode
+    }
+
+    /// 队列的 RX 环还没有释放
+    fn has_live_ring(&self, queue: usize) -> bool {
+        self.rx_refills
+            .get(queue)
+            .and_then(Option::as_ref)
+            .is_some_and(ring::RxRefill::is_live)
+    }
+
+    /// How interrupt causes are acknowledged, read back from GPIE.
+    ///
+    /// Follows the [`IrqMode`]: [`IrqAckPolicy::ClearOnRead`] for legacy
+    /// and MSI, [`IrqAckPolicy::WriteToClear`] for MSI-X.
+    pub fn irq_ack_policy(&self) -> IrqAckPolicy {
+        self.mac.irq_ack_policy()
+    }
+
+    /// Interrupt vectors in use with their causes, e.g. to set the CPU
+    /// affinity of each MSI-X entry. Queue vectors are listed while their
+    /// RX ring exists.
+    pub fn vectors(&self) -> Vec<(u8, VectorCause)> {
+        let IrqMode::MsiX { vectors } = self.irq_mode else {
+            return alloc::vec![(0, VectorCause::All)];
+        };
+        let other = vectors - 1;
+        let mut list: Vec<_> = (0..other)
+            .filter(|&v| self.has_live_ring(v as usize))
+            .map(|v| (v, VectorCause::Queue(v)))
+            .collect();
+        list.push((other, VectorCause::Other));
+        list
+    }
+
+    /// Install a flexible filter that wakes the host (or triggers proxying)
+    /// when the first [`FLEX_FILTER_LEN`] bytes of a frame match.
+    ///
+    /// Byte `offsets[i]` of the frame is compared with `pattern[i]` when
+    /// `masks[i]` is `0xFF` and ignored when it is `0x00`. Returns the index
+    /// of the filter used.
+    pub fn add_flex_filter(
+        &mut self,
+        offsets: &[usize],
+        masks: &[u8],
+        pattern: &[u8],
+    ) -> Result<usize, DError> {
+        let index = (0..FLEX_FILTER_COUNT)
+            .find(|i| self.flex_filters & (1 << i) == 0)
+            .ok_or(DError::NoMemory)?;
+        filter::write_flex_filter(&mut self.mac, index, offsets, masks, pattern)?;
+        self.flex_filters |= 1 << index;
+        self.flex_queues[index] = None;
+        Ok(index)
+    }
+
+    pub fn remove_flex_filter(&mut self, index: usize) -> Result<(), DError> {
+        filter::disable_flex_filter(&mut self.mac, index)?;
+        self.flex_filters &= !(1 << index);
+        self.set_flex_filter_queue(index, None)
+    }
+
+    /// Receive frames matching flexible filter `index` on `queue` (below 8)
+    /// while in D0, or stop steering them with `None`.
+    pub fn set_flex_filter_queue(&mut self, index: usize, queue: Option<u8>) -> Result<(), DError> {
+        if index >= FLEX_FILTER_COUNT
+            || queue.is_some_and(|q| q as usize >= self.family.queue_count())
+        {
+            return Err(DError::InvalidParameter);
         }
+        let mut queues = self.flex_queues;
+        queues[index] = queue;
+        let host_queueing = queues.iter().any(Option::is_some);
+        filter::set_flex_filter_queue(&mut self.mac, index, queue, host_queueing)?;
+        self.flex_queues = queues;
+        Ok(())
+    }
+
+    /// Install EtherType filter `index`, or remove it with `None`.
+    ///
+    /// Matching frames report the filter in [`RxMeta::etype_filter`].
+    /// Takes effect immediately and is re-applied by [`Igb::open`].
+    pub fn set_etype_filter(
+        &mut self,
+        index: usize,
+        filter: Option<EtherTypeFilter>,
+    ) -> Result<(), DError> {
+        steer::write_etype_filter(&mut self.mac, self.family, index, filter.as_ref())?;
+        self.etype_filters[index] = filter;
+        Ok(())
+    }
+
+    pub fn etype_filter(&self, index: usize) -> Option<EtherTypeFilter> {
+        self.etype_filters.get(index).copied().flatten()
+    }
+
+    /// Install 2-tuple filter `index`, or remove it with `None`.
+    ///
+    /// Replaces the [`LliFilter`] of the same index, which uses the same
+    /// hardware slot. Takes effect immediately and is re-applied by
+    /// [`Igb::open`].
+    pub fn set_tuple_filter(
+        &mut self,
+        index: usize,
+        filter: Option<TupleFilter>,
+    ) -> Result<(), DError> {
+        steer::write_tuple_filter(&mut self.mac, self.family, index, filter.as_ref())?;
+        self.tuple_filters[index] = filter;
+        self.lli_filters[index] = None;
+        Ok(())
+    }
+
+    pub fn tuple_filter(&self, index: usize) -> Option<TupleFilter> {
+        self.tuple_filters.get(index).copied().flatten()
+    }
+
+    fn restore_queue_filters(&mut self) {
+        for (index, filter) in self.etype_filters.iter().enumerate() {
+            if let Some(filter) = filter
+                && let Err(e) =
+                    steer::write_etype_filter(&mut self.mac, self.family, index, Some(filter))
+            {
+                warn!("EtherType filter {index} not restored: {e:?}");
+            }
+        }
+        for (index, filter) in self.tuple_filters.iter().enumerate() {
+            if let Some(filter) = filter
+                && let Err(e) =
+                    steer::write_tuple_filter(&mut self.mac, self.family, index, Some(filter))
+            {
+                warn!("2-tuple filter {index} not restored: {e:?}");
+            }
+        }
+    }
+
+    /// Configure ARP / Neighbor Discovery proxying (I350/I210 only).
+    ///
+    /// Passing a default [`ProxyConfig`] disables proxying.
+    pub fn configure_proxy(&mut self, config: ProxyConfig) -> Result<(), DError> {
+        filter::write_proxy(&mut self.mac, &config)
+    }
+
+    /// Install low latency interrupt filter `index`, or remove it with
+    /// `None`. Matching packets interrupt immediately instead of waiting
+    /// for EITR throttling and are flagged in [`RxMeta::llint`].
+    ///
+    /// Replaces the [`TupleFilter`] of the same index, which uses the same
+    /// hardware slot. Takes effect immediately and is re-applied by
+    /// [`Igb::open`].
+    pub fn set_lli_filter(
+        &mut self,
+        index: usize,
+        filter: Option<LliFilter>,
+    ) -> Result<(), DError> {
+        if self.tuple_filters.get(index).is_some_and(Option::is_some) {
+            steer::write_tuple_filter(&mut self.mac, self.family, index, None)?;
+            self.tuple_filters[index] = None;
+        }
+        lli::write_filter(&mut self.mac, index, filter.as_ref())?;
+        self.lli_filters[index] = filter;
+        Ok(())
+    }
+
+    pub fn lli_filter(&self, index: usize) -> Option<LliFilter> {
+        self.lli_filters.get(index).copied().flatten()
+    }
+
+    /// Interrupt immediately for VLAN-tagged packets with a priority of at
+    /// least `priority` (0-7), or stop doing so with `None`.
+    pub fn set_lli_vlan_priority(&mut self, priority: Option<u8>) -> Result<(), DError> {
+        lli::write_vlan_priority(&mut self.mac, priority)?;
+        self.lli_vlan_priority = priority;
+        Ok(())
     }
 
+    /// Set interrupt throttling (EITR) of all vectors in use for `profile`,
+    /// and clear the legacy RX delay timer (RDTR).
+    ///
+    /// Pair it with [`RxRing::set_latency_profile`] or
+    /// [`RxLatencyProfile::rx_thresholds`] for the descriptor write-back
+    /// side. Re-applied by [`Igb::open`] and [`Igb::set_irq_mode`].
+    pub fn set_rx_latency_profile(&mut self, profile: RxLatencyProfile) {
+        latency::apply(&mut self.mac, self.family, self.irq_mode, profile);
+        self.rx_latency = Some(profile);
+    }
+
+    /// Go back to no interrupt throttling, as after reset, undoing
+    /// [`Igb::set_rx_latency_profile`].
+    pub fn clear_rx_latency_profile(&mut self) {
+        // 间隔为 0 即复位值
+        latency::apply(
+            &mut self.mac,
+            self.family,
+            self.irq_mode,
+            RxLatencyProfile::LowLatency,
+        );
+        self.rx_latency = None;
+    }
+
+    pub fn rx_latency_profile(&self) -> Option<RxLatencyProfile> {
+        self.rx_latency
+    }
+
+    /// Rate-limit low latency interrupts to one per `interval` × 4 µs
+    /// (up to [`LLI_MAX_INTERVAL`]) on every vector in use, or leave them
+    /// unlimited with `None`.
+    pub fn set_lli_moderation(&mut self, interval: Option<u8>) -> Result<(), DError> {
+        lli::apply_moderation(&mut self.mac, self.irq_mode, interval)?;
+        self.lli_moderation = interval;
+        Ok(())
+    }
+
+    fn restore_lli(&mut self) {
+        for (index, filter) in self.lli_filters.iter().enumerate() {
+            if let Some(filter) = filter
+                && let Err(e) = lli::write_filter(&mut self.mac, index, Some(filter))
+            {
+                warn!("LLI filter {index} not restored: {e:?}");
+            }
+        }
+        if let Err(e) = lli::write_vlan_priority(&mut self.mac, self.lli_vlan_priority) {
+            warn!("LLI VLAN priority not restored: {e:?}");
+        }
+    }
+
+    /// Inject an interrupt for EICR bit `vector` by writing EICS.
+    ///
+    /// In MSI/MSI-X mode this raises the same interrupt the hardware cause
+    /// would, which is useful to test the waker and OS interrupt routing.
+    pub fn trigger_interrupt(&mut self, vector: u8) -> Result<(), DError> {
+        if vector >= 32 {
+            return Err(DError::InvalidParameter);
+        }
+        self.mac.trigger_vectors(1 << vector);
+        Ok(())
+    }
+
+    /// Use legacy INTx interrupts: only ICR causes are enabled and
+    /// [`Igb::handle_interrupt`] decodes ICR instead of EICR.
+    ///
+    /// Call before [`Igb::open`].
     pub fn irq_mode_legacy(&mut self) {
-        self.mac.configure_legacy_mode();
+        self.irq_mode = IrqMode::Legacy;
+        self.apply_irq_mode();
+    }
+
+    fn apply_irq_mode(&mut self) {
+        match self.irq_mode {
+            IrqMode::Legacy | IrqMode::Msi => self.mac.configure_legacy_mode(),
+            IrqMode::MsiX { vectors } => {
+                self.mac.configure_msix_mode();
+                self.mac.map_other_vector(vectors - 1);
+            }
+        }
+        self.irq_ack = self.mac.irq_ack_policy();
+        if let Some(profile) = self.rx_latency {
+            latency::apply(&mut self.mac, self.family, self.irq_mode, profile);
+        }
+        // GPIE 被重写，LLI 限速需要重新配置
+        if let Err(e) = lli::apply_moderation(&mut self.mac, self.irq_mode, self.lli_moderation) {
+            warn!("LLI moderation not applied: {e:?}");
+        }
     }
 }
 
+/// Steps of [`Igb::open`] reported to [`Igb::set_open_tracer`], in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OpenPhase {
+    /// The MAC was reset and the receive addresses, filters and packet
+    /// buffers are restored.
+    ResetDone,
+    /// The PHY is powered up and auto-negotiation started.
+    PhyUp,
+    AutonegComplete,
+    /// Flow control is configured for the negotiated link.
+    FlowControlResolved,
+    /// Interrupts, RX and TX are enabled; `open` is about to succeed.
+    RxTxEnabled,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Speed {
     Mb10,
@@ -0,0 +1,165 @@
+use crate::{DError, irq::IrqMode, mac::Mac};
+
+const EITR: usize = 0x1680; // Extended Interrupt Throttle, one per vector
+const IMIR: usize = 0x5A80; // Immediate Interrupt RX, one per filter
+const IMIREXT: usize = 0x5AA0; // Immediate Interrupt RX Extended
+const IMIRVP: usize = 0x5AC0; // Immediate Interrupt RX VLAN Priority
+
+const EITR_LLI_EN: u32 = 1 << 15;
+
+const IMIR_PORT_IM_EN: u32 = 1 << 16; // 过滤器使能
+const IMIR_PORT_BP: u32 = 1 << 17; // 不检查端口
+
+const IMIREXT_SIZE_MASK: u32 = 0xFFF;
+const IMIREXT_SIZE_BP: u32 = 1 << 12;
+const IMIREXT_CTRL_URG: u32 = 1 << 13;
+const IMIREXT_CTRL_ACK: u32 = 1 << 14;
+const IMIREXT_CTRL_PSH: u32 = 1 << 15;
+const IMIREXT_CTRL_RST: u32 = 1 << 16;
+const IMIREXT_CTRL_SYN: u32 = 1 << 17;
+const IMIREXT_CTRL_FIN: u32 = 1 << 18;
+const IMIREXT_CTRL_BP: u32 = 1 << 19;
+
+const IMIRVP_PRIORITY_MASK: u32 = 0x7;
+const IMIRVP_EN: u32 = 1 << 3;
+
+/// Number of low latency interrupt filters.
+pub const LLI_FILTER_COUNT: usize = 8;
+/// Largest LLI credit interval, in 4 µs units.
+pub const LLI_MAX_INTERVAL: u8 = 31;
+
+/// A low latency interrupt filter, see [`crate::Igb::set_lli_filter`].
+///
+/// A received packet raises its interrupt immediately, bypassing EITR
+/// throttling, when it matches every condition that is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LliFilter {
+    /// TCP/UDP destination port.
+    pub port: Option<u16>,
+    /// Match packets shorter than this many bytes, up to 4095.
+    pub max_size: Option<u16>,
+    /// Match TCP segments with any of these `TCP_*` flags set, 0 for any.
+    pub tcp_flags: u8,
+}
+
+impl LliFilter {
+    pub const TCP_FIN: u8 = 0x01;
+    pub const TCP_SYN: u8 = 0x02;
+    pub const TCP_RST: u8 = 0x04;
+    pub const TCP_PSH: u8 = 0x08;
+    pub const TCP_ACK: u8 = 0x10;
+    pub const TCP_URG: u8 = 0x20;
+
+    fn imir(&self) -> u32 {
+        match self.port {
+            // 端口按网络字节序比较
+            Some(port) => port.to_be() as u32 | IMIR_PORT_IM_EN,
+            None => IMIR_PORT_BP | IMIR_PORT_IM_EN,
+        }
+    }
+
+    fn imirext(&self) -> Result<u32, DError> {
+        let mut value = match self.max_size {
+            Some(size) if size as u32 > IMIREXT_SIZE_MASK => {
+                return Err(DError::InvalidParameter);
+            }
+            Some(size) => size as u32,
+            None => IMIREXT_SIZE_BP,
+        };
+        if self.tcp_flags & !0x3F != 0 {
+            return Err(DError::InvalidParameter);
+        }
+        if self.tcp_flags == 0 {
+            return Ok(value | IMIREXT_CTRL_BP);
+        }
+        let ctrl = [
+            (Self::TCP_FIN, IMIREXT_CTRL_FIN),
+            (Self::TCP_SYN, IMIREXT_CTRL_SYN),
+            (Self::TCP_RST, IMIREXT_CTRL_RST),
+            (Self::TCP_PSH, IMIREXT_CTRL_PSH),
+            (Self::TCP_ACK, IMIREXT_CTRL_ACK),
+            (Self::TCP_URG, IMIREXT_CTRL_URG),
+        ];
+        for (flag, bit) in ctrl {
+            if self.tcp_flags & flag != 0 {
+                value |= bit;
+            }
+        }
+        Ok(value)
+    }
+}
+
+pub(crate) fn write_filter(
+    mac: &mut Mac,
+    index: usize,
+    filter: Option<&LliFilter>,
+) -> Result<(), DError> {
+    if index >= LLI_FILTER_COUNT {
+        return Err(DError::InvalidParameter);
+    }
+    match filter {
+        Some(filter) => {
+            let imirext = filter.imirext()?;
+            // 先写扩展条件，再使能过滤器
+            mac.write_reg(IMIREXT + index * 4, imirext);
+            mac.write_reg(IMIR + index * 4, filter.imir());
+        }
+        None => {
+            mac.write_reg(IMIR + index * 4, 0);
+            mac.write_reg(IMIREXT + index * 4, 0);
+        }
+    }
+    Ok(())
+}
+
+/// 只检查过滤条件，不访问寄存器
+pub(crate) fn check_filter(filter: &LliFilter) -> Result<(), DError> {
+    filter.imirext().map(drop)
+}
+
+pub(crate) fn check_vlan_priority(priority: Option<u8>) -> Result<(), DError> {
+    if priority.is_some_and(|p| p as u32 > IMIRVP_PRIORITY_MASK) {
+        return Err(DError::InvalidParameter);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_moderation(interval: Option<u8>) -> Result<(), DError> {
+    if interval.is_some_and(|i| i > LLI_MAX_INTERVAL) {
+        return Err(DError::InvalidParameter);
+    }
+    Ok(())
+}
+
+/// VLAN 优先级不低于 `priority` 的包立即中断
+pub(crate) fn write_vlan_priority(mac: &mut Mac, priority: Option<u8>) -> Result<(), DError> {
+    check_vlan_priority(priority)?;
+    let value = match priority {
+        Some(p) => p as u32 | IMIRVP_EN,
+        None => 0,
+    };
+    mac.write_reg(IMIRVP, value);
+    Ok(())
+}
+
+/// 为所用的中断向量打开 EITR.LLI_EN，LLI 按 GPIE.LL_Interval 的信用限速
+pub(crate) fn apply_moderation(
+    mac: &mut Mac,
+    mode: IrqMode,
+    interval: Option<u8>,
+) -> Result<(), DError> {
+    check_moderation(interval)?;
+    mac.set_ll_interval(interval.unwrap_or(0));
+    let vectors = match mode {
+        IrqMode::MsiX { vectors } => vectors as usize,
+        IrqMode::Legacy | IrqMode::Msi => 1,
+    };
+    for vector in 0..vectors {
+        let reg = EITR + vector * 4;
+        let eitr = mac.read_reg(reg) & !EITR_LLI_EN;
+        let lli_en = if interval.is_some() { EITR_LLI_EN } else { 0 };
+        mac.write_reg(reg, eitr | lli_en);
+    }
+    Ok(())
+}
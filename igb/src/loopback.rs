@@ -0,0 +1,73 @@
+use crate::{family::DeviceFamily, mac::Mac};
+
+const SCTL: usize = 0x0024; // SerDes Control
+const CONNSW: usize = 0x0034; // Copper/Fiber Switch Control
+const PCS_CFG0: usize = 0x4200; // PCS Configuration
+const PCS_LCTL: usize = 0x4208; // PCS Link Control
+
+const SCTL_ENABLE_LOOPBACK: u32 = 0x0410;
+const SCTL_DISABLE_LOOPBACK: u32 = 0x0400;
+const CONNSW_ENRGSRC: u32 = 1 << 2;
+const PCS_CFG_IGN_SD: u32 = 1 << 0;
+const PCS_LCTL_FLV_LINK_UP: u32 = 1 << 0;
+const PCS_LCTL_FSV_1000: u32 = 1 << 2;
+const PCS_LCTL_FDV_FULL: u32 = 1 << 3;
+const PCS_LCTL_FSD: u32 = 1 << 4;
+const PCS_LCTL_FORCE_LINK: u32 = 1 << 5;
+const PCS_LCTL_AN_ENABLE: u32 = 1 << 16;
+const PCS_LCTL_FORCED: u32 = PCS_LCTL_FLV_LINK_UP
+    | PCS_LCTL_FSV_1000
+    | PCS_LCTL_FDV_FULL
+    | PCS_LCTL_FSD
+    | PCS_LCTL_FORCE_LINK;
+
+/// Where transmitted frames are looped back, see [`crate::Igb::set_loopback`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LoopbackMode {
+    /// Normal operation.
+    #[default]
+    None,
+    /// Inside the MAC, before the PHY (RCTL.LBM).
+    Mac,
+    /// Inside the copper PHY, exercising the MAC/PHY interface at a forced
+    /// 1000 Mb/s full duplex.
+    Phy,
+    /// Inside the SerDes transceiver, for SerDes and SGMII ports.
+    Serdes,
+}
+
+/// 参考 igb_setup_loopback_test：强制 PCS 1000 Mb/s 全双工并在 SerDes 内环回
+pub(crate) fn enable_serdes(mac: &mut Mac, family: DeviceFamily) {
+    mac.enable_transceiver_loopback();
+    mac.write_reg(SCTL, SCTL_ENABLE_LOOPBACK);
+    mac.set_flow_control(false, false);
+    mac.set_link_up();
+
+    // 不再根据能量检测切换铜缆/SerDes
+    let connsw = mac.read_reg(CONNSW);
+    mac.write_reg(CONNSW, connsw & !CONNSW_ENRGSRC);
+
+    // 环回时没有光信号，82580 之后需要忽略信号检测
+    if family.has_pcs_ignore_sd() {
+        let cfg = mac.read_reg(PCS_CFG0);
+        mac.write_reg(PCS_CFG0, cfg | PCS_CFG_IGN_SD);
+    }
+
+    let lctl = mac.read_reg(PCS_LCTL) & !PCS_LCTL_AN_ENABLE;
+    mac.write_reg(PCS_LCTL, lctl | PCS_LCTL_FORCED);
+}
+
+/// 撤销 SerDes 环回并恢复 PCS 自协商
+pub(crate) fn disable_serdes(mac: &mut Mac, family: DeviceFamily) {
+    mac.disable_loopback();
+    mac.write_reg(SCTL, SCTL_DISABLE_LOOPBACK);
+
+    if family.has_pcs_ignore_sd() {
+        let cfg = mac.read_reg(PCS_CFG0);
+        mac.write_reg(PCS_CFG0, cfg & !PCS_CFG_IGN_SD);
+    }
+
+    let lctl = mac.read_reg(PCS_LCTL) & !PCS_LCTL_FORCED;
+    mac.write_reg(PCS_LCTL, lctl | PCS_LCTL_AN_ENABLE);
+}
@@ -1,10 +1,22 @@
-use core::{fmt::Debug, ptr::NonNull, time::Duration};
+use core::{fmt::Debug, ptr::NonNull, str::FromStr, time::Duration};
 
-use log::error;
+use log::{debug, error, warn};
 use mbarrier::mb;
-use tock_registers::{interfaces::*, register_bitfields, register_structs, registers::*};
+use tock_registers::{
+    fields::FieldValue, interfaces::*, register_bitfields, register_structs, registers::*,
+};
 
-use crate::{DError, Speed, osal::wait_for};
+use crate::{DError, FcSource, IrqAckPolicy, Speed, osal::wait_for};
+
+const COLLISION_DISTANCE: u32 = 63;
+
+/// Size of the register space the driver touches. BAR0 of 82576/I350/I210
+/// is 128 KiB; the per-queue statistics at 0x10000 are the highest used.
+pub const REG_SPAN: usize = 0x20000;
+
+/// Receive address slots besides RAR0 on the part with the most (I350),
+/// see [`DeviceFamily::rar_count`](crate::DeviceFamily::rar_count).
+pub const MAC_FILTER_COUNT: usize = 31;
 
 register_structs! {
     pub MacRegister {
@@ -28,6 +40,7 @@ register_structs! {
         (0x404 => _rsv12),
         (0x1514 => gpie: ReadWrite<u32, GPIE::Register>),
         (0x1518 => _rsv16),
+        (0x1520 => eics: WriteOnly<u32>),
         (0x1524 => eims: ReadWrite<u32>),
         (0x1528 => eimc: ReadWrite<u32>),
         (0x152c => eiac: ReadWrite<u32>),
@@ -35,14 +48,22 @@ register_structs! {
         (0x1534 => _rsv5),
         (0x1580 => eicr: ReadWrite<u32>),
         (0x1584 => _rsv6),
+        (0x1700 => ivar: [ReadWrite<u32>; 8]),
+        (0x1720 => _rsv17),
+        (0x1740 => ivar_misc: ReadWrite<u32>),
+        (0x1744 => _rsv18),
         (0x5400 => ralh_0_15: [ReadWrite<u32>; 32]),
         (0x5480 => _rsv8),
         (0x54e0 => ralh_16_23: [ReadWrite<u32>;32]),
         (0x5560 => _rsv9),
+        (0x5820 => manc: ReadWrite<u32, MANC::Register>),
+        (0x5824 => _rsv19),
+        (0x5860 => manc2h: ReadWrite<u32>),
+        (0x5864 => _rsv20),
         (0x5B50 => swsm: ReadWrite<u32, SWSM::Register>),
-        (0x5B54 => fwsm: ReadWrite<u32>),
+        (0x5B54 => fwsm: ReadWrite<u32, FWSM::Register>),
         (0x5B58 => _rsv10),
-        (0x5B5C => sw_fw_sync: ReadWrite<u32>),
+        (0x5B5C => sw_fw_sync: ReadWrite<u32, SW_FW_SYNC::Register>),
         (0x5B60 => _rsv11),
 
         // The end of the struct is marked as follows.
@@ -50,6 +71,21 @@ register_structs! {
     }
 }
 
+/// SW/FW shared resources, see [`Mac::software_sync_aquire`].
+pub type SyncFlags = FieldValue<u32, SW_FW_SYNC::Register>;
+
+const MANC2H_PORT_623: u32 = 1 << 5;
+const MANC2H_PORT_664: u32 = 1 << 6;
+
+const IVAR_INT_ALLOC_MASK: u32 = 0x1F;
+const IVAR_VALID: u32 = 1 << 7;
+const RAH_AV: u32 = 1 << 31;
+
+const EEC: usize = 0x10; // EEPROM/Flash Control
+const EEC_AUTO_RD: u32 = 1 << 9;
+const EEMNGCTL: usize = 0x1010; // Management EEPROM Control
+const EEMNGCTL_CFG_DONE0: u32 = 1 << 18;
+
 register_bitfields! [
     // First parameter is the register width. Can be u8, u16, u32, or u64.
     u32,
@@ -59,6 +95,7 @@ register_bitfields! [
             HalfDuplex = 0,
             FullDuplex = 1,
         ],
+        GIO_MASTER_DISABLE OFFSET(2) NUMBITS(1)[],
         SLU OFFSET(6) NUMBITS(1)[],
         SPEED OFFSET(8) NUMBITS(2)[
             Speed10 = 0,
@@ -71,6 +108,8 @@ register_bitfields! [
             Normal = 0,
             Reset = 1,
         ],
+        RFCE OFFSET(27) NUMBITS(1)[],
+        TFCE OFFSET(28) NUMBITS(1)[],
         PHY_RST OFFSET(31) NUMBITS(1)[],
     ],
     STATUS [
@@ -79,14 +118,17 @@ register_bitfields! [
             FullDuplex = 1,
         ],
         LU OFFSET(1) NUMBITS(1)[],
+        FUNC_ID OFFSET(2) NUMBITS(2)[],
         SPEED OFFSET(6) NUMBITS(2)[
             Speed10 = 0,
             Speed100 = 1,
             Speed1000 = 0b10,
         ],
          PHYRA OFFSET(10) NUMBITS(1)[],
+        GIO_MASTER_ENABLE OFFSET(19) NUMBITS(1)[],
     ],
     pub CTRL_EXT [
+        DRV_LOAD OFFSET(28) NUMBITS(1)[],
         LINK_MODE OFFSET(22) NUMBITS(2)[
             DircetCooper = 0,
             SGMII = 0b10,
@@ -120,7 +162,26 @@ register_bitfields! [
         EEUR OFFSET(3) NUMBITS(1)[],
     ],
 
-    SW_FW_SYNC [
+    FWSM [
+        MODE OFFSET(1) NUMBITS(3)[
+            None = 0,
+            Asf = 1,
+            PassThrough = 2,
+            Ipmi = 3,
+            HostInterface = 4,
+        ],
+        FW_VAL OFFSET(15) NUMBITS(1)[],
+    ],
+
+    // Management Control Register - MANC (0x5820)
+    MANC [
+        ARP_EN OFFSET(13) NUMBITS(1)[],
+        RCV_TCO_EN OFFSET(17) NUMBITS(1)[],
+        BLK_PHY_RST_ON_IDE OFFSET(18) NUMBITS(1)[],
+        EN_MNG2HOST OFFSET(21) NUMBITS(1)[],
+    ],
+
+    pub SW_FW_SYNC [
         SW_EEP_SM OFFSET(0) NUMBITS(1)[],
         SW_PHY_SM0 OFFSET(1) NUMBITS(1)[],
         SW_PHY_SM1 OFFSET(2) NUMBITS(1)[],
@@ -158,7 +219,7 @@ register_bitfields! [
         LBM OFFSET(6) NUMBITS(2)[
             Normal = 0b00,
             MacLoopback = 0b01,
-            Reserved = 0b11,
+            TransceiverLoopback = 0b11,
         ],
         MO OFFSET(12) NUMBITS(2)[
             Bits47_36 = 0b00,
@@ -194,8 +255,8 @@ register_bitfields! [
             Discard = 1,
         ],
         PMCF OFFSET(23) NUMBITS(1)[
-            Pass = 0,
-            Filter = 1,
+            Filter = 0,
+            Pass = 1,
         ],
         SECRC OFFSET(26) NUMBITS(1)[
             DoNotStrip = 0,
@@ -272,7 +333,7 @@ register_bitfields! [
         SRPD OFFSET(16) NUMBITS(1)[],  // Small Receive Packet Detected
         ACK OFFSET(17) NUMBITS(1)[],   // Receive ACK Frame
         MNG OFFSET(18) NUMBITS(1)[],   // Management Bus Interrupt
-        DOCK OFFSET(19) NUMBITS(1)[],  // Dock/Undock Status Change
+        TS OFFSET(19) NUMBITS(1)[],    // Time Sync (82576 and later)
         INT_ASSERTED OFFSET(31) NUMBITS(1)[], // Interrupt Asserted
     ],
 
@@ -295,7 +356,7 @@ register_bitfields! [
         SRPD OFFSET(16) NUMBITS(1)[],  // Small Receive Packet Detected
         ACK OFFSET(17) NUMBITS(1)[],   // Receive ACK Frame
         MNG OFFSET(18) NUMBITS(1)[],   // Management Bus Interrupt
-        DOCK OFFSET(19) NUMBITS(1)[],  // Dock/Undock Status Change
+        TS OFFSET(19) NUMBITS(1)[],    // Time Sync (82576 and later)
     ],
 
     // Legacy Interrupt Mask Clear - IMC (0x000D8)
@@ -317,7 +378,7 @@ register_bitfields! [
         SRPD OFFSET(16) NUMBITS(1)[],  // Small Receive Packet Detected
         ACK OFFSET(17) NUMBITS(1)[],   // Receive ACK Frame
         MNG OFFSET(18) NUMBITS(1)[],   // Management Bus Interrupt
-        DOCK OFFSET(19) NUMBITS(1)[],  // Dock/Undock Status Change
+        TS OFFSET(19) NUMBITS(1)[],    // Time Sync (82576 and later)
     ],
 
     // General Purpose Interrupt Enable - GPIE (0x1514)
@@ -356,45 +417,86 @@ impl Mac {
         self.reg.cast()
     }
 
-    pub fn write_mdic(&self, phys_addr: u32, offset: u32, data: u16) -> Result<(), DError> {
-        self.reg().mdic.write(
-            MDIC::REGADDR.val(offset)
-                + MDIC::PHY_ADDR.val(phys_addr)
-                + MDIC::DATA.val(data as _)
-                + MDIC::OP::Write,
-        );
-        mb();
+    fn mdic_destination(external: bool) -> FieldValue<u32, MDIC::Register> {
+        if external {
+            MDIC::Destination::External
+        } else {
+            MDIC::Destination::Internal
+        }
+    }
 
-        loop {
-            let mdic = self.reg().mdic.extract();
+    /// Start an MDIO transaction; `interrupt` raises MDAC when it completes.
+    pub fn mdic_start(&self, cmd: MdicCmd, interrupt: bool) {
+        let (op, data) = match cmd.write {
+            Some(data) => (MDIC::OP::Write, data),
+            None => (MDIC::OP::Read, 0),
+        };
+        let mut val = MDIC::REGADDR.val(cmd.offset)
+            + MDIC::PHY_ADDR.val(cmd.phys_addr)
+            + MDIC::DATA.val(data as _)
+            + op
+            + Self::mdic_destination(cmd.external);
+        if interrupt {
+            val += MDIC::I::SET;
+        }
+        self.reg().mdic.write(val);
+        mb();
+    }
 
-            if mdic.is_set(MDIC::READY) {
-                break;
-            }
-            if mdic.is_set(MDIC::E) {
-                error!("MDIC read error");
-                return Err(DError::Unknown("MDIC read error"));
-            }
+    /// Result of the last MDIO transaction, `None` while it is in progress.
+    pub fn mdic_poll(&self) -> Option<Result<u16, DError>> {
+        let mdic = self.reg().mdic.extract();
+        if mdic.is_set(MDIC::E) {
+            error!("MDIC access error");
+            return Some(Err(DError::Unknown("MDIC access error")));
+        }
+        if mdic.is_set(MDIC::READY) {
+            return Some(Ok(mdic.read(MDIC::DATA) as _));
         }
+        None
+    }
 
-        Ok(())
+    fn mdic_wait(&self) -> Result<u16, DError> {
+        let mut result = None;
+        // 最多等待约 100ms，防止 MDIO 总线卡死时挂起
+        wait_for(
+            "MDIO access",
+            || {
+                result = self.mdic_poll();
+                result.is_some()
+            },
+            Duration::from_micros(50),
+            Some(1920),
+        )?;
+        result.unwrap()
     }
 
-    pub fn read_mdic(&self, phys_addr: u32, offset: u32) -> Result<u16, DError> {
-        self.reg()
-            .mdic
-            .write(MDIC::REGADDR.val(offset) + MDIC::PHY_ADDR.val(phys_addr) + MDIC::OP::Read);
-        mb();
-        loop {
-            let mdic = self.reg().mdic.extract();
-            if mdic.is_set(MDIC::READY) {
-                return Ok(mdic.read(MDIC::DATA) as _);
-            }
-            if mdic.is_set(MDIC::E) {
-                error!("MDIC read error");
-                return Err(DError::Unknown("MDIC read error"));
-            }
-        }
+    pub fn write_mdic(
+        &self,
+        phys_addr: u32,
+        offset: u32,
+        data: u16,
+        external: bool,
+    ) -> Result<(), DError> {
+        let cmd = MdicCmd {
+            phys_addr,
+            offset,
+            external,
+            write: Some(data),
+        };
+        self.mdic_start(cmd, false);
+        self.mdic_wait().map(|_| ())
+    }
+
+    pub fn read_mdic(&self, phys_addr: u32, offset: u32, external: bool) -> Result<u16, DError> {
+        let cmd = MdicCmd {
+            phys_addr,
+            offset,
+            external,
+            write: None,
+        };
+        self.mdic_start(cmd, false);
+        self.mdic_wait()
     }
 
     pub fn disable_interrupts(&mut self) {
@@ -402,10 +504,6 @@ impl Mac {
         self.clear_interrupts();
     }
 
-    pub fn enable_interrupts(&mut self) {
-        self.reg_mut().eims.set(u32::MAX);
-    }
-
     /// Enable legacy interrupts
     pub fn enable_legacy_interrupts(&mut self) {
         // Enable common legacy interrupts
@@ -413,6 +511,7 @@ impl Mac {
             IMS::TXDW::SET
                 + IMS::TXQE::SET
                 + IMS::LSC::SET
+                + IMS::RXSEQ::SET
                 + IMS::RXDMT0::SET
                 + IMS::RXO::SET
                 + IMS::RXT0::SET
@@ -420,6 +519,14 @@ impl Mac {
         );
     }
 
+    /// Mask the TXDW, TXQE and RXT0 causes that all queues share in
+    /// legacy mode.
+    pub fn mask_legacy_queue_causes(&mut self) {
+        self.reg_mut()
+            .imc
+            .write(IMC::TXDW::SET + IMC::TXQE::SET + IMC::RXT0::SET);
+    }
+
     /// Disable legacy interrupts
     pub fn disable_legacy_interrupts(&mut self) {
         // Disable all legacy interrupts
@@ -441,7 +548,7 @@ impl Mac {
                 + IMC::SRPD::SET
                 + IMC::ACK::SET
                 + IMC::MNG::SET
-                + IMC::DOCK::SET,
+                + IMC::TS::SET,
         );
     }
 
@@ -461,24 +568,133 @@ impl Mac {
             rxt0: status & ICR::RXT0.mask != 0,
             mdac: status & ICR::MDAC.mask != 0,
             rxcfg: status & ICR::RXCFG.mask != 0,
+            time_sync: status & ICR::TS.mask != 0,
             asserted: status & ICR::INT_ASSERTED.mask != 0,
         }
     }
 
-    pub fn interrupts_ack(&mut self) -> IrqMsg {
-        let eicr = self.reg().eicr.get();
+    pub fn interrupts_ack(&mut self, policy: IrqAckPolicy) -> IrqMsg {
+        let eicr = self.ack_eicr(policy);
         let eims = self.reg().eims.get();
         let status = eicr & eims;
         let tcp_timer = status & EICR::TCP_Timer.mask != 0;
         let other = status & EICR::Other_Cause.mask != 0;
         let queue_idx = (status & EICR::RxTxQ.mask) as u16;
-        IrqMsg {
-            queue_idx,
-            tcp_timer,
-            other,
+        // Other causes are reported through ICR
+        let icr = if other { self.reg().icr.get() } else { 0 };
+        IrqMsg::new(queue_idx, tcp_timer, other, icr)
+    }
+
+    /// Read and clear extended interrupt causes in MSI-X mode, where each EICR
+    /// bit is a vector and `other_vector` carries the non-queue causes.
+    pub fn msix_interrupts_ack(&mut self, other_vector: u8, policy: IrqAckPolicy) -> IrqMsg {
+        // 自动屏蔽时 EIMS 已被清除，因此这里不与 EIMS 相与
+        let status = self.ack_eicr(policy);
+        let other_mask = 1 << other_vector;
+        let other = status & other_mask != 0;
+        let queue_idx = (status & !other_mask & EICR::RxTxQ.mask) as u16;
+        let icr = if other { self.reg().icr.get() } else { 0 };
+        IrqMsg::new(queue_idx, false, other, icr)
+    }
+
+    /// Clear only the EICR bit of MSI-X vector `vector`. For the vector
+    /// carrying the "other" causes, also read and clear ICR.
+    pub fn msix_vector_ack(&mut self, vector: u8, other: bool) -> IrqMsg {
+        let bit = 1u32 << vector;
+        self.reg_mut().eicr.set(bit);
+        if other {
+            let icr = self.reg().icr.get();
+            IrqMsg::new(0, false, true, icr)
+        } else {
+            IrqMsg::new((bit & EICR::RxTxQ.mask) as u16, false, false, 0)
         }
     }
 
+    /// How reads and writes of EICR acknowledge causes with the current GPIE.
+    pub fn irq_ack_policy(&self) -> IrqAckPolicy {
+        let gpie = self.reg().gpie.extract();
+        if gpie.matches_all(GPIE::Multiple_MSIX::MultipleVectors) {
+            IrqAckPolicy::WriteToClear
+        } else if gpie.matches_all(GPIE::NSICR::ClearOnRead) {
+            IrqAckPolicy::ClearOnRead
+        } else {
+            IrqAckPolicy::ClearOnReadWhenAsserted
+        }
+    }
+
+    /// 读取并清除 EICR：读清除模式下读一次即可，否则写 1 清除
+    fn ack_eicr(&mut self, policy: IrqAckPolicy) -> u32 {
+        let status = self.reg().eicr.get();
+        if policy != IrqAckPolicy::ClearOnRead && status != 0 {
+            self.reg_mut().eicr.set(status);
+        }
+        status
+    }
+
+    /// Route the "other" causes (link change, etc.) to MSI-X vector `vector`.
+    pub fn map_other_vector(&mut self, vector: u8) {
+        let entry = ((vector as u32) & IVAR_INT_ALLOC_MASK) | IVAR_VALID;
+        self.reg_mut().ivar_misc.set(entry << 8);
+    }
+
+    /// Enable link status change reporting through the "other" cause.
+    pub fn enable_link_interrupt(&mut self) {
+        self.reg_mut().ims.write(IMS::LSC::SET);
+    }
+
+    /// Report RX overrun, descriptor minimum threshold and sequence error
+    /// through the "other" cause.
+    pub fn enable_rx_error_interrupts(&mut self) {
+        self.reg_mut()
+            .ims
+            .write(IMS::RXO::SET + IMS::RXDMT0::SET + IMS::RXSEQ::SET);
+    }
+
+    /// Enable the MDI/O access complete interrupt.
+    pub fn enable_mdac_interrupt(&mut self) {
+        self.reg_mut().ims.write(IMS::MDAC::SET);
+    }
+
+    /// Enable time sync events (TSICR) through the "other" cause.
+    pub fn enable_time_sync_interrupt(&mut self) {
+        self.reg_mut().ims.write(IMS::TS::SET);
+    }
+
+    /// MSI-X vector of the "other" causes, `None` outside MSI-X mode.
+    pub fn other_vector(&self) -> Option<u8> {
+        if !self
+            .reg()
+            .gpie
+            .matches_all(GPIE::Multiple_MSIX::MultipleVectors)
+        {
+            return None;
+        }
+        let entry = self.reg().ivar_misc.get() >> 8;
+        (entry & IVAR_VALID != 0).then_some((entry & IVAR_INT_ALLOC_MASK) as u8)
+    }
+
+    /// Map the RX or TX cause of `queue` onto EICR bit / MSI-X vector `vector`.
+    ///
+    /// Fails with [`DError::InvalidParameter`] for the vector of the "other"
+    /// causes, which would hide queue interrupts behind link events.
+    pub fn map_queue_vector(
+        &mut self,
+        queue: usize,
+        vector: u8,
+        is_tx: bool,
+    ) -> Result<(), DError> {
+        if self.other_vector() == Some(vector) {
+            return Err(DError::InvalidParameter);
+        }
+        // IVAR[n]: RX n [7:0], TX n [15:8], RX n+8 [23:16], TX n+8 [31:24]
+        let shift = (queue / 8) * 16 + if is_tx { 8 } else { 0 };
+        let entry = ((vector as u32) & IVAR_INT_ALLOC_MASK) | IVAR_VALID;
+        let ivar = &self.reg_mut().ivar[queue % 8];
+        let val = (ivar.get() & !(0xFF << shift)) | (entry << shift);
+        ivar.set(val);
+        Ok(())
+    }
+
     pub fn link_mode(&self) -> Option<LinkMode> {
         Some(
             match self.reg().ctrl_ext.read_as_enum(CTRL_EXT::LINK_MODE) {
@@ -498,20 +714,170 @@ impl Mac {
     }
 
     pub fn reset(&mut self) -> Result<(), DError> {
-        self.reg_mut()
-            .ctrl
-            .modify(CTRL::RST::Reset + CTRL::PHY_RST::SET);
+        if self.manageability_status().phy_reset_blocked {
+            // PHY 与 BMC 共享，不能复位 PHY
+            self.reg_mut().ctrl.modify(CTRL::RST::Reset);
+        } else {
+            self.reg_mut()
+                .ctrl
+                .modify(CTRL::RST::Reset + CTRL::PHY_RST::SET);
+        }
         wait_for(
+            "MAC reset",
             || self.reg().ctrl.matches_any(&[CTRL::RST::Normal]),
             Duration::from_millis(1),
             Some(1000),
         )
     }
 
+    pub fn manageability_status(&self) -> ManageabilityStatus {
+        let mode = match self.reg().fwsm.read_as_enum(FWSM::MODE) {
+            Some(FWSM::MODE::Value::Asf) => ManageabilityMode::Asf,
+            Some(FWSM::MODE::Value::PassThrough) => ManageabilityMode::PassThrough,
+            Some(FWSM::MODE::Value::Ipmi) => ManageabilityMode::Ipmi,
+            Some(FWSM::MODE::Value::HostInterface) => ManageabilityMode::HostInterface,
+            _ => ManageabilityMode::None,
+        };
+        let manc = self.reg().manc.extract();
+        ManageabilityStatus {
+            mode,
+            receive_tco: manc.is_set(MANC::RCV_TCO_EN),
+            to_host: manc.is_set(MANC::EN_MNG2HOST),
+            phy_reset_blocked: manc.is_set(MANC::BLK_PHY_RST_ON_IDE),
+        }
+    }
+
+    /// Let the host keep seeing management traffic while the BMC is active:
+    /// stop the firmware from answering ARP and forward RMCP ports to the host.
+    pub fn init_manageability(&mut self) {
+        if !self.manageability_status().receive_tco {
+            return;
+        }
+        self.reg_mut()
+            .manc
+            .modify(MANC::ARP_EN::CLEAR + MANC::EN_MNG2HOST::SET);
+        let manc2h = self.reg().manc2h.get();
+        self.reg_mut()
+            .manc2h
+            .set(manc2h | MANC2H_PORT_623 | MANC2H_PORT_664);
+    }
+
+    /// Acquire the SWSM hardware semaphore that guards SW_FW_SYNC.
+    pub fn software_semaphore_aquire(&self) -> Result<(), DError> {
+        // 读 SWSM 时硬件会自动置位 SMBI，读到 0 即获得
+        wait_for(
+            "SWSM semaphore",
+            || !self.reg().swsm.is_set(SWSM::SMBI),
+            Duration::from_micros(50),
+            Some(2000),
+        )?;
+        let res = wait_for(
+            "SWSM firmware semaphore",
+            || {
+                self.reg().swsm.modify(SWSM::SWESMBI::SET);
+                self.reg().swsm.is_set(SWSM::SWESMBI)
+            },
+            Duration::from_micros(50),
+            Some(2000),
+        );
+        if res.is_err() {
+            error!("Failed to acquire SWESMBI");
+            self.software_semaphore_release();
+        }
+        res
+    }
+
+    pub fn software_semaphore_release(&self) {
+        self.reg()
+            .swsm
+            .modify(SWSM::SMBI::CLEAR + SWSM::SWESMBI::CLEAR);
+    }
+
+    /// Take the software side of the SW/FW resources in `flags`, waiting
+    /// until neither firmware nor another driver instance holds them.
+    pub fn software_sync_aquire(&self, flags: SyncFlags) -> Result<u32, DError> {
+        let sw_mask = flags.value;
+        let fw_mask = sw_mask << 16;
+        for _ in 0..200 {
+            self.software_semaphore_aquire()?;
+            let sync = self.reg().sw_fw_sync.get();
+            if sync & (sw_mask | fw_mask) == 0 {
+                self.reg().sw_fw_sync.set(sync | sw_mask);
+                self.software_semaphore_release();
+                return Ok(sw_mask);
+            }
+            self.software_semaphore_release();
+            crate::osal::kernel::sleep(Duration::from_millis(5));
+        }
+        Err(DError::TimedOut {
+            op: "SW_FW_SYNC",
+            elapsed: Duration::from_millis(5 * 200),
+        })
+    }
+
+    pub fn software_sync_release(&self, mask: u32) {
+        // 释放时即使拿不到信号量也要清除自己的位
+        let locked = self.software_semaphore_aquire().is_ok();
+        let sync = self.reg().sw_fw_sync.get();
+        self.reg().sw_fw_sync.set(sync & !mask);
+        if locked {
+            self.software_semaphore_release();
+        }
+    }
+
+    /// Tell the firmware whether a driver owns the device (CTRL_EXT.DRV_LOAD).
+    pub fn set_driver_loaded(&mut self, loaded: bool) {
+        if loaded {
+            self.reg_mut().ctrl_ext.modify(CTRL_EXT::DRV_LOAD::SET);
+        } else {
+            self.reg_mut().ctrl_ext.modify(CTRL_EXT::DRV_LOAD::CLEAR);
+        }
+    }
+
+    /// Hand ARP handling back to the firmware.
+    pub fn release_manageability(&mut self) {
+        if self.manageability_status().receive_tco {
+            self.reg_mut().manc.modify(MANC::ARP_EN::SET);
+        }
+    }
+
     pub fn set_link_up(&mut self) {
         self.reg_mut().ctrl.modify(CTRL::SLU::SET + CTRL::FD::SET);
     }
 
+    /// Check that a device answers at the mapping before anything is written.
+    pub fn probe(&self) -> Result<(), DError> {
+        // 设备不存在或掉链时 MMIO 读全 1
+        let status = self.read_reg(0x8);
+        let ctrl = self.read_reg(0x0);
+        if status == u32::MAX || ctrl == u32::MAX {
+            return Err(DError::NoDevice);
+        }
+        Ok(())
+    }
+
+    /// Read a register outside of [`MacRegister`] by byte offset.
+    pub fn read_reg(&self, offset: usize) -> u32 {
+        unsafe {
+            self.reg
+                .cast::<u8>()
+                .add(offset)
+                .cast::<u32>()
+                .read_volatile()
+        }
+    }
+
+    /// Write a register outside of [`MacRegister`] by byte offset.
+    pub fn write_reg(&mut self, offset: usize, value: u32) {
+        unsafe {
+            self.reg
+                .cast::<u8>()
+                .add(offset)
+                .cast::<u32>()
+                .write_volatile(value)
+        }
+    }
+
     pub fn reg(&self) -> &MacRegister {
         unsafe { self.reg.as_ref() }
     }
@@ -520,23 +886,52 @@ impl Mac {
     }
 
     pub fn read_mac(&self) -> [u8; 6] {
-        let low = self.ral(0);
-        let high = self.rah(0);
+        self.read_rar(0).0
+    }
 
-        [
+    /// Receive address `i` and its Address Valid bit.
+    pub fn read_rar(&self, i: usize) -> ([u8; 6], bool) {
+        let low = self.ral(i);
+        let high = self.rah(i);
+
+        let addr = [
             (low & 0xff) as u8,
             ((low >> 8) & 0xff) as u8,
             ((low >> 16) & 0xff) as u8,
             (low >> 24) as u8,
             (high & 0xff) as u8,
             ((high >> 8) & 0xff) as u8,
-        ]
+        ];
+        (addr, high & RAH_AV != 0)
     }
 
     pub fn disable_rx(&mut self) {
         self.reg_mut().rctl.modify(RCTL::RXEN::Disabled);
     }
 
+    /// Program RCTL.BSIZE with the largest size not above `bytes`.
+    ///
+    /// Only queues using legacy descriptors, or advanced ones with
+    /// SRRCTL.BSIZEPACKET left at 0, take their buffer size from here.
+    pub fn set_rx_buffer_size(&mut self, bytes: usize) {
+        let bsize = match bytes {
+            2048.. => RCTL::BSIZE::Bytes2048,
+            1024.. => RCTL::BSIZE::Bytes1024,
+            512.. => RCTL::BSIZE::Bytes512,
+            _ => RCTL::BSIZE::Bytes256,
+        };
+        self.reg_mut().rctl.modify(bsize);
+    }
+
+    pub fn rx_buffer_size(&self) -> usize {
+        match self.reg().rctl.read_as_enum(RCTL::BSIZE) {
+            Some(RCTL::BSIZE::Value::Bytes1024) => 1024,
+            Some(RCTL::BSIZE::Value::Bytes512) => 512,
+            Some(RCTL::BSIZE::Value::Bytes256) => 256,
+            _ => 2048,
+        }
+    }
+
     pub fn enable_rx(&mut self) {
         self.reg_mut().rctl.modify(RCTL::RXEN::Enabled);
     }
@@ -545,14 +940,180 @@ impl Mac {
         self.reg_mut().tctl.modify(TCTL::EN::Enabled);
     }
 
+    pub fn disable_tx(&mut self) {
+        self.reg_mut().tctl.modify(TCTL::EN::Disabled);
+    }
+
+    /// Honour received PAUSE frames (RFCE) and send them when the RX packet
+    /// buffer crosses the watermarks (TFCE).
+    pub fn set_flow_control(&mut self, rx_pause: bool, tx_pause: bool) {
+        let rfce = if rx_pause {
+            CTRL::RFCE::SET
+        } else {
+            CTRL::RFCE::CLEAR
+        };
+        let tfce = if tx_pause {
+            CTRL::TFCE::SET
+        } else {
+            CTRL::TFCE::CLEAR
+        };
+        self.reg_mut().ctrl.modify(rfce + tfce);
+    }
+
+    /// Transmit one PAUSE frame with the FCTTV pause time now.
+    pub fn send_xoff(&mut self) -> Result<(), DError> {
+        self.reg_mut().tctl.modify(TCTL::SWXOFF::SET);
+        // 发送完成后硬件自动清零
+        wait_for(
+            "XOFF transmit",
+            || !self.reg().tctl.is_set(TCTL::SWXOFF),
+            Duration::from_micros(10),
+            Some(1000),
+        )
+    }
+
+    /// Drive CTRL.PHY_RST; the caller must own the PHY semaphore.
+    pub fn set_phy_reset(&mut self, assert: bool) {
+        if assert {
+            self.reg_mut().ctrl.modify(CTRL::PHY_RST::SET);
+        } else {
+            self.reg_mut().ctrl.modify(CTRL::PHY_RST::CLEAR);
+        }
+    }
+
+    /// Wait for the PHY configuration from NVM to finish loading after a
+    /// PHY reset.
+    pub fn wait_phy_cfg_done(&self) {
+        let func = self.reg().status.read(STATUS::FUNC_ID);
+        let mask = EEMNGCTL_CFG_DONE0 << func;
+        // 超时时 PHY 仍可用，只是 NVM 配置可能未加载
+        if wait_for(
+            "PHY config load",
+            || self.read_reg(EEMNGCTL) & mask != 0,
+            Duration::from_millis(1),
+            Some(100),
+        )
+        .is_err()
+        {
+            debug!("PHY config from NVM not done");
+        }
+    }
+
+    /// Block new PCIe master requests and wait for outstanding ones to finish.
+    pub fn disable_pcie_master(&mut self) -> Result<(), DError> {
+        self.reg_mut().ctrl.modify(CTRL::GIO_MASTER_DISABLE::SET);
+        wait_for(
+            "PCIe master disable",
+            || !self.reg().status.is_set(STATUS::GIO_MASTER_ENABLE),
+            Duration::from_micros(100),
+            Some(800),
+        )
+    }
+
+    pub fn enable_pcie_master(&mut self) {
+        self.reg_mut().ctrl.modify(CTRL::GIO_MASTER_DISABLE::CLEAR);
+    }
+
     pub fn enable_loopback(&mut self) {
         self.reg_mut().rctl.modify(RCTL::LBM::MacLoopback);
     }
 
+    /// 参考 igb_config_collision_dist：半双工冲突窗口，按 512 比特时间
+    pub fn set_collision_distance(&mut self) {
+        self.reg_mut()
+            .tctl
+            .modify(TCTL::COLD.val(COLLISION_DISTANCE));
+    }
+
     pub fn disable_loopback(&mut self) {
         self.reg_mut().rctl.modify(RCTL::LBM::Normal);
     }
 
+    /// Loop frames back in the SerDes/SGMII transceiver (RCTL.LBM = 11b).
+    pub fn enable_transceiver_loopback(&mut self) {
+        self.reg_mut().rctl.modify(RCTL::LBM::TransceiverLoopback);
+    }
+
+    /// Force the MAC to 1000 Mb/s full duplex with link up, ignoring the
+    /// PHY, or go back to following the PHY.
+    pub fn force_link_1000_full(&mut self, force: bool) {
+        if force {
+            self.reg_mut().ctrl.modify(
+                CTRL::SPEED::Speed1000
+                    + CTRL::FRCSPD::SET
+                    + CTRL::FRCDPLX::SET
+                    + CTRL::FD::FullDuplex
+                    + CTRL::SLU::SET,
+            );
+        } else {
+            self.reg_mut()
+                .ctrl
+                .modify(CTRL::FRCSPD::CLEAR + CTRL::FRCDPLX::CLEAR);
+        }
+    }
+
+    /// Deliver PAUSE frames (RCTL.DPF) and other MAC control frames
+    /// (RCTL.PMCF) to the host instead of only consuming them in the MAC.
+    pub fn set_control_frames(&mut self, pause: bool, mac_control: bool) {
+        let dpf = if pause {
+            RCTL::DPF::Forward
+        } else {
+            RCTL::DPF::Discard
+        };
+        let pmcf = if mac_control {
+            RCTL::PMCF::Pass
+        } else {
+            RCTL::PMCF::Filter
+        };
+        self.reg_mut().rctl.modify(dpf + pmcf);
+    }
+
+    /// Accept broadcast frames (RCTL.BAM); returns the previous setting.
+    pub fn set_broadcast_accept(&mut self, enable: bool) -> bool {
+        let prev = self.reg().rctl.is_set(RCTL::BAM);
+        let bam = if enable {
+            RCTL::BAM::Accept
+        } else {
+            RCTL::BAM::Ignore
+        };
+        self.reg_mut().rctl.modify(bam);
+        prev
+    }
+
+    /// Accept all multicast frames (RCTL.MPE); returns the previous setting.
+    pub fn set_multicast_promisc(&mut self, enable: bool) -> bool {
+        let prev = self.reg().rctl.is_set(RCTL::MPE);
+        let mpe = if enable {
+            RCTL::MPE::Enabled
+        } else {
+            RCTL::MPE::Disabled
+        };
+        self.reg_mut().rctl.modify(mpe);
+        prev
+    }
+
+    /// Configure which vectors are auto-cleared (EIAC) and auto-masked (EIAM)
+    /// when their interrupt is asserted.
+    pub fn set_auto_mask(&mut self, auto_clear: u32, auto_mask: u32) {
+        self.reg_mut().eiac.set(auto_clear);
+        self.reg_mut().eiam.set(auto_mask);
+        if auto_mask != 0 {
+            self.reg_mut().gpie.modify(GPIE::EIAME::Enabled);
+        } else {
+            self.reg_mut().gpie.modify(GPIE::EIAME::Disabled);
+        }
+    }
+
+    /// Set the given EICR bits by software (EICS), raising their interrupts.
+    pub fn trigger_vectors(&mut self, mask: u32) {
+        self.reg_mut().eics.set(mask);
+    }
+
+    /// Unmask the given vectors in EIMS.
+    pub fn enable_vectors(&mut self, mask: u32) {
+        self.reg_mut().eims.set(mask);
+    }
+
     /// Configure GPIE register for MSI-X mode
     pub fn configure_msix_mode(&mut self) {
         self.reg_mut().gpie.write(
@@ -561,21 +1122,18 @@ impl Mac {
     }
 
     /// Configure GPIE register for legacy/MSI mode
+    ///
+    /// NSICR makes every EICR read clear the causes, not only reads while
+    /// the interrupt is asserted.
     pub fn configure_legacy_mode(&mut self) {
         self.reg_mut().gpie.write(
-            GPIE::Multiple_MSIX::SingleVector + GPIE::EIAME::Disabled + GPIE::PBA_Support::Legacy,
+            GPIE::Multiple_MSIX::SingleVector
+                + GPIE::EIAME::Disabled
+                + GPIE::PBA_Support::Legacy
+                + GPIE::NSICR::ClearOnRead,
         );
     }
 
-    /// Set non-selective interrupt clear on read
-    pub fn set_nsicr(&mut self, enable: bool) {
-        if enable {
-            self.reg_mut().gpie.modify(GPIE::NSICR::ClearOnRead);
-        } else {
-            self.reg_mut().gpie.modify(GPIE::NSICR::Normal);
-        }
-    }
-
     /// Set Low Latency Credits Increment Rate
     pub fn set_ll_interval(&mut self, interval: u8) {
         // interval is in 4μs increments, valid values 0-31
@@ -583,20 +1141,80 @@ impl Mac {
         self.reg_mut().gpie.modify(GPIE::LL_Interval.val(val));
     }
 
-    fn ral(&self, i: usize) -> u32 {
-        if i <= 15 {
-            self.reg().ralh_0_15[i * 2].get()
+    fn ral_rah(&self, i: usize) -> (&ReadWrite<u32>, &ReadWrite<u32>) {
+        let (regs, i) = if i <= 15 {
+            (&self.reg().ralh_0_15, i)
         } else {
-            self.reg().ralh_16_23[i * 2].get()
-        }
+            (&self.reg().ralh_16_23, i - 16)
+        };
+        (&regs[i * 2], &regs[i * 2 + 1])
+    }
+
+    fn ral(&self, i: usize) -> u32 {
+        self.ral_rah(i).0.get()
     }
 
     fn rah(&self, i: usize) -> u32 {
-        if i <= 15 {
-            self.reg().ralh_0_15[i * 2 + 1].get()
-        } else {
-            self.reg().ralh_16_23[i * 2 + 1].get()
+        self.ral_rah(i).1.get()
+    }
+
+    /// Program receive address `i`; `valid` sets the Address Valid bit.
+    pub fn write_rar(&mut self, i: usize, addr: [u8; 6], valid: bool) {
+        let low = u32::from_le_bytes([addr[0], addr[1], addr[2], addr[3]]);
+        let mut high = u16::from_le_bytes([addr[4], addr[5]]) as u32;
+        if valid {
+            high |= RAH_AV;
         }
+        let (ral, rah) = self.ral_rah(i);
+        // 先清除 AV，避免短暂匹配半新半旧的地址
+        rah.set(0);
+        ral.set(low);
+        rah.set(high);
+    }
+
+    /// Wait for the NVM auto-read after reset, which reloads RAR0 among
+    /// others.
+    pub fn wait_auto_read(&self) -> Result<(), DError> {
+        wait_for(
+            "NVM auto read",
+            || self.read_reg(EEC) & EEC_AUTO_RD != 0,
+            Duration::from_millis(1),
+            Some(10),
+        )
+    }
+
+    /// Make sure RAR0 holds a valid unicast address, falling back to a
+    /// random locally administered one on parts with a blank NVM.
+    ///
+    /// The driver has no random source of its own, so the fallback is seeded
+    /// from the kernel clock; set a stable address with
+    /// [`crate::Igb::set_mac_address`] if the platform has one.
+    pub fn init_rar0(&mut self) -> MacAddr6 {
+        let addr = self.read_mac();
+        let valid = self.rah(0) & RAH_AV != 0;
+        let unicast = addr[0] & 0x01 == 0 && addr != [0; 6];
+        if valid && unicast {
+            return MacAddr6(addr);
+        }
+        if unicast {
+            self.write_rar(0, addr, true);
+            return MacAddr6(addr);
+        }
+        // 用 splitmix64 打散启动以来的时间
+        let mut seed = crate::osal::kernel::now().as_nanos() as u64;
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        seed = (seed ^ (seed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        seed = (seed ^ (seed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        seed ^= seed >> 31;
+        let mut entropy = [0u8; 6];
+        entropy.copy_from_slice(&seed.to_be_bytes()[..6]);
+        // 低两位放 PCI function，同一网卡的端口即使同时初始化也不会重复
+        let func = self.reg().status.read(STATUS::FUNC_ID) as u8;
+        entropy[5] = (entropy[5] & !0x03) | (func & 0x03);
+        let fallback = MacAddr6::random_local(entropy);
+        warn!("no valid MAC address in NVM, using {fallback}");
+        self.write_rar(0, fallback.bytes(), true);
+        fallback
     }
 
     pub fn status(&self) -> MacStatus {
@@ -609,52 +1227,136 @@ impl Mac {
         let full_duplex = status.is_set(STATUS::FD);
         let link_up = status.is_set(STATUS::LU);
         let phy_reset_asserted = status.is_set(STATUS::PHYRA);
+        let ctrl = self.reg().ctrl.extract();
 
         MacStatus {
             full_duplex,
             link_up,
             speed,
             phy_reset_asserted,
+            downshifted: false,
+            rx_pause: ctrl.is_set(CTRL::RFCE),
+            tx_pause: ctrl.is_set(CTRL::TFCE),
+            fc_source: FcSource::default(),
         }
     }
 }
 
+/// One MDIO transaction.
+#[derive(Debug, Clone, Copy)]
+pub struct MdicCmd {
+    pub phys_addr: u32,
+    pub offset: u32,
+    pub external: bool,
+    /// `Some(data)` to write, `None` to read.
+    pub write: Option<u16>,
+}
+
 #[derive(Debug, Clone)]
 pub struct IrqMsg {
     pub queue_idx: u16,
     pub tcp_timer: bool,
     pub other: bool,
+    pub lsc: bool,
+    pub mdac: bool,
+    pub rxo: bool,
+    pub rxdmt0: bool,
+    pub rxseq: bool,
+    pub time_sync: bool,
+}
+
+impl IrqMsg {
+    /// 非队列原因从 ICR 解码
+    fn new(queue_idx: u16, tcp_timer: bool, other: bool, icr: u32) -> Self {
+        Self {
+            queue_idx,
+            tcp_timer,
+            other,
+            lsc: icr & ICR::LSC.mask != 0,
+            mdac: icr & ICR::MDAC.mask != 0,
+            rxo: icr & ICR::RXO.mask != 0,
+            rxdmt0: icr & ICR::RXDMT0.mask != 0,
+            rxseq: icr & ICR::RXSEQ.mask != 0,
+            time_sync: icr & ICR::TS.mask != 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct LegacyIrqMsg {
-    pub txdw: bool,     // Transmit Descriptor Written Back
-    pub txqe: bool,     // Transmit Queue Empty
-    pub lsc: bool,      // Link Status Change
-    pub rxseq: bool,    // Receive Sequence Error
-    pub rxdmt0: bool,   // Receive Descriptor Minimum Threshold Reached
-    pub rxo: bool,      // Receiver Overrun
-    pub rxt0: bool,     // Receiver Timer Interrupt
-    pub mdac: bool,     // MDI/O Access Complete
-    pub rxcfg: bool,    // Receiving /C/ ordered sets
-    pub asserted: bool, // Interrupt Asserted
+    pub txdw: bool,      // Transmit Descriptor Written Back
+    pub txqe: bool,      // Transmit Queue Empty
+    pub lsc: bool,       // Link Status Change
+    pub rxseq: bool,     // Receive Sequence Error
+    pub rxdmt0: bool,    // Receive Descriptor Minimum Threshold Reached
+    pub rxo: bool,       // Receiver Overrun
+    pub rxt0: bool,      // Receiver Timer Interrupt
+    pub mdac: bool,      // MDI/O Access Complete
+    pub rxcfg: bool,     // Receiving /C/ ordered sets
+    pub time_sync: bool, // Time Sync event
+    pub asserted: bool,  // Interrupt Asserted
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(transparent)]
 pub struct MacAddr6([u8; 6]);
 
+/// 站地址必须是非零的单播地址
+pub(crate) fn check_station_address(addr: MacAddr6) -> Result<(), DError> {
+    if addr.is_multicast() || addr == MacAddr6::ZERO {
+        return Err(DError::InvalidParameter);
+    }
+    Ok(())
+}
+
 impl MacAddr6 {
+    pub const BROADCAST: Self = MacAddr6([0xFF; 6]);
+    pub const ZERO: Self = MacAddr6([0; 6]);
+
     pub fn new(bytes: [u8; 6]) -> Self {
         MacAddr6(bytes)
     }
 
+    /// A random locally administered unicast address, e.g. for a device
+    /// without a programmed NVM. `entropy` comes from the platform's random
+    /// source; the caller provides it, the driver has none of its own.
+    pub fn random_local(entropy: [u8; 6]) -> Self {
+        let mut bytes = entropy;
+        bytes[0] = (bytes[0] & !0x01) | 0x02;
+        MacAddr6(bytes)
+    }
+
     pub fn bytes(&self) -> [u8; 6] {
         self.0
     }
+
+    /// Group bit (I/G) set, including [`MacAddr6::BROADCAST`].
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    pub fn is_unicast(&self) -> bool {
+        !self.is_multicast()
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    /// Locally administered bit (U/L) set.
+    pub fn is_local(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
 }
 
 impl Debug for MacAddr6 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
+    }
+}
+
+impl core::fmt::Display for MacAddr6 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
@@ -664,6 +1366,29 @@ impl Debug for MacAddr6 {
     }
 }
 
+impl FromStr for MacAddr6 {
+    type Err = DError;
+
+    /// Parse six octets of exactly two hex digits separated by `:` or `-`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sep = if s.contains('-') { '-' } else { ':' };
+        let mut bytes = [0; 6];
+        let mut parts = s.split(sep);
+        for byte in &mut bytes {
+            let part = parts.next().ok_or(DError::InvalidParameter)?;
+            // from_str_radix 还接受 '+' 前缀，这里逐字符检查
+            if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(DError::InvalidParameter);
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| DError::InvalidParameter)?;
+        }
+        if parts.next().is_some() {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(MacAddr6(bytes))
+    }
+}
+
 impl From<[u8; 6]> for MacAddr6 {
     fn from(addr: [u8; 6]) -> Self {
         MacAddr6(addr)
@@ -676,12 +1401,101 @@ impl From<MacAddr6> for [u8; 6] {
     }
 }
 
+/// Link state change found by [`crate::Igb::link_event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkEvent {
+    Up {
+        speed: Speed,
+        full_duplex: bool,
+    },
+    Down,
+    /// The link stayed up but the partner renegotiated speed or duplex.
+    SpeedChanged {
+        speed: Speed,
+        full_duplex: bool,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub struct MacStatus {
     pub full_duplex: bool,
     pub link_up: bool,
     pub speed: Speed,
     pub phy_reset_asserted: bool,
+    /// The link came up below 1000 Mb/s although both ends advertise it.
+    ///
+    /// Updated by [`crate::Igb::refresh_link_status`].
+    pub downshifted: bool,
+    /// Received PAUSE frames stop transmission.
+    pub rx_pause: bool,
+    /// PAUSE frames are sent when the RX packet buffer fills.
+    pub tx_pause: bool,
+    /// How `rx_pause` and `tx_pause` were decided, updated when the link
+    /// comes up.
+    pub fc_source: FcSource,
+}
+
+#[cfg(feature = "display")]
+impl core::fmt::Display for MacStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let yes_no = |v: bool| if v { "yes" } else { "no" };
+        let speed = match self.speed {
+            Speed::Mb10 => 10,
+            Speed::Mb100 => 100,
+            Speed::Mb1000 => 1000,
+        };
+        if self.link_up {
+            writeln!(f, "\tSpeed: {speed}Mb/s")?;
+            let duplex = if self.full_duplex { "Full" } else { "Half" };
+            writeln!(f, "\tDuplex: {duplex}")?;
+        } else {
+            writeln!(f, "\tSpeed: Unknown!")?;
+            writeln!(f, "\tDuplex: Unknown!")?;
+        }
+        writeln!(f, "\tDownshifted: {}", yes_no(self.downshifted))?;
+        writeln!(
+            f,
+            "\tPause: RX {} TX {}, {:?}",
+            yes_no(self.rx_pause),
+            yes_no(self.tx_pause),
+            self.fc_source
+        )?;
+        writeln!(
+            f,
+            "\tPHY reset asserted: {}",
+            yes_no(self.phy_reset_asserted)
+        )?;
+        writeln!(f, "\tLink detected: {}", yes_no(self.link_up))
+    }
+}
+
+/// Firmware manageability mode reported in FWSM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManageabilityMode {
+    None,
+    Asf,
+    PassThrough,
+    Ipmi,
+    HostInterface,
+}
+
+/// Whether the port is shared with a BMC (NC-SI / SMBus pass-through).
+#[derive(Debug, Clone)]
+pub struct ManageabilityStatus {
+    pub mode: ManageabilityMode,
+    /// The BMC receives traffic from this port (MANC.RCV_TCO_EN).
+    pub receive_tco: bool,
+    /// Management packets are also forwarded to the host.
+    pub to_host: bool,
+    /// The firmware forbids resetting the PHY.
+    pub phy_reset_blocked: bool,
+}
+
+impl ManageabilityStatus {
+    /// The port is shared with manageability firmware.
+    pub fn is_shared(&self) -> bool {
+        self.mode != ManageabilityMode::None || self.receive_tco
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
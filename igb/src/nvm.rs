@@ -0,0 +1,184 @@
+use core::time::Duration;
+
+use alloc::string::String;
+
+use crate::{DError, MacAddr6, mac::Mac, osal::wait_for};
+
+const EERD: usize = 0x14; // EEPROM Read
+
+const EERD_START: u32 = 1 << 0;
+const EERD_DONE: u32 = 1 << 1;
+const EERD_ADDR_SHIFT: u32 = 2;
+const EERD_DATA_SHIFT: u32 = 16;
+
+const WORD_ETHERNET_ADDR: u16 = 0x00;
+const WORD_IMAGE_VERSION: u16 = 0x05;
+const WORD_PBA_0: u16 = 0x08;
+const WORD_PBA_1: u16 = 0x09;
+const WORD_SUBSYSTEM_ID: u16 = 0x0B;
+const WORD_SUBSYSTEM_VENDOR_ID: u16 = 0x0C;
+/// NVM word holding the PCI device ID loaded at power-up.
+pub(crate) const WORD_DEVICE_ID: u16 = 0x0D;
+const WORD_VENDOR_ID: u16 = 0x0E;
+const WORD_ETRACK_LO: u16 = 0x42;
+const WORD_ETRACK_HI: u16 = 0x43;
+
+// PBA 字 0 为该值时，字 1 是指向 PBA 字符串块的指针
+const PBA_PTR_GUARD: u16 = 0xFAFA;
+const PBA_MAX_WORDS: u16 = 32;
+const ETRACK_VALID: u16 = 0x8000;
+
+/// Read one 16-bit word of the NVM through EERD.
+///
+/// On I210 this reads the shadow RAM, so it also works for flash-less parts
+/// programmed through iNVM.
+pub(crate) fn read_word(mac: &mut Mac, offset: u16) -> Result<u16, DError> {
+    if offset > 0x3FFF {
+        return Err(DError::InvalidParameter);
+    }
+    mac.write_reg(EERD, ((offset as u32) << EERD_ADDR_SHIFT) | EERD_START);
+    wait_for(
+        "NVM read",
+        || mac.read_reg(EERD) & EERD_DONE != 0,
+        Duration::from_micros(5),
+        Some(20000),
+    )?;
+    Ok((mac.read_reg(EERD) >> EERD_DATA_SHIFT) as u16)
+}
+
+/// NVM image version, as `ethtool -i` reports it in `firmware-version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FwVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub build: u8,
+    /// EEPROM tracking ID of the image, when programmed.
+    pub etrack_id: Option<u32>,
+}
+
+#[cfg(feature = "display")]
+impl core::fmt::Display for FwVersion {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.build)?;
+        if let Some(etrack_id) = self.etrack_id {
+            write!(f, ", 0x{etrack_id:08x}")?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn read_fw_version(mac: &mut Mac) -> Result<FwVersion, DError> {
+    let version = read_word(mac, WORD_IMAGE_VERSION)?;
+    // 次版本号按十六进制存放十进制数字，例如 0x63 表示 63
+    let minor = ((version >> 4) & 0xFF) as u8;
+    let minor = (minor >> 4) * 10 + (minor & 0xF);
+    let hi = read_word(mac, WORD_ETRACK_HI)?;
+    let etrack_id = if hi & 0xF000 == ETRACK_VALID {
+        let lo = read_word(mac, WORD_ETRACK_LO)?;
+        Some(((hi as u32) << 16) | lo as u32)
+    } else {
+        None
+    };
+    Ok(FwVersion {
+        major: (version >> 12) as u8,
+        minor,
+        build: (version & 0xF) as u8,
+        etrack_id,
+    })
+}
+
+/// Board identification stored in the NVM.
+///
+/// The PCI revision ID only lives in configuration space, which the driver
+/// does not access.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartInfo {
+    /// Printed Board Assembly number, e.g. `E43709-006`.
+    pub pba: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub subsystem_vendor_id: u16,
+    pub subsystem_id: u16,
+    /// Ethernet address of port 0; other ports derive theirs from it.
+    pub base_mac: MacAddr6,
+}
+
+pub(crate) fn read_part_info(mac: &mut Mac) -> Result<PartInfo, DError> {
+    let addr = read_base_mac(mac)?;
+    Ok(PartInfo {
+        pba: read_pba(mac)?,
+        vendor_id: read_word(mac, WORD_VENDOR_ID)?,
+        device_id: read_word(mac, WORD_DEVICE_ID)?,
+        subsystem_vendor_id: read_word(mac, WORD_SUBSYSTEM_VENDOR_ID)?,
+        subsystem_id: read_word(mac, WORD_SUBSYSTEM_ID)?,
+        base_mac: MacAddr6::new(addr),
+    })
+}
+
+fn read_pba(mac: &mut Mac) -> Result<String, DError> {
+    let word0 = read_word(mac, WORD_PBA_0)?;
+    let word1 = read_word(mac, WORD_PBA_1)?;
+
+    if word0 != PBA_PTR_GUARD {
+        // 旧格式：两个字按十六进制拼成 XXXXXX-0XX
+        let digits = [
+            word0 >> 12,
+            word0 >> 8,
+            word0 >> 4,
+            word0,
+            word1 >> 12,
+            word1 >> 8,
+        ];
+        let mut pba = String::new();
+        for d in digits {
+            pba.push(
+                char::from_digit((d & 0xF) as u32, 16)
+                    .unwrap()
+                    .to_ascii_uppercase(),
+            );
+        }
+        pba.push_str("-0");
+        for d in [word1 >> 4, word1] {
+            pba.push(
+                char::from_digit((d & 0xF) as u32, 16)
+                    .unwrap()
+                    .to_ascii_uppercase(),
+            );
+        }
+        return Ok(pba);
+    }
+
+    // 新格式：第一个字是块长度（字数，含自身），之后每字两个 ASCII 字符
+    let len = read_word(mac, word1)?;
+    if len == 0 || len > PBA_MAX_WORDS {
+        return Err(DError::Unknown("invalid PBA block length"));
+    }
+    let mut pba = String::new();
+    for i in 1..len {
+        for b in read_word(mac, word1 + i)?.to_be_bytes() {
+            if b != 0 {
+                pba.push(b as char);
+            }
+        }
+    }
+    Ok(pba)
+}
+
+/// The NVM loads the DSN capability with the EUI-64 form of the port 0
+/// Ethernet address, `FF-FE` inserted in the middle.
+pub(crate) fn read_serial_number(mac: &mut Mac) -> Result<u64, DError> {
+    let addr = read_base_mac(mac)?;
+    let eui64 = [
+        addr[0], addr[1], addr[2], 0xFF, 0xFE, addr[3], addr[4], addr[5],
+    ];
+    Ok(u64::from_be_bytes(eui64))
+}
+
+fn read_base_mac(mac: &mut Mac) -> Result<[u8; 6], DError> {
+    let mut addr = [0u8; 6];
+    let (words, _) = addr.as_chunks_mut::<2>();
+    for (i, word) in words.iter_mut().enumerate() {
+        *word = read_word(mac, WORD_ETHERNET_ADDR + i as u16)?.to_le_bytes();
+    }
+    Ok(addr)
+}
@@ -1,4 +1,6 @@
-use core::time::Duration;
+#[cfg(not(feature = "dma-api"))]
+use core::alloc::Layout;
+use core::{ptr::NonNull, time::Duration};
 
 use trait_ffi::def_extern_trait;
 
@@ -7,19 +9,128 @@ use crate::DError;
 #[def_extern_trait]
 pub trait Kernel {
     fn sleep(duration: Duration);
+    /// Monotonic time since an arbitrary fixed point, e.g. boot. Timeouts
+    /// of the driver are measured with it.
+    fn now() -> Duration;
 }
 
+/// Direction of a DMA transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ToDevice,
+    FromDevice,
+    Bidirectional,
+}
+
+/// DMA backend used when the `dma-api` feature is disabled.
+///
+/// Implement it with [`crate::impl_trait!`] for a custom IOMMU, bounce
+/// buffers or hypervisor mediated DMA.
+#[cfg(not(feature = "dma-api"))]
+#[def_extern_trait(not_def_impl)]
+pub trait DmaAlloc {
+    /// Allocate zeroed memory for `layout` reachable below `dma_mask`.
+    ///
+    /// Returns the CPU pointer and the bus address.
+    fn alloc(dma_mask: u64, layout: Layout) -> Option<(NonNull<u8>, u64)>;
+    /// Free memory returned by [`DmaAlloc::alloc`].
+    fn dealloc(ptr: NonNull<u8>, layout: Layout);
+    /// Make existing memory visible to the device and return its bus address.
+    fn map(ptr: NonNull<u8>, size: usize, direction: Direction) -> u64;
+    fn unmap(ptr: NonNull<u8>, size: usize);
+    /// Give memory to the device: write CPU caches back for
+    /// [`Direction::ToDevice`], drop cache lines for [`Direction::FromDevice`],
+    /// both for [`Direction::Bidirectional`]. A no-op on coherent platforms.
+    fn sync_for_device(ptr: NonNull<u8>, size: usize, direction: Direction);
+    /// Take memory back from the device: drop stale cache lines unless the
+    /// direction is [`Direction::ToDevice`].
+    fn sync_for_cpu(ptr: NonNull<u8>, size: usize, direction: Direction);
+}
+
+/// Short critical sections around descriptor and tail updates, so a ring
+/// can be fed from task context while it is harvested from an interrupt
+/// handler. Enabled with the `irq-safe` feature.
+///
+/// Typically disables local interrupts and returns the previous state. It
+/// must tolerate nesting; the sections never block or allocate.
+#[cfg(feature = "irq-safe")]
+#[def_extern_trait(not_def_impl)]
+pub trait CriticalSection {
+    /// Enter the section and return the state to restore.
+    fn acquire() -> usize;
+    /// Leave the section entered by the matching [`CriticalSection::acquire`].
+    fn release(state: usize);
+}
+
+/// Translation of bus addresses back to CPU pointers, for diagnostics
+/// such as checking head write-back locations or dumping descriptors.
+/// Enabled with the `bus-to-virt` feature.
+#[cfg(feature = "bus-to-virt")]
+#[def_extern_trait(not_def_impl)]
+pub trait BusToVirt {
+    /// The CPU pointer of DMA memory at `bus_addr`, `None` if it isn't
+    /// mapped for the CPU.
+    fn bus_to_virt(bus_addr: u64) -> Option<NonNull<u8>>;
+}
+
+/// CPU pointer of `bus_addr` through [`BusToVirt`], `None` if the
+/// platform can't translate it or the `bus-to-virt` feature is disabled.
+pub fn bus_to_virt(bus_addr: u64) -> Option<NonNull<u8>> {
+    #[cfg(feature = "bus-to-virt")]
+    {
+        bus_to_virt::bus_to_virt(bus_addr)
+    }
+    #[cfg(not(feature = "bus-to-virt"))]
+    {
+        let _ = bus_addr;
+        None
+    }
+}
+
+/// 未启用 `irq-safe` 时环只允许单一上下文访问，这里不做任何事
+pub(crate) fn critical_section<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "irq-safe")]
+    {
+        let state = critical_section::acquire();
+        let r = f();
+        critical_section::release(state);
+        r
+    }
+    #[cfg(not(feature = "irq-safe"))]
+    f()
+}
+
+/// 轮询间隔最多退避到 `interval` 的这么多倍
+const WAIT_BACKOFF_MAX: u32 = 16;
+
+/// 在 `interval × try_count` 的时间内重试 `f`，间隔从 `interval` 开始
+/// 逐次翻倍退避；超时返回 [`DError::TimedOut`]，`op` 说明在等什么，
+/// `elapsed` 是按 [`kernel::now`] 实际测得的时间
 pub(crate) fn wait_for<F: FnMut() -> bool>(
+    op: &'static str,
     mut f: F,
     interval: Duration,
     try_count: Option<usize>,
 ) -> Result<(), DError> {
-    for _ in 0..try_count.unwrap_or(usize::MAX) {
+    let budget = try_count.map(|n| interval.saturating_mul(n.try_into().unwrap_or(u32::MAX)));
+    let max_delay = interval.saturating_mul(WAIT_BACKOFF_MAX);
+    let start = kernel::now();
+    let mut delay = interval;
+    loop {
         if f() {
             return Ok(());
         }
 
-        kernel::sleep(interval);
+        let elapsed = kernel::now().saturating_sub(start);
+        let delay_now = match budget {
+            Some(budget) if elapsed >= budget => {
+                return Err(DError::TimedOut { op, elapsed });
+            }
+            // 最后一次不要睡过预算
+            Some(budget) => delay.min(budget - elapsed),
+            None => delay,
+        };
+        kernel::sleep(delay_now);
+        delay = delay.saturating_mul(2).min(max_delay);
     }
-    Err(DError::Timeout)
 }
@@ -0,0 +1,78 @@
+use crate::{DError, family::DeviceFamily, mac::Mac};
+
+const RXPBS: usize = 0x2404; // RX Packet Buffer Size
+const TXPBS: usize = 0x3404; // TX Packet Buffer Size
+
+const PBS_SIZE_MASK: u32 = 0x3F;
+
+/// Split of the on-chip packet buffer between RX and TX, in KB, see
+/// [`crate::Igb::set_packet_buffers`].
+///
+/// On I350 and I210 `tx_kb` is TX packet buffer 0; the buffers shared
+/// with a BMC are left alone. I350 encodes the RX size in a table, so
+/// `rx_kb` must be one of 1, 2, 4, 8, 16, 35, 36, 70, 72, 140 or 144.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PacketBuffers {
+    pub rx_kb: u32,
+    pub tx_kb: u32,
+}
+
+impl PacketBuffers {
+    pub(crate) fn total_kb(&self) -> u32 {
+        self.rx_kb + self.tx_kb
+    }
+
+    /// 参考 igb_reset：TX 至少容纳两个最大帧，RX 至少一个
+    pub(crate) fn fits(&self, max_frame: usize) -> bool {
+        let frame = max_frame as u32;
+        self.tx_kb * 1024 >= 2 * frame && self.rx_kb * 1024 >= frame
+    }
+
+    /// 在 `total_kb` 内给 TX 刚好两个帧，至少保持 `current` 的 TX 大小；
+    /// RX 取剩余空间内可编码的最大值
+    pub(crate) fn for_max_frame(family: DeviceFamily, current: &Self, max_frame: usize) -> Self {
+        let total = current.total_kb();
+        let tx_kb = (2 * max_frame as u32).div_ceil(1024).max(current.tx_kb);
+        Self {
+            rx_kb: family.rxpbs_encoding().floor(total.saturating_sub(tx_kb)),
+            tx_kb,
+        }
+    }
+}
+
+pub(crate) fn read(mac: &Mac, family: DeviceFamily) -> PacketBuffers {
+    PacketBuffers {
+        rx_kb: family.rxpbs_encoding().decode(mac.read_reg(RXPBS)),
+        tx_kb: mac.read_reg(TXPBS) & PBS_SIZE_MASK,
+    }
+}
+
+/// `pb` 的总量不能超过上电时的默认划分 `default`
+pub(crate) fn validate(
+    family: DeviceFamily,
+    pb: &PacketBuffers,
+    default: Option<&PacketBuffers>,
+) -> Result<(), DError> {
+    if pb.rx_kb == 0
+        || pb.tx_kb == 0
+        || family.rxpbs_encoding().encode(pb.rx_kb).is_none()
+        || pb.tx_kb > PBS_SIZE_MASK
+        || default.is_some_and(|d| pb.total_kb() > d.total_kb())
+    {
+        return Err(DError::InvalidParameter);
+    }
+    Ok(())
+}
+
+/// 重新划分包缓冲区，须在收发使能前调用；`pb` 已经过 [`validate`]
+pub(crate) fn apply(mac: &mut Mac, family: DeviceFamily, pb: &PacketBuffers) {
+    let encoding = family.rxpbs_encoding();
+    let Some(field) = encoding.encode(pb.rx_kb) else {
+        return;
+    };
+    let rxpbs = mac.read_reg(RXPBS) & !encoding.mask();
+    mac.write_reg(RXPBS, rxpbs | field);
+    let txpbs = mac.read_reg(TXPBS) & !PBS_SIZE_MASK;
+    mac.write_reg(TXPBS, txpbs | pb.tx_kb);
+}
@@ -0,0 +1,108 @@
+use crate::{family::DeviceFamily, mac::Mac};
+
+const CTRL_EXT: usize = 0x18; // Extended Device Control
+const GCR: usize = 0x5B00; // PCIe Control
+
+const CTRL_EXT_RO_DIS: u32 = 1 << 17; // Relaxed Ordering Disable
+
+// GCR 中描述符/数据读写的 No-Snoop 使能位 [5:0]
+const GCR_NO_SNOOP_MASK: u32 = 0x3F;
+const GCR_CMPL_TMOUT_MASK: u32 = 0xF << 12;
+const GCR_CMPL_TMOUT_10MS: u32 = 0x1 << 12;
+const GCR_CMPL_TMOUT_RESEND: u32 = 1 << 16;
+const GCR_CAP_VER2: u32 = 1 << 18;
+
+/// PCIe transaction settings, see [`crate::Igb::pcie_tuning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcieTuning {
+    /// Program a 10 ms completion timeout when the NVM left it at the default.
+    pub completion_timeout: bool,
+    /// Resend requests on completion timeout instead of reporting an error.
+    pub completion_timeout_resend: bool,
+    /// Allow relaxed ordering on DMA writes.
+    pub relaxed_ordering: bool,
+    /// Set the no-snoop attribute on descriptor and buffer DMA.
+    ///
+    /// Only sound when the platform does not rely on snooping to keep CPU
+    /// caches coherent with DMA, or when caches are maintained by software.
+    pub no_snoop: bool,
+}
+
+impl Default for PcieTuning {
+    /// The values the datasheet recommends for throughput.
+    fn default() -> Self {
+        Self {
+            completion_timeout: true,
+            completion_timeout_resend: false,
+            relaxed_ordering: true,
+            no_snoop: false,
+        }
+    }
+}
+
+pub(crate) fn apply_tuning(mac: &mut Mac, tuning: &PcieTuning) {
+    let mut gcr = mac.read_reg(GCR);
+    // 仅当 NVM 没有配置超时且设备是 v1 capability 时由驱动设置
+    if tuning.completion_timeout && gcr & GCR_CMPL_TMOUT_MASK == 0 && gcr & GCR_CAP_VER2 == 0 {
+        gcr |= GCR_CMPL_TMOUT_10MS;
+    }
+    if tuning.completion_timeout_resend {
+        gcr |= GCR_CMPL_TMOUT_RESEND;
+    } else {
+        gcr &= !GCR_CMPL_TMOUT_RESEND;
+    }
+    if tuning.no_snoop {
+        gcr |= GCR_NO_SNOOP_MASK;
+    } else {
+        gcr &= !GCR_NO_SNOOP_MASK;
+    }
+    mac.write_reg(GCR, gcr);
+
+    let ctrl_ext = mac.read_reg(CTRL_EXT);
+    if tuning.relaxed_ordering {
+        mac.write_reg(CTRL_EXT, ctrl_ext & !CTRL_EXT_RO_DIS);
+    } else {
+        mac.write_reg(CTRL_EXT, ctrl_ext | CTRL_EXT_RO_DIS);
+    }
+}
+
+/// Negotiated PCIe link, decoded from the Link Status register of the PCIe
+/// capability.
+///
+/// The MAC registers don't report the link width, so the value has to be
+/// read from configuration space by the OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcieLink {
+    /// Link speed in GT/s × 10 (25 = 2.5 GT/s, 50 = 5 GT/s).
+    pub speed_gts_x10: u16,
+    /// Number of negotiated lanes.
+    pub width: u8,
+}
+
+impl PcieLink {
+    pub fn from_link_status(link_status: u16) -> Self {
+        let speed_gts_x10 = match link_status & 0xF {
+            1 => 25,
+            2 => 50,
+            3 => 80,
+            _ => 0,
+        };
+        Self {
+            speed_gts_x10,
+            width: ((link_status >> 4) & 0x3F) as u8,
+        }
+    }
+
+    /// The link trained narrower than `family` supports, e.g. x1 for an
+    /// I350 in a x4 slot. See [`DeviceFamily::pcie_lanes`].
+    pub fn is_degraded(&self, family: DeviceFamily) -> bool {
+        self.width < family.pcie_lanes()
+    }
+}
+
+/// Device serial number from the two dwords following the header of the PCIe
+/// Device Serial Number extended capability (ID 0x0003), read by the OS from
+/// configuration space.
+pub fn serial_from_dsn_capability(lower: u32, upper: u32) -> u64 {
+    ((upper as u64) << 32) | lower as u64
+}
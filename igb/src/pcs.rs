@@ -0,0 +1,53 @@
+use crate::mac::Mac;
+
+const CONNSW: usize = 0x0034; // Copper/Fiber Switch Control
+const PCS_LSTS: usize = 0x420C; // PCS Link Status
+const PCS_LPAB: usize = 0x4218; // PCS Link Partner Ability
+
+const CONNSW_SERDESD: u32 = 1 << 9; // 光模块 / SerDes 信号检测
+const PCS_LSTS_LINK_OK: u32 = 1 << 0;
+const PCS_LSTS_SYNC_OK: u32 = 1 << 4;
+const PCS_LSTS_AN_COMPLETE: u32 = 1 << 16;
+const PCS_LSTS_AN_TIMED_OUT: u32 = 1 << 18;
+const PCS_LSTS_AN_REMOTE_FAULT: u32 = 1 << 19;
+const PCS_LSTS_AN_ERROR_RWS: u32 = 1 << 20;
+// 1000BASE-X 基本页 RF1/RF2
+const PCS_LPAB_REMOTE_FAULT: u32 = 0x3 << 12;
+
+/// State of the SerDes / SGMII physical coding sublayer, see
+/// [`crate::Igb::serdes_status`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SerdesStatus {
+    /// The transceiver sees a signal (CONNSW.SERDESD).
+    pub signal_detect: bool,
+    /// The PCS is synchronized to the incoming code groups.
+    pub sync: bool,
+    /// The PCS reports link up.
+    pub link_up: bool,
+    /// Auto-negotiation completed.
+    pub an_complete: bool,
+    /// The link partner signals a fault in its base page.
+    pub remote_fault: bool,
+    /// Auto-negotiation timed out or received a page with a wrong selector.
+    pub local_fault: bool,
+}
+
+impl SerdesStatus {
+    /// Synchronized, but the link is down or a fault is indicated.
+    pub fn is_faulted(&self) -> bool {
+        self.sync && (!self.link_up || self.remote_fault || self.local_fault)
+    }
+}
+
+pub(crate) fn status(mac: &Mac) -> SerdesStatus {
+    let lsts = mac.read_reg(PCS_LSTS);
+    let lpab = mac.read_reg(PCS_LPAB);
+    SerdesStatus {
+        signal_detect: mac.read_reg(CONNSW) & CONNSW_SERDESD != 0,
+        sync: lsts & PCS_LSTS_SYNC_OK != 0,
+        link_up: lsts & PCS_LSTS_LINK_OK != 0,
+        an_complete: lsts & PCS_LSTS_AN_COMPLETE != 0,
+        remote_fault: lsts & PCS_LSTS_AN_REMOTE_FAULT != 0 || lpab & PCS_LPAB_REMOTE_FAULT != 0,
+        local_fault: lsts & (PCS_LSTS_AN_TIMED_OUT | PCS_LSTS_AN_ERROR_RWS) != 0,
+    }
+}
@@ -1,10 +1,64 @@
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use alloc::sync::Arc;
+use futures::task::AtomicWaker;
 use log::debug;
 use tock_registers::register_bitfields;
 
-use crate::{err::DError, mac::Mac, osal::wait_for};
+use crate::{
+    err::DError,
+    family::DeviceFamily,
+    mac::{Mac, MdicCmd, SW_FW_SYNC, SyncFlags},
+    osal::{kernel, wait_for},
+};
 
 const PHY_CONTROL: u32 = 0;
 const PHY_STATUS: u32 = 1;
+const PHY_AUTONEG_ADV: u32 = 4;
+// ANAR 和 ANLPAR 的 PAUSE 位位置相同
+const NWAY_AR_PAUSE: u16 = 1 << 10;
+const NWAY_AR_ASM_DIR: u16 = 1 << 11;
+const PHY_LP_ABILITY: u32 = 5;
+const PHY_AUTONEG_EXP: u32 = 6;
+const PHY_1000T_CTRL: u32 = 9;
+// Clause 22 间接访问 Clause 45 MMD 寄存器
+const PHY_MMD_ACCESS_CTRL: u32 = 13;
+const PHY_MMD_ADDR_DATA: u32 = 14;
+const MMD_FUNC_DATA: u16 = 0x1 << 14;
+const MMD_DEVAD_MASK: u16 = 0x1F;
+// I350/I210 通过 MDICNFG 选择外部 PHY
+const MDICNFG: usize = 0x0E04;
+const MDICNFG_EXT_MDIO: u32 = 1 << 31;
+const MDICNFG_PHY_SHIFT: u32 = 21;
+const MAX_PHY_ADDR: u32 = 31;
+const PHY_1000T_STATUS: u32 = 10;
+// 82576 (IGP) 端口配置寄存器
+const IGP_PORT_CONFIG: u32 = 0x10;
+const IGP_PSCFR_SMART_SPEED: u16 = 1 << 7;
+// I350/I210 PHY 配置寄存器
+const I82580_CFG_REG: u32 = 22;
+const I82580_CFG_ENABLE_DOWNSHIFT: u16 = 0x3 << 10;
+// 82576 (IGP) 电源管理寄存器
+const IGP02_PHY_POWER_MGMT: u32 = 0x19;
+const IGP02_PM_SPD: u16 = 1 << 0; // Smart Power Down
+const IGP02_PM_D0_LPLU: u16 = 1 << 1;
+const IGP02_PM_D3_LPLU: u16 = 1 << 2;
+// I350/I210 的 LPLU 配置在 MAC 的 PHPM 寄存器
+const PHPM: usize = 0x0E14;
+const PHPM_SPD_EN: u32 = 1 << 0;
+const PHPM_D0_LPLU: u32 = 1 << 1;
+const PHPM_D3_LPLU: u32 = 1 << 2;
+// 1000BASE-T 控制寄存器中的通告位
+const CR_1000T_HD_CAPS: u16 = 1 << 8;
+const CR_1000T_FD_CAPS: u16 = 1 << 9;
+// I350 PHY 的环回控制寄存器，参考 igb_integrated_phy_loopback
+const I82580_PHY_LBK_CTRL: u32 = 19;
+const I82580_LBK_MII: u16 = 0x8041;
 
 register_bitfields! {
     u16,
@@ -245,27 +299,201 @@ register_bitfields! {
     ]
 }
 
+register_bitfields! {
+    u16,
+
+    /// Link Partner Ability Register (ANLPAR) - Register 0x05 (Read Only)
+    ANLPAR [
+        /// Link partner supports 10BASE-T half duplex
+        LP_10T_HD OFFSET(5) NUMBITS(1) [],
+        /// Link partner supports 10BASE-T full duplex
+        LP_10T_FD OFFSET(6) NUMBITS(1) [],
+        /// Link partner supports 100BASE-TX half duplex
+        LP_100TX_HD OFFSET(7) NUMBITS(1) [],
+        /// Link partner supports 100BASE-TX full duplex
+        LP_100TX_FD OFFSET(8) NUMBITS(1) [],
+        /// Link partner supports symmetric pause
+        LP_PAUSE OFFSET(10) NUMBITS(1) [],
+        /// Link partner supports asymmetric pause
+        LP_ASM_DIR OFFSET(11) NUMBITS(1) [],
+        /// Link partner reports a remote fault
+        LP_REMOTE_FAULT OFFSET(13) NUMBITS(1) []
+    ],
+
+    /// Auto-Negotiation Expansion Register (ANE) - Register 0x06 (Read Only)
+    ANE [
+        /// Link partner is able to auto-negotiate
+        LP_AN_ABLE OFFSET(0) NUMBITS(1) []
+    ],
+
+    /// 1000BASE-T Status Register (GSTATUS) - Register 0x0A (Read Only)
+    GSTATUS [
+        /// Link partner supports 1000BASE-T half duplex
+        LP_1000T_HD OFFSET(10) NUMBITS(1) [],
+        /// Link partner supports 1000BASE-T full duplex
+        LP_1000T_FD OFFSET(11) NUMBITS(1) [],
+        /// Master/Slave resolution
+        /// 1b = Local PHY is master
+        /// 0b = Local PHY is slave
+        MS_RESOLUTION OFFSET(14) NUMBITS(1) [
+            Slave = 0,
+            Master = 1
+        ],
+        /// Master/Slave configuration fault
+        MS_FAULT OFFSET(15) NUMBITS(1) []
+    ]
+}
+
+/// Speeds and duplex modes advertised by a link partner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkSpeeds {
+    pub mb10_half: bool,
+    pub mb10_full: bool,
+    pub mb100_half: bool,
+    pub mb100_full: bool,
+    pub mb1000_half: bool,
+    pub mb1000_full: bool,
+}
+
+/// When the PHY may save power on an unplugged port, see
+/// [`crate::Igb::set_power_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PowerPolicy {
+    /// Keep the PHY fully powered.
+    #[default]
+    AlwaysOn,
+    /// Once the link has been down for `after`, let the PHY drop into
+    /// energy-detect mode (Smart Power Down). It powers back up by itself
+    /// when a link partner's energy shows up on the wire.
+    EnergyDetect { after: Duration },
+}
+
+/// 1000BASE-T clock role of the local PHY.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MasterSlave {
+    Master,
+    Slave,
+    /// Both ends insisted on the same role; the link can't reach 1000 Mb/s.
+    Fault,
+    /// Not resolved, e.g. the link is below 1000 Mb/s.
+    Unknown,
+}
+
+/// What the link partner advertised during auto-negotiation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkPartnerInfo {
+    /// The partner takes part in auto-negotiation; when false the other
+    /// fields are not meaningful.
+    pub autoneg: bool,
+    pub speeds: LinkSpeeds,
+    pub pause: bool,
+    pub asym_pause: bool,
+    pub remote_fault: bool,
+    pub master_slave: MasterSlave,
+}
+
 pub struct Phy {
     mac: Mac,
     addr: u32,
+    external: bool,
 }
 
 impl Phy {
     pub fn new(mac: Mac) -> Self {
-        Self { mac, addr: 1 }
+        Self {
+            mac,
+            addr: 1,
+            external: false,
+        }
     }
 
     pub fn read_mdic(&mut self, offset: u32) -> Result<u16, DError> {
-        self.mac.read_mdic(self.addr, offset)
+        let _sync = self.aquire_sync(SW_FW_SYNC::SW_PHY_SM0::SET)?;
+        self.mac.read_mdic(self.addr, offset, self.external)
     }
 
     pub fn write_mdic(&mut self, offset: u32, data: u16) -> Result<(), DError> {
-        self.mac.write_mdic(self.addr, offset, data)
+        let _sync = self.aquire_sync(SW_FW_SYNC::SW_PHY_SM0::SET)?;
+        self.mac.write_mdic(self.addr, offset, data, self.external)
+    }
+
+    /// Address an external PHY at MDIO address `addr` instead of the
+    /// internal one, as used with SGMII.
+    pub fn use_external(&mut self, family: DeviceFamily, addr: u32) -> Result<(), DError> {
+        if addr > MAX_PHY_ADDR {
+            return Err(DError::InvalidParameter);
+        }
+        match family {
+            // 82576 由 MDIC.Destination 选择
+            DeviceFamily::I82576 => self.external = true,
+            DeviceFamily::I350 | DeviceFamily::I210 => {
+                self.mac
+                    .write_reg(MDICNFG, MDICNFG_EXT_MDIO | (addr << MDICNFG_PHY_SHIFT));
+            }
+        }
+        self.addr = addr;
+        Ok(())
     }
 
-    // pub fn aquire_sync(&self, flags: SyncFlags) -> Result<Synced, DError> {
-    //     Synced::new(self.mac, flags)
-    // }
+    /// Read Clause 45 register `reg` of MMD `dev` through the Clause 22
+    /// indirect access registers.
+    pub fn read_mmd(&mut self, dev: u8, reg: u16) -> Result<u16, DError> {
+        // 四步访问之间不能让固件插入其它 MDIO 操作，整个序列持有信号量
+        let _sync = self.aquire_sync(SW_FW_SYNC::SW_PHY_SM0::SET)?;
+        self.select_mmd(dev, reg)?;
+        self.mac
+            .read_mdic(self.addr, PHY_MMD_ADDR_DATA, self.external)
+    }
+
+    pub fn write_mmd(&mut self, dev: u8, reg: u16, data: u16) -> Result<(), DError> {
+        let _sync = self.aquire_sync(SW_FW_SYNC::SW_PHY_SM0::SET)?;
+        self.select_mmd(dev, reg)?;
+        self.mac
+            .write_mdic(self.addr, PHY_MMD_ADDR_DATA, data, self.external)
+    }
+
+    /// 调用方已持有 PHY 信号量
+    fn select_mmd(&mut self, dev: u8, reg: u16) -> Result<(), DError> {
+        let dev = dev as u16;
+        if dev > MMD_DEVAD_MASK {
+            return Err(DError::InvalidParameter);
+        }
+        let (addr, external) = (self.addr, self.external);
+        self.mac
+            .write_mdic(addr, PHY_MMD_ACCESS_CTRL, dev, external)?;
+        self.mac
+            .write_mdic(addr, PHY_MMD_ADDR_DATA, reg, external)?;
+        self.mac
+            .write_mdic(addr, PHY_MMD_ACCESS_CTRL, MMD_FUNC_DATA | dev, external)
+    }
+
+    /// Start reading PHY register `offset` and complete on the MDAC
+    /// interrupt instead of polling; `waker` is woken from the interrupt
+    /// handler.
+    pub fn read_mdic_async(
+        &mut self,
+        offset: u32,
+        waker: Arc<AtomicWaker>,
+    ) -> Result<MdioRead, DError> {
+        let sync = self.aquire_sync(SW_FW_SYNC::SW_PHY_SM0::SET)?;
+        let cmd = MdicCmd {
+            phys_addr: self.addr,
+            offset,
+            external: self.external,
+            write: None,
+        };
+        self.mac.mdic_start(cmd, true);
+        Ok(MdioRead {
+            mac: self.mac,
+            _sync: sync,
+            waker,
+        })
+    }
+
+    pub fn aquire_sync(&self, flags: SyncFlags) -> Result<Synced, DError> {
+        Synced::new(self.mac, flags)
+    }
 
     pub fn power_up(&mut self) -> Result<(), DError> {
         let mut mii_reg = self.read_mdic(PHY_CONTROL)?;
@@ -273,15 +501,108 @@ impl Phy {
         self.write_mdic(PHY_CONTROL, mii_reg)
     }
 
+    /// Hardware reset of the PHY through CTRL.PHY_RST.
+    ///
+    /// The PHY comes back with NVM defaults; auto-negotiation settings have
+    /// to be programmed again afterwards.
+    pub fn reset(&mut self) -> Result<(), DError> {
+        if self.mac.manageability_status().phy_reset_blocked {
+            return Err(DError::Unknown("PHY reset blocked by firmware"));
+        }
+        {
+            let _sync = self.aquire_sync(SW_FW_SYNC::SW_PHY_SM0::SET)?;
+            self.mac.set_phy_reset(true);
+            kernel::sleep(Duration::from_micros(100));
+            self.mac.set_phy_reset(false);
+            kernel::sleep(Duration::from_micros(150));
+        }
+        self.mac.wait_phy_cfg_done();
+        Ok(())
+    }
+
+    /// Power the PHY off; the link drops until [`Phy::power_up`].
+    pub fn power_down(&mut self) -> Result<(), DError> {
+        let mut mii_reg = self.read_mdic(PHY_CONTROL)?;
+        mii_reg |= PCTRL::POWER_DOWN::SET.value;
+        self.write_mdic(PHY_CONTROL, mii_reg)
+    }
+
+    /// Low Power Link Up: link at the lowest common speed instead of the
+    /// highest, while in D0 and/or D3.
+    ///
+    /// Takes effect at the next auto-negotiation.
+    pub fn set_lplu(&mut self, family: DeviceFamily, d0: bool, d3: bool) -> Result<(), DError> {
+        match family {
+            DeviceFamily::I82576 => {
+                let mut data = self.read_mdic(IGP02_PHY_POWER_MGMT)?;
+                data &= !(IGP02_PM_D0_LPLU | IGP02_PM_D3_LPLU);
+                if d0 {
+                    data |= IGP02_PM_D0_LPLU;
+                }
+                if d3 {
+                    data |= IGP02_PM_D3_LPLU;
+                }
+                self.write_mdic(IGP02_PHY_POWER_MGMT, data)
+            }
+            DeviceFamily::I350 | DeviceFamily::I210 => {
+                let mut data = self.mac.read_reg(PHPM) & !(PHPM_D0_LPLU | PHPM_D3_LPLU);
+                if d0 {
+                    data |= PHPM_D0_LPLU;
+                }
+                if d3 {
+                    data |= PHPM_D3_LPLU;
+                }
+                self.mac.write_reg(PHPM, data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Current D0 and D3 LPLU settings, see [`Phy::set_lplu`].
+    pub fn lplu(&mut self, family: DeviceFamily) -> Result<(bool, bool), DError> {
+        Ok(match family {
+            DeviceFamily::I82576 => {
+                let data = self.read_mdic(IGP02_PHY_POWER_MGMT)?;
+                (data & IGP02_PM_D0_LPLU != 0, data & IGP02_PM_D3_LPLU != 0)
+            }
+            DeviceFamily::I350 | DeviceFamily::I210 => {
+                let data = self.mac.read_reg(PHPM);
+                (data & PHPM_D0_LPLU != 0, data & PHPM_D3_LPLU != 0)
+            }
+        })
+    }
+
+    /// Smart Power Down: with no link, keep only energy detection powered.
+    pub fn set_smart_power_down(
+        &mut self,
+        family: DeviceFamily,
+        enable: bool,
+    ) -> Result<(), DError> {
+        match family {
+            DeviceFamily::I82576 => {
+                let data = self.read_mdic(IGP02_PHY_POWER_MGMT)? & !IGP02_PM_SPD;
+                let spd = if enable { IGP02_PM_SPD } else { 0 };
+                self.write_mdic(IGP02_PHY_POWER_MGMT, data | spd)
+            }
+            DeviceFamily::I350 | DeviceFamily::I210 => {
+                let data = self.mac.read_reg(PHPM) & !PHPM_SPD_EN;
+                let spd = if enable { PHPM_SPD_EN } else { 0 };
+                self.mac.write_reg(PHPM, data | spd);
+                Ok(())
+            }
+        }
+    }
+
     pub fn read_status(&mut self) -> Result<u16, DError> {
         self.read_mdic(PHY_STATUS)
     }
 
     pub fn wait_for_auto_negotiation_complete(&mut self) -> Result<(), DError> {
-        let interval = core::time::Duration::from_millis(100);
+        let interval = Duration::from_millis(100);
         let try_count = 30; // Wait for up to 3 seconds
 
         wait_for(
+            "auto-negotiation",
             || self.is_auto_negotiation_complete().unwrap_or(false),
             interval,
             Some(try_count),
@@ -293,6 +614,119 @@ impl Phy {
         Ok(status & PSTATUS::AUTO_NEGOTIATION_COMPLETE::Complete.value != 0)
     }
 
+    pub fn link_partner(&mut self) -> Result<LinkPartnerInfo, DError> {
+        let lpa = self.read_mdic(PHY_LP_ABILITY)?;
+        let exp = self.read_mdic(PHY_AUTONEG_EXP)?;
+        let gstatus = self.read_mdic(PHY_1000T_STATUS)?;
+        let has = |reg: u16, field: u16| reg & field != 0;
+
+        let speeds = LinkSpeeds {
+            mb10_half: has(lpa, ANLPAR::LP_10T_HD::SET.value),
+            mb10_full: has(lpa, ANLPAR::LP_10T_FD::SET.value),
+            mb100_half: has(lpa, ANLPAR::LP_100TX_HD::SET.value),
+            mb100_full: has(lpa, ANLPAR::LP_100TX_FD::SET.value),
+            mb1000_half: has(gstatus, GSTATUS::LP_1000T_HD::SET.value),
+            mb1000_full: has(gstatus, GSTATUS::LP_1000T_FD::SET.value),
+        };
+        let master_slave = if has(gstatus, GSTATUS::MS_FAULT::SET.value) {
+            MasterSlave::Fault
+        } else if !speeds.mb1000_half && !speeds.mb1000_full {
+            MasterSlave::Unknown
+        } else if has(gstatus, GSTATUS::MS_RESOLUTION::Master.value) {
+            MasterSlave::Master
+        } else {
+            MasterSlave::Slave
+        };
+
+        Ok(LinkPartnerInfo {
+            autoneg: has(exp, ANE::LP_AN_ABLE::SET.value),
+            speeds,
+            pause: has(lpa, ANLPAR::LP_PAUSE::SET.value),
+            asym_pause: has(lpa, ANLPAR::LP_ASM_DIR::SET.value),
+            remote_fault: has(lpa, ANLPAR::LP_REMOTE_FAULT::SET.value),
+            master_slave,
+        })
+    }
+
+    /// Symmetric and asymmetric PAUSE as advertised by this PHY.
+    pub fn pause_advertised(&mut self) -> Result<(bool, bool), DError> {
+        let adv = self.read_mdic(PHY_AUTONEG_ADV)?;
+        Ok((adv & NWAY_AR_PAUSE != 0, adv & NWAY_AR_ASM_DIR != 0))
+    }
+
+    /// Advertise symmetric and asymmetric PAUSE; returns whether the
+    /// advertisement changed. Takes effect at the next auto-negotiation.
+    pub fn set_pause_advertisement(
+        &mut self,
+        pause: bool,
+        asym_pause: bool,
+    ) -> Result<bool, DError> {
+        let adv = self.read_mdic(PHY_AUTONEG_ADV)?;
+        let mut data = adv & !(NWAY_AR_PAUSE | NWAY_AR_ASM_DIR);
+        if pause {
+            data |= NWAY_AR_PAUSE;
+        }
+        if asym_pause {
+            data |= NWAY_AR_ASM_DIR;
+        }
+        if data == adv {
+            return Ok(false);
+        }
+        self.write_mdic(PHY_AUTONEG_ADV, data)?;
+        Ok(true)
+    }
+
+    /// Let the PHY fall back to a lower speed when 1000BASE-T training keeps
+    /// failing (SmartSpeed on 82576, downshift on I350/I210).
+    ///
+    /// Takes effect at the next auto-negotiation.
+    pub fn set_downshift(&mut self, family: DeviceFamily, enable: bool) -> Result<(), DError> {
+        let (reg, bits) = match family {
+            DeviceFamily::I82576 => (IGP_PORT_CONFIG, IGP_PSCFR_SMART_SPEED),
+            DeviceFamily::I350 | DeviceFamily::I210 => {
+                (I82580_CFG_REG, I82580_CFG_ENABLE_DOWNSHIFT)
+            }
+        };
+        let mut data = self.read_mdic(reg)?;
+        if enable {
+            data |= bits;
+        } else {
+            data &= !bits;
+        }
+        self.write_mdic(reg, data)
+    }
+
+    /// Loop transmitted frames back inside the PHY at a forced 1000 Mb/s
+    /// full duplex, or return to auto-negotiation.
+    pub fn set_loopback(&mut self, family: DeviceFamily, enable: bool) -> Result<(), DError> {
+        if !enable {
+            let control = self.read_mdic(PHY_CONTROL)? & !PCTRL::LOOPBACK::Enable.value;
+            self.write_mdic(PHY_CONTROL, control)?;
+            if family == DeviceFamily::I350 {
+                self.write_mdic(I82580_PHY_LBK_CTRL, 0)?;
+            }
+            return self.enable_auto_negotiation();
+        }
+        if family == DeviceFamily::I350 {
+            self.write_mdic(I82580_PHY_LBK_CTRL, I82580_LBK_MII)?;
+        }
+        // 稍作等待，否则环回测试偶尔失败
+        kernel::sleep(Duration::from_millis(50));
+        let control = PCTRL::LOOPBACK::Enable
+            + PCTRL::SPEED_SELECTION_MSB::SET
+            + PCTRL::DUPLEX_MODE::Full
+            + PCTRL::AUTO_NEGOTIATION_ENABLE::Disable;
+        self.write_mdic(PHY_CONTROL, control.value)
+    }
+
+    /// Whether both ends advertise 1000BASE-T, i.e. a link below 1000 Mb/s
+    /// means the PHY downshifted.
+    pub fn gigabit_possible(&mut self) -> Result<bool, DError> {
+        let ours = self.read_mdic(PHY_1000T_CTRL)? & (CR_1000T_HD_CAPS | CR_1000T_FD_CAPS) != 0;
+        let speeds = self.link_partner()?.speeds;
+        Ok(ours && (speeds.mb1000_half || speeds.mb1000_full))
+    }
+
     pub fn enable_auto_negotiation(&mut self) -> Result<(), DError> {
         debug!("Enabling auto-negotiation for PHY at address {}", self.addr);
         let mut control = self.read_mdic(PHY_CONTROL)?;
@@ -302,41 +736,49 @@ impl Phy {
     }
 }
 
-// pub struct Synced {
-//     mac: Mac,
-//     mask: u32,
-// }
-
-// impl Synced {
-//     pub fn new(mac: Mac, flags: SyncFlags) -> Result<Self, IgbError> {
-//         let semaphore = Semaphore::new(mac)?;
-//         let mask = mac.software_sync_aquire(flags)?;
-//         drop(semaphore);
-//         Ok(Self { mac, mask })
-//     }
-// }
-
-// impl Drop for Synced {
-//     fn drop(&mut self) {
-//         let semaphore = Semaphore::new(self.mac).unwrap();
-//         self.mac.software_sync_release(self.mask);
-//         drop(semaphore);
-//     }
-// }
-
-// pub struct Semaphore {
-//     mac: Mac,
-// }
-
-// impl Semaphore {
-//     pub fn new(mac: Mac) -> Result<Self, DError> {
-//         mac.software_semaphore_aquire()?;
-//         Ok(Self { mac })
-//     }
-// }
-
-// impl Drop for Semaphore {
-//     fn drop(&mut self) {
-//         self.mac.software_semaphore_release();
-//     }
-// }
+/// Ownership of SW/FW shared resources, released on drop.
+pub struct Synced {
+    mac: Mac,
+    mask: u32,
+}
+
+impl Synced {
+    pub fn new(mac: Mac, flags: SyncFlags) -> Result<Self, DError> {
+        let mask = mac.software_sync_aquire(flags)?;
+        Ok(Self { mac, mask })
+    }
+}
+
+impl Drop for Synced {
+    fn drop(&mut self) {
+        self.mac.software_sync_release(self.mask);
+    }
+}
+
+/// A PHY register read in progress, see [`crate::Igb::read_phy_async`].
+///
+/// Holds the PHY semaphore until it completes or is dropped.
+pub struct MdioRead {
+    mac: Mac,
+    _sync: Synced,
+    waker: Arc<AtomicWaker>,
+}
+
+impl Drop for MdioRead {
+    fn drop(&mut self) {
+        // 取消时不留下失效的 waker
+        self.waker.take();
+    }
+}
+
+impl Future for MdioRead {
+    type Output = Result<u16, DError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.waker.register(cx.waker());
+        match self.mac.mdic_poll() {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+}
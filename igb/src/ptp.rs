@@ -0,0 +1,231 @@
+use crate::{
+    DError,
+    family::DeviceFamily,
+    mac::Mac,
+    sdp::{Sdp, SdpFunction},
+};
+
+const SYSTIML: usize = 0xB600; // System Time, low
+const SYSTIMH: usize = 0xB604; // System Time, high
+const TIMINCA: usize = 0xB608; // Increment Attributes
+const SYSTIMR: usize = 0xB6F8; // System Time residue (I350/I210)
+pub(crate) const TSYNCTXCTL: usize = 0xB614; // TX Time Sync Control
+const TSYNCRXCTL: usize = 0xB620; // RX Time Sync Control
+pub(crate) const TXSTMPL: usize = 0xB618; // TX timestamp, low
+pub(crate) const TXSTMPH: usize = 0xB61C; // TX timestamp, high
+pub(crate) const RXSTMPL: usize = 0xB624; // RX timestamp, low
+pub(crate) const RXSTMPH: usize = 0xB628; // RX timestamp, high
+const TSAUXC: usize = 0xB640; // Auxiliary Control
+const TRGTTIML0: usize = 0xB644; // Target Time 0, low; target 1 follows at +8
+const TRGTTIMH0: usize = 0xB648;
+const FREQOUT0: usize = 0xB654; // Frequency Out 0 half period
+const TSICR: usize = 0xB66C; // Time Sync Interrupt Cause
+const TSIM: usize = 0xB674; // Time Sync Interrupt Mask
+
+pub(crate) const TSYNCTXCTL_VALID: u32 = 1 << 0;
+const TSYNCXCTL_EN: u32 = 1 << 4;
+const TSYNCRXCTL_TYPE_ALL: u32 = 0x4 << 1;
+
+const TSAUXC_EN_TT0: u32 = 1 << 0;
+const TSAUXC_EN_CLK0: u32 = 1 << 2;
+const TSAUXC_ST0: u32 = 1 << 4; // Start clock 0 at target time 0
+const TSAUXC_DISABLE_SYSTIME: u32 = 1 << 31;
+
+const TSINTR_TT0: u32 = 1 << 3;
+
+/// Number of target time registers.
+pub const TARGET_TIME_COUNT: usize = 2;
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+// 82576: 每 16ns 加 16 << 19，即 SYSTIM 以 2^-19 ns 为单位
+const TSYNC_SHIFT_82576: u32 = 19;
+const INCPERIOD_82576: u32 = 1 << 24;
+const INCVALUE_82576: u32 = 16 << TSYNC_SHIFT_82576;
+const INCVALUE_82576_MASK: u32 = INCPERIOD_82576 - 1;
+// I350/I210: TIMINCA 为相对标称频率的有符号修正
+const TIMINCA_ISGN: u32 = 1 << 31;
+const TIMINCA_INCVALUE_MASK: u32 = 0x7FFF_FFFF;
+
+/// Largest frequency adjustment accepted by [`crate::Igb::adjust_systim_ppb`].
+pub fn max_adjust_ppb(family: DeviceFamily) -> u32 {
+    match family {
+        DeviceFamily::I82576 => 999_999_881,
+        DeviceFamily::I350 | DeviceFamily::I210 => 62_499_999,
+    }
+}
+
+/// Convert a SYSTIM-format register pair to nanoseconds.
+pub(crate) fn to_ns(family: DeviceFamily, low: u32, high: u32) -> u64 {
+    match family {
+        DeviceFamily::I82576 => (((high as u64) << 32) | low as u64) >> TSYNC_SHIFT_82576,
+        // 40 位纳秒计数器
+        DeviceFamily::I350 => (((high & 0xFF) as u64) << 32) | low as u64,
+        // 秒 + 纳秒格式
+        DeviceFamily::I210 => high as u64 * NSEC_PER_SEC + low as u64,
+    }
+}
+
+fn from_ns(family: DeviceFamily, ns: u64) -> (u32, u32) {
+    match family {
+        DeviceFamily::I82576 => {
+            let raw = ns << TSYNC_SHIFT_82576;
+            (raw as u32, (raw >> 32) as u32)
+        }
+        DeviceFamily::I350 => (ns as u32, ((ns >> 32) & 0xFF) as u32),
+        DeviceFamily::I210 => ((ns % NSEC_PER_SEC) as u32, (ns / NSEC_PER_SEC) as u32),
+    }
+}
+
+/// Program the nominal increment and start SYSTIM.
+pub(crate) fn init(mac: &mut Mac, family: DeviceFamily) {
+    let timinca = match family {
+        DeviceFamily::I82576 => INCPERIOD_82576 | INCVALUE_82576,
+        DeviceFamily::I350 | DeviceFamily::I210 => 0,
+    };
+    mac.write_reg(TIMINCA, timinca);
+    start_systim(mac);
+}
+
+pub(crate) fn read_systim(mac: &Mac, family: DeviceFamily) -> u64 {
+    // 读最低位的寄存器会锁存其余部分
+    if family != DeviceFamily::I82576 {
+        mac.read_reg(SYSTIMR);
+    }
+    let low = mac.read_reg(SYSTIML);
+    to_ns(family, low, mac.read_reg(SYSTIMH))
+}
+
+pub(crate) fn set_systim(mac: &mut Mac, family: DeviceFamily, ns: u64) {
+    let (low, high) = from_ns(family, ns);
+    mac.write_reg(SYSTIML, low);
+    mac.write_reg(SYSTIMH, high);
+}
+
+pub(crate) fn adjust_ppb(mac: &mut Mac, family: DeviceFamily, ppb: i32) -> Result<(), DError> {
+    if ppb.unsigned_abs() > max_adjust_ppb(family) {
+        return Err(DError::InvalidParameter);
+    }
+    let timinca = match family {
+        DeviceFamily::I82576 => {
+            let diff = (INCVALUE_82576 as u64 * ppb.unsigned_abs() as u64 / NSEC_PER_SEC) as u32;
+            let inc = if ppb < 0 {
+                INCVALUE_82576 - diff
+            } else {
+                INCVALUE_82576 + diff
+            };
+            INCPERIOD_82576 | (inc & INCVALUE_82576_MASK)
+        }
+        DeviceFamily::I350 | DeviceFamily::I210 => {
+            // 修正量单位为每 8ns 周期 2^-32 ns
+            let rate = ((ppb.unsigned_abs() as u64) << 26) / 1_953_125;
+            let mut inca = rate as u32 & TIMINCA_INCVALUE_MASK;
+            if ppb < 0 {
+                inca |= TIMINCA_ISGN;
+            }
+            inca
+        }
+    };
+    mac.write_reg(TIMINCA, timinca);
+    Ok(())
+}
+
+/// Enable hardware timestamping of all received packets and of transmit
+/// requests sent with [`crate::TxRing::send_timestamped`] (I210).
+pub(crate) fn set_timestamping(mac: &mut Mac, rx: bool, tx: bool) {
+    let rxctl = if rx {
+        TSYNCXCTL_EN | TSYNCRXCTL_TYPE_ALL
+    } else {
+        0
+    };
+    mac.write_reg(TSYNCRXCTL, rxctl);
+    mac.write_reg(TSYNCTXCTL, if tx { TSYNCXCTL_EN } else { 0 });
+    if rx || tx {
+        start_systim(mac);
+    }
+}
+
+fn start_systim(mac: &mut Mac) {
+    let tsauxc = mac.read_reg(TSAUXC);
+    mac.write_reg(TSAUXC, tsauxc & !TSAUXC_DISABLE_SYSTIME);
+}
+
+/// 目标时间与 SYSTIM 同一格式比较，按器件换算
+fn write_target_time(mac: &mut Mac, family: DeviceFamily, timer: usize, time_ns: u64) {
+    let off = timer * 8;
+    let (low, high) = from_ns(family, time_ns);
+    mac.write_reg(TRGTTIML0 + off, low);
+    mac.write_reg(TRGTTIMH0 + off, high);
+}
+
+/// Arm target time `timer` to fire at SYSTIM `time_ns`, optionally toggling
+/// SDP `pin` when it does.
+pub(crate) fn set_target_time(
+    mac: &mut Mac,
+    family: DeviceFamily,
+    timer: usize,
+    time_ns: u64,
+    pin: Option<u8>,
+) -> Result<(), DError> {
+    if timer >= TARGET_TIME_COUNT {
+        return Err(DError::InvalidParameter);
+    }
+    if let Some(pin) = pin {
+        let function = if timer == 0 {
+            SdpFunction::TargetTime0
+        } else {
+            SdpFunction::TargetTime1
+        };
+        Sdp::new(mac, pin)?.route(function);
+    }
+    start_systim(mac);
+    write_target_time(mac, family, timer, time_ns);
+
+    let tsim = mac.read_reg(TSIM);
+    mac.write_reg(TSIM, tsim | (TSINTR_TT0 << timer));
+    let tsauxc = mac.read_reg(TSAUXC);
+    mac.write_reg(TSAUXC, tsauxc | (TSAUXC_EN_TT0 << timer));
+    mac.enable_time_sync_interrupt();
+    Ok(())
+}
+
+pub(crate) fn clear_target_time(mac: &mut Mac, timer: usize) -> Result<(), DError> {
+    if timer >= TARGET_TIME_COUNT {
+        return Err(DError::InvalidParameter);
+    }
+    let tsauxc = mac.read_reg(TSAUXC);
+    mac.write_reg(TSAUXC, tsauxc & !(TSAUXC_EN_TT0 << timer));
+    let tsim = mac.read_reg(TSIM);
+    mac.write_reg(TSIM, tsim & !(TSINTR_TT0 << timer));
+    Ok(())
+}
+
+/// Output a 1 Hz square wave on SDP `pin`, rising on each SYSTIM second.
+pub(crate) fn enable_pps(mac: &mut Mac, family: DeviceFamily, pin: u8) -> Result<(), DError> {
+    // 只有 I210 的频率时钟按纳秒计半周期
+    if family != DeviceFamily::I210 {
+        return Err(DError::InvalidParameter);
+    }
+    Sdp::new(mac, pin)?.route(SdpFunction::ClockOut0);
+    start_systim(mac);
+
+    // 从下下个整秒开始，避免起始时间在写入过程中已经过去
+    let start = (read_systim(mac, family) / NSEC_PER_SEC + 2) * NSEC_PER_SEC;
+    write_target_time(mac, family, 0, start);
+    mac.write_reg(FREQOUT0, (NSEC_PER_SEC / 2) as u32);
+    let tsauxc = mac.read_reg(TSAUXC);
+    mac.write_reg(TSAUXC, tsauxc | TSAUXC_EN_CLK0 | TSAUXC_ST0);
+    Ok(())
+}
+
+pub(crate) fn disable_pps(mac: &mut Mac) {
+    let tsauxc = mac.read_reg(TSAUXC);
+    mac.write_reg(TSAUXC, tsauxc & !(TSAUXC_EN_CLK0 | TSAUXC_ST0));
+}
+
+/// Read and clear the time sync causes, returning the fired target times.
+pub(crate) fn ack_interrupt(mac: &mut Mac) -> u8 {
+    let tsicr = mac.read_reg(TSICR);
+    mac.write_reg(TSICR, tsicr);
+    ((tsicr >> 3) & 0x3) as u8
+}
@@ -1,44 +1,91 @@
-use core::{cell::UnsafeCell, ptr::NonNull, time::Duration};
+use core::{
+    cell::UnsafeCell,
+    future::poll_fn,
+    pin::pin,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    task::{Poll, Waker},
+    time::Duration,
+};
 
-use alloc::vec::Vec;
-use dma_api::{DVec, Direction};
+use alloc::{sync::Arc, vec::Vec};
 use futures::task::AtomicWaker;
 use log::debug;
 use mbarrier::mb;
-use tock_registers::register_bitfields;
+use tock_registers::{
+    fields::FieldValue,
+    interfaces::Writeable,
+    register_bitfields, register_structs,
+    registers::{ReadOnly, ReadWrite},
+};
 
 use crate::{
     Request,
-    descriptor::{AdvTxDesc, Descriptor},
+    descriptor::{AdvRxDesc, AdvTxDesc, Descriptor},
+    dma::DmaMem,
     err::DError,
-    osal::wait_for,
+    family::DeviceFamily,
+    osal::{Direction, kernel, wait_for},
 };
 
+mod pair;
 mod rx;
 mod tx;
-pub use rx::{RxPacket, RxRing};
-pub use tx::TxRing;
+pub use pair::{NapiLike, QueuePair};
+pub(crate) use rx::RxRefill;
+pub use rx::{RxMeta, RxPacket, RxRing};
+pub use tx::{TxCompletion, TxRing};
 
 pub const DEFAULT_RING_SIZE: usize = 256;
-const RDBAL: usize = 0xC000; // RX Descriptor Base Address Low
-const RDBAH: usize = 0xC004; // RX Descriptor Base Address High
-const RDLEN: usize = 0xC008; // RX Descriptor Length
-const SRRCTL: usize = 0xC00C; // RX Descriptor Control
-const RDH: usize = 0xC010; // RX Descriptor Head
-const RDT: usize = 0xC018; // RX Descriptor Tail
-const RXDCTL: usize = 0xC028; // RX Descriptor Control
-// const RXCTL: usize = 0xC014; // RX Control
-// const RQDPC: usize = 0xC030; // RX Descriptor Polling Control
-
-// TX descriptor registers
-const TDBAL: usize = 0xE000; // TX Descriptor Base Address Low
-const TDBAH: usize = 0xE004; // TX Descriptor Base Address High
-const TDLEN: usize = 0xE008; // TX Descriptor Length
-const TDH: usize = 0xE010; // TX Descriptor Head
-const TDT: usize = 0xE018; // TX Descriptor Tail
-const TXDCTL: usize = 0xE028; // TX Descriptor Control
-// const TDWBAL: usize = 0xE038; // TX Descriptor Write Back Address Low
-// const TDWBAH: usize = 0xE03C; // TX Descriptor Write Back Address High
+/// 每个队列的寄存器块大小
+const QUEUE_REGS_STRIDE: usize = 0x40;
+
+register_structs! {
+    /// Descriptor ring registers of one queue, `0x40` bytes per queue from
+    /// 0xC000 (RX) or 0xE000 (TX). Both directions share the layout.
+    pub QueueRegs {
+        (0x00 => bal: ReadWrite<u32>),                    // RDBAL / TDBAL
+        (0x04 => bah: ReadWrite<u32>),                    // RDBAH / TDBAH
+        (0x08 => len: ReadWrite<u32>),                    // RDLEN / TDLEN
+        (0x0C => srrctl: ReadWrite<u32, SRRCTL::Register>), // RX only
+        (0x10 => head: ReadWrite<u32>),                   // RDH / TDH
+        (0x14 => dca_ctrl: ReadWrite<u32>),               // DCA_RXCTRL / DCA_TXCTRL
+        (0x18 => tail: ReadWrite<u32>),                   // RDT / TDT
+        (0x1C => _rsv0),
+        (0x28 => dctl: ReadWrite<u32>),                   // RXDCTL / TXDCTL
+        (0x2C => _rsv1),
+        (0x30 => rqdpc: ReadOnly<u32>),                   // RX only, clear on read
+        (0x34 => _rsv2),
+        // 0x38/0x3C: TDWBAL/TDWBAH (TX only), not used
+        (0x40 => @END),
+    }
+}
+
+/// Byte offset of SRRCTL of RX queue `queue` from the MMIO base.
+pub(crate) fn srrctl_offset(queue: usize) -> usize {
+    AdvRxDesc::QUEUE_BASE + queue * QUEUE_REGS_STRIDE + core::mem::offset_of!(QueueRegs, srrctl)
+}
+
+// DCA_RXCTRL: 描述符回写不设置宽松排序，否则 DD 可能先于数据可见
+const DCA_RXCTRL_DESC_READ_RO: u32 = 1 << 9;
+const DCA_RXCTRL_DATA_WRITE_RO: u32 = 1 << 13;
+const DCA_RXCTRL_HEADER_WRITE_RO: u32 = 1 << 15;
+
+const PSRTYPE: usize = 0x5480; // Packet Split Receive Type, 4 bytes per queue
+
+// PSRTYPE: headers the hardware recognizes when splitting/replicating
+const PSRTYPE_TCPHDR: u32 = 1 << 4;
+const PSRTYPE_UDPHDR: u32 = 1 << 5;
+const PSRTYPE_IPV4HDR: u32 = 1 << 8;
+const PSRTYPE_IPV6HDR: u32 = 1 << 9;
+const PSRTYPE_L2HDR: u32 = 1 << 12;
+
+const EIMS: usize = 0x1524; // Extended Interrupt Mask Set
+const EIMC: usize = 0x1528; // Extended Interrupt Mask Clear
+const IMS: usize = 0x00D0; // Interrupt Mask Set
+const IMC: usize = 0x00D8; // Interrupt Mask Clear
+/// 传统模式下各队列共用的收发中断原因：TXDW、TXQE、RXT0
+const IMS_QUEUE_CAUSES: u32 = 1 << 0 | 1 << 1 | 1 << 7;
 
 const PACKET_SIZE_KB: u32 = 2;
 const PACKET_SIZE: u32 = PACKET_SIZE_KB * 1024;
@@ -47,10 +94,6 @@ register_bitfields! [
     // First parameter is the register width. Can be u8, u16, u32, or u64.
     u32,
 
-    RDLEN [
-        LEN OFFSET(7) NUMBITS(13)[],
-    ],
-
     pub SRRCTL [
         BSIZEPACKET OFFSET(0) NUMBITS(7)[],
         BSIZEHEADER OFFSET(8) NUMBITS(4)[],
@@ -97,29 +140,539 @@ register_bitfields! [
 
 ];
 
+const FCS_LEN: usize = 4;
+/// Ethernet header, VLAN tag and FCS around the MTU.
+const FRAME_OVERHEAD: usize = 14 + 4 + FCS_LEN;
+const MAX_BUFFER_SIZE: usize = 127 * 1024;
+const MAX_RING_SIZE: usize = 4096;
+
+const MAX_THRESH: u8 = 31;
+
+/// SRRCTL.RDMTS is 5 bits in units of 16 descriptors.
+const RDMTS_UNIT: usize = 16;
+const RDMTS_MAX: usize = 31;
+
+/// SRRCTL.BSIZEHEADER is 4 bits in 64-byte units.
+const HEADER_SIZE_UNIT: usize = 64;
+const MAX_HEADER_SIZE: usize = 15 * HEADER_SIZE_UNIT;
+
+/// How RX frame headers are placed into a separate header buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeaderMode {
+    /// One buffer per descriptor, no header buffer.
+    #[default]
+    None,
+    /// Copy the headers of every frame into the header buffer; the whole
+    /// frame still lands in the packet buffer.
+    ReplicateAlways,
+    /// Like [`HeaderMode::ReplicateAlways`], but only for frames larger than
+    /// the header buffer.
+    ReplicateLargePacket,
+}
+
+impl HeaderMode {
+    fn desc_type(self) -> FieldValue<u32, SRRCTL::Register> {
+        match self {
+            HeaderMode::None => SRRCTL::DESCTYPE::AdvancedOneBuffer,
+            HeaderMode::ReplicateAlways => SRRCTL::DESCTYPE::AdvancedHeaderReplicationAlways,
+            HeaderMode::ReplicateLargePacket => {
+                SRRCTL::DESCTYPE::AdvancedHeaderReplicationLargePacket
+            }
+        }
+    }
+}
+
+/// RX descriptor layout of an [`RxRing`], fixed when the ring is created.
+///
+/// Accessors that only make sense with a header buffer, such as
+/// [`RxPacket::header`], only exist on [`HeaderSplit`] rings.
+pub trait RxLayout: 'static {
+    /// Whether the ring has a header buffer, i.e. [`RingConfig::header_mode`]
+    /// is not [`HeaderMode::None`].
+    const HEADERS: bool;
+}
+
+/// One packet buffer per descriptor, [`HeaderMode::None`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OneBuffer {}
+
+/// Packet buffer plus header buffer per descriptor, see
+/// [`HeaderMode::ReplicateAlways`] and [`HeaderMode::ReplicateLargePacket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderSplit {}
+
+impl RxLayout for OneBuffer {
+    const HEADERS: bool = false;
+}
+
+impl RxLayout for HeaderSplit {
+    const HEADERS: bool = true;
+}
+
+/// Descriptor prefetch, host and write-back thresholds (xXDCTL).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DescThresholds {
+    /// Prefetch when fewer than this many descriptors are cached on chip.
+    pub prefetch: u8,
+    /// Only prefetch when at least this many descriptors are available in host memory.
+    pub host: u8,
+    /// Write back completed descriptors in batches of this many; 0 writes each
+    /// one back immediately.
+    pub writeback: u8,
+}
+
+impl DescThresholds {
+    pub const RX_DEFAULT: Self = Self {
+        prefetch: 8,
+        host: 8,
+        writeback: 1,
+    };
+    pub const TX_DEFAULT: Self = Self {
+        prefetch: 0,
+        host: 0,
+        writeback: 1,
+    };
+
+    fn validate(&self, ring_size: usize, max_writeback: u8) -> Result<(), DError> {
+        if self.prefetch > MAX_THRESH || self.host > MAX_THRESH || self.writeback > max_writeback {
+            return Err(DError::InvalidParameter);
+        }
+        if self.prefetch as usize >= ring_size || self.writeback as usize >= ring_size {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(())
+    }
+
+    fn rxdctl(&self) -> FieldValue<u32, RXDCTL::Register> {
+        RXDCTL::PTHRESH.val(self.prefetch as _)
+            + RXDCTL::HTHRESH.val(self.host as _)
+            + RXDCTL::WTHRESH.val(self.writeback as _)
+    }
+
+    fn txdctl(&self) -> FieldValue<u32, TXDCTL::Register> {
+        TXDCTL::PTHRESH.val(self.prefetch as _)
+            + TXDCTL::HTHRESH.val(self.host as _)
+            + TXDCTL::WTHRESH.val(self.writeback as _)
+    }
+}
+
+/// How an [`RxRing`] replaces buffers taken out with
+/// [`RxPacket::into_request`], using buffers from
+/// [`RxRing::add_pool_buffer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RefillPolicy {
+    /// Only [`RxRing::submit`] and [`RxRing::refill`] post buffers.
+    #[default]
+    Manual,
+    /// Post a pool buffer as soon as a buffer is taken out.
+    Immediate,
+    /// Post pool buffers in bulk from the RX interrupt, in
+    /// [`crate::Igb::handle_interrupt`] or
+    /// [`crate::Igb::handle_vector_interrupt`], once at least this many
+    /// descriptors are free. Enable the `irq-safe` feature when the handler
+    /// can preempt the task using the ring.
+    Threshold(usize),
+}
+
+/// Move up to `budget` received frames from `rx` to `tx` without copying.
+///
+/// Each frame's RX buffer is queued on `tx` as is and replaced on `rx` by a
+/// buffer from its pool ([`RxRing::add_pool_buffer`]); buffers `tx` has
+/// finished sending go back into that pool. Frames spanning several
+/// descriptors or with errors are dropped. Stops early when `tx` is full or
+/// the pool is empty. Returns the number of frames forwarded.
+///
+/// The NIC reads a forwarded frame straight from its RX buffer, so every
+/// buffer given to `rx`, through [`RxRing::submit`] or the pool, must be
+/// mapped for both directions with [`Request::new_forward`]. A buffer mapped
+/// `FromDevice` can't be written back for the device on non-coherent
+/// platforms; its frame is dropped and the buffer stays on `rx`.
+///
+/// `tx` must only be used through this function, otherwise its finished
+/// requests don't come back to the pool.
+pub fn forward<L: RxLayout>(rx: &mut RxRing<L>, tx: &mut TxRing, budget: usize) -> usize {
+    while let Some(request) = tx.next_finished() {
+        let _ = rx.add_pool_buffer(request);
+    }
+
+    let mut done = 0;
+    while done < budget && !tx.is_queue_full() && rx.pool_len() > 0 {
+        let Some(pkt) = rx.next_pkt() else {
+            break;
+        };
+        let meta = *pkt.meta();
+        if pkt.segments() > 1 || meta.has_errors || !pkt.request().is_forwardable() {
+            // 丢弃后缓冲区自动回到环中
+            continue;
+        }
+        let mut request = pkt.into_request();
+        rx.refill();
+        request.set_len(meta.len);
+        if let Err(e) = tx.send(request) {
+            datapath_debug!("forward: send failed: {e:?}");
+            break;
+        }
+        done += 1;
+    }
+    done
+}
+
+/// Per-ring settings for [`crate::Igb::new_ring_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RingConfig {
+    /// Number of descriptors, a power of two from 8 to 4096, so index
+    /// wrap-around on the datapath is a mask instead of a division.
+    pub size: usize,
+    /// Length of each RX buffer (SRRCTL.BSIZEPACKET) and largest TX request,
+    /// a multiple of 1 KB up to 127 KB.
+    pub buffer_size: usize,
+    /// Largest payload expected on this ring; a frame of this size must fit
+    /// in one buffer.
+    pub mtu: usize,
+    pub rx_thresholds: DescThresholds,
+    pub tx_thresholds: DescThresholds,
+    /// Highest bus address the device may reach, e.g. [`dma_bit_mask`]`(32)`
+    /// behind a 32-bit IOMMU window. Descriptors are allocated below it and
+    /// requests outside it are rejected with [`DError::NoMemory`].
+    pub dma_mask: u64,
+    pub header_mode: HeaderMode,
+    /// Header buffer length per descriptor when `header_mode` is set, a
+    /// multiple of 64 bytes up to 960.
+    pub header_size: usize,
+    /// Set NSE in RX descriptors so packet and header writes skip the CPU
+    /// cache snoop. Only takes effect with [`crate::PcieTuning::no_snoop`].
+    ///
+    /// Unsound on cache-coherent platforms where the DMA layer does no
+    /// cache maintenance: the CPU would read stale lines. Only enable when
+    /// [`RxRing`] buffers are invalidated by software before they are read.
+    pub rx_no_snoop: bool,
+    /// Allow relaxed ordering for RX descriptor fetches and packet/header
+    /// writes. Descriptor write-back always stays strongly ordered so a
+    /// completed descriptor is never seen before its data.
+    ///
+    /// Only takes effect with [`crate::PcieTuning::relaxed_ordering`].
+    pub rx_relaxed_ordering: bool,
+    /// Set Report Status on every `tx_rs_interval`-th TX descriptor only,
+    /// so the device writes back one descriptor per batch instead of one
+    /// per packet. At least 1 and smaller than `size`.
+    ///
+    /// Requests after the last RS descriptor are returned by
+    /// [`TxRing::next_finished`] once a later RS descriptor completes, or by
+    /// [`TxRing::flush`].
+    pub tx_rs_interval: usize,
+}
+
+impl Default for RingConfig {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_RING_SIZE,
+            buffer_size: PACKET_SIZE as usize,
+            mtu: 1500,
+            rx_thresholds: DescThresholds::RX_DEFAULT,
+            tx_thresholds: DescThresholds::TX_DEFAULT,
+            dma_mask: u64::MAX,
+            header_mode: HeaderMode::None,
+            header_size: 256,
+            rx_no_snoop: false,
+            rx_relaxed_ordering: false,
+            tx_rs_interval: 1,
+        }
+    }
+}
+
+/// 整个缓冲区 `[addr, addr + len)` 都必须落在 DMA 掩码内，否则写入寄存器会被截断
+fn check_dma_range(addr: u64, len: usize, dma_mask: u64) -> Result<(), DError> {
+    let last = addr
+        .checked_add(len.max(1) as u64 - 1)
+        .ok_or(DError::NoMemory)?;
+    if last & !dma_mask != 0 {
+        return Err(DError::NoMemory);
+    }
+    Ok(())
+}
+
+/// Mask for DMA addresses of `bits` bits, e.g. `dma_bit_mask(32)` to keep
+/// rings and buffers below 4 GB via [`RingConfig::dma_mask`].
+pub const fn dma_bit_mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// 等待结束或被取消时注销 waker，环上不留已失效任务的 waker
+struct WakerGuard(Arc<AtomicWaker>);
+
+impl Drop for WakerGuard {
+    fn drop(&mut self) {
+        self.0.take();
+    }
+}
+
+/// Completions one TX ring can have outstanding from [`TxRing::send_wait`].
+pub const MAX_TX_WAITERS: usize = 32;
+
+/// 等待发送完成的任务，固定槽位：不分配内存，中断中无锁唤醒
+pub(crate) struct WaitList {
+    /// 已占用的槽位
+    used: AtomicU32,
+    slots: [AtomicWaker; MAX_TX_WAITERS],
+}
+
+impl Default for WaitList {
+    fn default() -> Self {
+        Self {
+            used: AtomicU32::new(0),
+            slots: [const { AtomicWaker::new() }; MAX_TX_WAITERS],
+        }
+    }
+}
+
+impl WaitList {
+    fn acquire(&self) -> Option<usize> {
+        let mut used = self.used.load(Ordering::Acquire);
+        loop {
+            let slot = used.trailing_ones() as usize;
+            if slot == MAX_TX_WAITERS {
+                return None;
+            }
+            match self.used.compare_exchange_weak(
+                used,
+                used | 1 << slot,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(slot),
+                Err(now) => used = now,
+            }
+        }
+    }
+
+    fn register(&self, slot: usize, waker: &Waker) {
+        self.slots[slot].register(waker);
+    }
+
+    fn release(&self, slot: usize) {
+        self.slots[slot].take();
+        self.used.fetch_and(!(1 << slot), Ordering::Release);
+    }
+
+    pub fn has_waiters(&self) -> bool {
+        self.used.load(Ordering::Acquire) != 0
+    }
+
+    /// 唤醒所有等待者，未完成的在 poll 时重新登记
+    pub fn wake(&self) {
+        let mut used = self.used.load(Ordering::Acquire);
+        while used != 0 {
+            self.slots[used.trailing_zeros() as usize].wake();
+            used &= used - 1;
+        }
+    }
+}
+
+/// 从调用时起超过 `timeout` 仍未完成就放弃 `fut`，返回 [`DError::TimedOut`]。
+/// 驱动没有定时器中断，等待期间每次 Pending 都立即唤醒自己，由执行器反复
+/// 轮询 osal 时钟
+fn with_timeout<T>(
+    op: &'static str,
+    fut: impl Future<Output = Result<T, DError>>,
+    timeout: Duration,
+) -> impl Future<Output = Result<T, DError>> {
+    let start = kernel::now();
+    async move {
+        let mut fut = pin!(fut);
+        poll_fn(|cx| {
+            if let Poll::Ready(result) = fut.as_mut().poll(cx) {
+                return Poll::Ready(result);
+            }
+            let elapsed = kernel::now().saturating_sub(start);
+            if elapsed >= timeout {
+                return Poll::Ready(Err(DError::TimedOut { op, elapsed }));
+            }
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// 按数据手册检查描述符环：基址 128 字节对齐，长度为 8 个描述符的倍数，
+/// 整个环在 DMA 掩码内。一次映射得到单个总线地址，硬件看到的就是连续内存。
+fn validate_desc_ring<D: Descriptor>(descriptors: &DmaMem<D>, dma_mask: u64) -> Result<(), DError> {
+    validate_ring_size(descriptors.len())?;
+    if !descriptors.bus_addr().is_multiple_of(128) {
+        return Err(DError::Misaligned);
+    }
+    check_dma_range(
+        descriptors.bus_addr(),
+        descriptors.len() * core::mem::size_of::<D>(),
+        dma_mask,
+    )
+}
+
+/// 中断模式下允许的最大 WTHRESH：82576 在 MSI-X 下中断不会刷新未满
+/// WTHRESH 的写回，只能逐个写回
+pub(crate) fn max_writeback(family: DeviceFamily, msix: bool) -> u8 {
+    if family == DeviceFamily::I82576 && msix {
+        1
+    } else {
+        MAX_THRESH
+    }
+}
+
+fn validate_ring_size(size: usize) -> Result<(), DError> {
+    // RDLEN/TDLEN 需要 128 字节对齐，即 8 个描述符；2 的幂便于用掩码回绕
+    if !(8..=MAX_RING_SIZE).contains(&size) || !size.is_power_of_two() {
+        return Err(DError::InvalidParameter);
+    }
+    Ok(())
+}
+
+fn validate_rs_interval(interval: usize, size: usize) -> Result<(), DError> {
+    // 环中始终空出一个描述符，间隔不能覆盖整个环
+    if interval == 0 || interval >= size {
+        return Err(DError::InvalidParameter);
+    }
+    Ok(())
+}
+
+impl RingConfig {
+    /// Largest frame on the wire, including L2 header, VLAN tag and FCS.
+    pub(crate) fn frame_size(&self) -> usize {
+        self.mtu + FRAME_OVERHEAD
+    }
+
+    /// Longest TX request; the MAC appends the FCS itself (IFCS).
+    pub(crate) fn max_tx_len(&self) -> usize {
+        self.frame_size() - FCS_LEN
+    }
+
+    pub fn validate(&self) -> Result<(), DError> {
+        self.validate_with(MAX_THRESH)
+    }
+
+    /// 同 [`RingConfig::validate`]，写回阈值不超过 [`max_writeback`]
+    pub(crate) fn validate_with(&self, max_writeback: u8) -> Result<(), DError> {
+        validate_ring_size(self.size)?;
+        if self.buffer_size == 0
+            || self.buffer_size > MAX_BUFFER_SIZE
+            || !self.buffer_size.is_multiple_of(1024)
+        {
+            return Err(DError::InvalidParameter);
+        }
+        if self.mtu + FRAME_OVERHEAD > self.buffer_size {
+            return Err(DError::InvalidParameter);
+        }
+        self.rx_thresholds.validate(self.size, max_writeback)?;
+        self.tx_thresholds.validate(self.size, max_writeback)?;
+        validate_rs_interval(self.tx_rs_interval, self.size)?;
+        // 至少要能覆盖一个 4 KB 页
+        if self.dma_mask < 0xFFF {
+            return Err(DError::InvalidParameter);
+        }
+        if self.header_mode != HeaderMode::None
+            && (!(HEADER_SIZE_UNIT..=MAX_HEADER_SIZE).contains(&self.header_size)
+                || !self.header_size.is_multiple_of(HEADER_SIZE_UNIT)
+                || self.header_size >= self.buffer_size)
+        {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(())
+    }
+}
+
+/// 每个描述符的簿记，按顺序访问，不需要对齐到缓存行
 #[derive(Default)]
 struct RingElemMeta {
     request: Option<Request>,
 }
 
+/// 生产者和消费者索引各占一个缓存行，中断和任务并发推进时互不干扰
+#[derive(Default)]
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+impl<T> CachePadded<T> {
+    const fn new(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> core::ops::Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
 struct Ring<D: Descriptor> {
-    pub descriptors: DVec<D>,
-    ring_base: NonNull<u8>,
-    _waker: AtomicWaker,
+    pub descriptors: DmaMem<D>,
+    idx: usize,
+    mmio_base: NonNull<u8>,
+    regs: NonNull<QueueRegs>,
+    vector: u8,
+    /// 整个设备被软件屏蔽的向量，与 Igb 共享
+    masked_vectors: Arc<AtomicU32>,
+    /// Igb 处于传统中断模式，队列中断由 IMS 而不是 EIMS 屏蔽
+    legacy_irq: Arc<AtomicBool>,
+    waker: Arc<AtomicWaker>,
     meta_ls: Vec<RingElemMeta>,
+    /// 描述符个数减一，环长度是 2 的幂，回绕只需一次与运算
+    wrap_mask: usize,
+    /// 创建时的中断模式允许的最大 WTHRESH，调整大小时沿用
+    max_writeback: u8,
     pkt_size: usize,
+    thresholds: DescThresholds,
+    family: DeviceFamily,
+    dma_mask: u64,
+    /// 软件维护的尾指针，批量模式下可能领先于门铃寄存器；生产者索引，
+    /// 与消费者索引分开缓存行
+    tail: CachePadded<usize>,
+    batch_doorbell: bool,
+    /// 影子尾指针尚未写入门铃寄存器
+    doorbell_pending: bool,
+    /// 队列被暂停，恢复前不写门铃寄存器
+    paused: bool,
+    #[cfg(feature = "telemetry")]
+    telemetry: crate::telemetry::Telemetry,
 }
 
 impl<D: Descriptor> Ring<D> {
     pub fn new(
         idx: usize,
         mmio_base: NonNull<u8>,
-        size: usize,
-        pkt_size: usize,
+        region: Option<&'static mut [u8]>,
+        config: &RingConfig,
+        thresholds: DescThresholds,
+        family: DeviceFamily,
+        max_writeback: u8,
     ) -> Result<Self, DError> {
-        let descriptors = DVec::zeros(u64::MAX, size, 0x1000, Direction::Bidirectional).unwrap();
+        let size = config.size;
+        let dma_mask = config.dma_mask;
+        let descriptors = match region {
+            Some(region) => DmaMem::from_region(region, size, 128, Direction::Bidirectional)?,
+            None => DmaMem::zeros(dma_mask, size, Direction::Bidirectional)?,
+        };
+        validate_desc_ring(&descriptors, dma_mask)?;
 
-        let ring_base = unsafe { mmio_base.add(idx * 0x40) };
+        let regs = unsafe {
+            mmio_base
+                .add(D::QUEUE_BASE + idx * QUEUE_REGS_STRIDE)
+                .cast()
+        };
 
         let mut meta_ls = Vec::with_capacity(size);
         for _ in 0..size {
@@ -128,13 +681,66 @@ impl<D: Descriptor> Ring<D> {
 
         Ok(Self {
             descriptors,
-            ring_base,
-            _waker: AtomicWaker::new(),
+            idx,
+            mmio_base,
+            regs,
+            vector: idx as u8,
+            masked_vectors: Arc::new(AtomicU32::new(0)),
+            legacy_irq: Arc::new(AtomicBool::new(false)),
+            waker: Arc::new(AtomicWaker::new()),
             meta_ls,
-            pkt_size,
+            wrap_mask: size - 1,
+            max_writeback,
+            pkt_size: config.buffer_size,
+            thresholds,
+            family,
+            dma_mask,
+            tail: CachePadded::new(0),
+            batch_doorbell: false,
+            doorbell_pending: false,
+            paused: false,
+            #[cfg(feature = "telemetry")]
+            telemetry: Default::default(),
         })
     }
 
+    /// 从 `start` 开始按环的顺序取回所有挂在描述符上的请求
+    fn take_requests(&mut self, start: usize) -> Vec<Request> {
+        let count = self.count();
+        (0..count)
+            .filter_map(|i| {
+                let index = self.wrap(start + i);
+                self.meta_ls[index].request.take()
+            })
+            .collect()
+    }
+
+    /// 按新长度分配描述符，失败时环和队列都保持原样
+    fn alloc_resized(&self, size: usize) -> Result<DmaMem<D>, DError> {
+        validate_ring_size(size)?;
+        self.thresholds.validate(size, self.max_writeback)?;
+        // 静态区域大小固定，无法重新分配
+        if self.descriptors.is_static() {
+            return Err(DError::InvalidParameter);
+        }
+        let descriptors = DmaMem::zeros(self.dma_mask, size, Direction::Bidirectional)?;
+        validate_desc_ring(&descriptors, self.dma_mask)?;
+        Ok(descriptors)
+    }
+
+    /// 取回所有挂在描述符上的请求，换上 [`Ring::alloc_resized`] 分配的描述符
+    ///
+    /// 调用前队列必须已经停止。
+    fn replace_descriptors(&mut self, descriptors: DmaMem<D>) -> Vec<Request> {
+        let size = descriptors.len();
+        let requests = self.take_requests(0);
+        self.descriptors = descriptors;
+        self.wrap_mask = size - 1;
+        self.meta_ls.clear();
+        self.meta_ls.resize_with(size, RingElemMeta::default);
+        requests
+    }
+
     pub fn bus_addr(&self) -> u64 {
         // 获取 DMA 物理地址
         // 暂时返回虚拟地址，这里需要根据实际的 DMA API 实现
@@ -149,16 +755,85 @@ impl<D: Descriptor> Ring<D> {
         self.descriptors.len()
     }
 
-    fn reg_addr(&self, reg: usize) -> NonNull<u32> {
-        unsafe { self.ring_base.add(reg).cast() }
+    /// 环回绕
+    #[inline]
+    fn wrap(&self, index: usize) -> usize {
+        index & self.wrap_mask
+    }
+
+    /// 推进影子尾指针；非批量模式下立即写门铃寄存器
+    fn advance_tail(&mut self, tail: usize) {
+        *self.tail = self.wrap(tail);
+        self.doorbell_pending = true;
+        if !self.batch_doorbell {
+            self.ring_doorbell();
+        }
+    }
+
+    /// 把影子尾指针写入门铃寄存器，已同步时不产生 MMIO 写
+    fn ring_doorbell(&mut self) {
+        if !self.doorbell_pending || self.paused {
+            return;
+        }
+        self.doorbell_pending = false;
+        telemetry!(self.telemetry.doorbells += 1);
+        self.regs().tail.set(*self.tail as u32);
+    }
+
+    /// 队列初始化时尾指针寄存器被清零，影子尾指针随之复位
+    fn reset_tail(&mut self) {
+        *self.tail = 0;
+        self.doorbell_pending = false;
+        self.paused = false;
     }
 
-    fn reg_write(&mut self, reg: usize, value: u32) {
+    fn regs(&self) -> &QueueRegs {
+        unsafe { self.regs.as_ref() }
+    }
+
+    // 访问设备级（非队列）寄存器
+    fn global_write(&mut self, reg: usize, value: u32) {
         unsafe {
-            self.reg_addr(reg).write_volatile(value);
+            self.mmio_base.add(reg).cast::<u32>().write_volatile(value);
         }
     }
-    fn reg_read(&self, reg: usize) -> u32 {
-        unsafe { self.reg_addr(reg).read_volatile() }
+    fn global_read(&self, reg: usize) -> u32 {
+        unsafe { self.mmio_base.add(reg).cast::<u32>().read_volatile() }
+    }
+
+    /// 重新使能该环所在的中断向量（配合 EIAM 自动屏蔽使用），被软件屏蔽时不动
+    fn rearm_irq(&mut self) {
+        let masked = self.masked_vectors.load(Ordering::Relaxed);
+        if self.legacy_irq.load(Ordering::Relaxed) {
+            // 传统模式下各队列共用中断原因，任一环屏蔽时都不打开
+            if masked == 0 {
+                self.global_write(IMS, IMS_QUEUE_CAUSES);
+            }
+            return;
+        }
+        let bit = 1 << self.vector;
+        if masked & bit == 0 {
+            self.global_write(EIMS, bit);
+        }
+    }
+
+    fn set_irq_enabled(&mut self, enable: bool) {
+        let bit = 1 << self.vector;
+        let legacy = self.legacy_irq.load(Ordering::Relaxed);
+        if enable {
+            let masked = self.masked_vectors.fetch_and(!bit, Ordering::Relaxed) & !bit;
+            if !legacy {
+                self.global_write(EIMS, bit);
+            } else if masked == 0 {
+                self.global_write(IMS, IMS_QUEUE_CAUSES);
+            }
+        } else {
+            self.masked_vectors.fetch_or(bit, Ordering::Relaxed);
+            if legacy {
+                self.global_write(IMC, IMS_QUEUE_CAUSES);
+            } else {
+                self.global_write(EIMC, bit);
+            }
+        }
     }
 }
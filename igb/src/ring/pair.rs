@@ -0,0 +1,159 @@
+use core::task::{Poll, Waker};
+
+use futures::future::poll_fn;
+
+use super::*;
+
+/// The RX and TX ring of one queue, sharing its interrupt vector, polled
+/// together like a NAPI context.
+///
+/// Convenient for executor-per-core designs where one task owns a queue.
+pub struct QueuePair<L: RxLayout = OneBuffer> {
+    pub tx: TxRing,
+    pub rx: RxRing<L>,
+}
+
+impl<L: RxLayout> QueuePair<L> {
+    /// Pair the rings returned by [`crate::Igb::new_ring`] or its variants.
+    ///
+    /// Fails with [`DError::InvalidParameter`] if they belong to different
+    /// queues.
+    pub fn new(tx: TxRing, rx: RxRing<L>) -> Result<Self, DError> {
+        if tx.queue_index() != rx.queue_index() {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(Self { tx, rx })
+    }
+
+    pub fn into_parts(self) -> (TxRing, RxRing<L>) {
+        (self.tx, self.rx)
+    }
+
+    pub fn queue_index(&self) -> usize {
+        self.rx.queue_index()
+    }
+
+    /// Interrupt vector shared by both rings.
+    pub fn vector(&self) -> u8 {
+        self.rx.vector()
+    }
+
+    /// Wake `waker` on RX or TX activity of the queue.
+    pub fn register_waker(&self, waker: &Waker) {
+        self.rx.waker().register(waker);
+        self.tx.waker().register(waker);
+    }
+
+    /// Harvest up to `budget` received packets into `on_rx`, then hand all
+    /// sent requests to `on_tx`.
+    ///
+    /// RX goes through [`RxRing::poll_burst`]: packets dropped by `on_rx`
+    /// are re-submitted, and the shared vector is re-armed only when RX
+    /// stayed under the budget; otherwise poll again. Returns the number of
+    /// packets received.
+    pub fn poll(
+        &mut self,
+        budget: usize,
+        on_rx: impl FnMut(RxPacket<'_, L>),
+        mut on_tx: impl FnMut(Request),
+    ) -> usize {
+        let done = self.rx.poll_burst(budget, on_rx);
+        while let Some(request) = self.tx.next_finished() {
+            on_tx(request);
+        }
+        done
+    }
+}
+
+/// Interrupt to kick, poll to drain: the interrupt only wakes the task, which
+/// masks the queue's vector and polls with a budget until the rings are
+/// empty, then unmasks it again.
+///
+/// Run [`NapiLike::wait`] and then [`NapiLike::poll`] until it returns
+/// `true`, yielding between passes, in a loop.
+///
+/// In [`IrqMode::Legacy`](crate::IrqMode::Legacy) masking the vector masks
+/// the queue causes all queues share, and only queue 0 is signaled.
+pub struct NapiLike<L: RxLayout = OneBuffer> {
+    pair: QueuePair<L>,
+    budget: usize,
+    /// 向量已屏蔽，正在轮询
+    polling: bool,
+}
+
+impl<L: RxLayout> NapiLike<L> {
+    /// `budget` packets are received per [`NapiLike::poll`] at most, e.g. 64.
+    ///
+    /// Fails with [`DError::InvalidParameter`] for a queue other than 0 in
+    /// legacy mode, whose interrupt would never wake it.
+    pub fn new(pair: QueuePair<L>, budget: usize) -> Result<Self, DError> {
+        if budget == 0 || (pair.rx.legacy_irq() && pair.queue_index() != 0) {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(Self {
+            pair,
+            budget,
+            polling: false,
+        })
+    }
+
+    pub fn into_inner(mut self) -> QueuePair<L> {
+        if self.polling {
+            self.pair.rx.enable_irq();
+        }
+        self.pair
+    }
+
+    pub fn pair(&mut self) -> &mut QueuePair<L> {
+        &mut self.pair
+    }
+
+    /// Switch to polling: mask the queue's vector. Called by
+    /// [`NapiLike::wait`]; call it directly when woken some other way.
+    pub fn kick(&mut self) {
+        if !self.polling {
+            self.pair.rx.disable_irq();
+            self.polling = true;
+        }
+    }
+
+    /// Wait for the queue's interrupt, or for work already pending, then
+    /// [`NapiLike::kick`].
+    pub async fn wait(&mut self) {
+        if !self.polling {
+            poll_fn(|cx| {
+                self.pair.register_waker(cx.waker());
+                if self.has_work() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+        }
+        self.kick();
+    }
+
+    fn has_work(&self) -> bool {
+        self.pair.rx.pending_count() > 0 || self.pair.tx.completed_count() > 0
+    }
+
+    /// One budgeted pass over the rings, see [`QueuePair::poll`].
+    ///
+    /// Returns `true` once the rings are drained and the vector is unmasked
+    /// again; `false` means poll again, after yielding to other tasks.
+    pub fn poll(&mut self, on_rx: impl FnMut(RxPacket<'_, L>), on_tx: impl FnMut(Request)) -> bool {
+        self.kick();
+        if self.pair.poll(self.budget, on_rx, on_tx) == self.budget {
+            return false;
+        }
+        self.pair.rx.enable_irq();
+        self.polling = false;
+        // 解除屏蔽前到达的包不一定会再触发中断，重新检查一次
+        if self.has_work() {
+            self.kick();
+            return false;
+        }
+        true
+    }
+}
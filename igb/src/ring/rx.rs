@@ -1,20 +1,100 @@
-use core::ops::{Deref, DerefMut};
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    task::{Context, Poll},
+};
+
+use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 
 use super::*;
 use crate::{
     DError,
-    descriptor::{AdvRxDesc, AdvRxDescRead},
+    descriptor::{AdvRxDesc, AdvRxDescRead, AdvRxDescWB},
+    latency::RxLatencyProfile,
+    osal::critical_section,
+    ptp,
 };
-use alloc::sync::Arc;
-use log::{error, trace};
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use futures::future::poll_fn;
+use log::error;
+use mbarrier::{rmb, wmb};
 
 struct RingInner {
     base: Ring<AdvRxDesc>,
+    /// 下一个待回收的描述符索引
+    next_clean: CachePadded<usize>,
+    /// 跨越多个描述符的帧计数
+    oversize_frames: u64,
+    /// RQDPC 读清零，这里累加
+    hw_drops: u64,
+    refill: RefillPolicy,
+    /// 用于自动补充的备用缓冲区
+    pool: Vec<Request>,
+    header_mode: HeaderMode,
+    header_size: usize,
+    /// 每个描述符一个头部缓冲区，仅在头部复制模式下分配
+    headers: Option<DmaMem<u8>>,
+    no_snoop: bool,
+    relaxed_ordering: bool,
+    /// 可用描述符低于该值时唤醒 low_waker
+    low_watermark: Option<usize>,
+    low_waker: Arc<AtomicWaker>,
+    /// shutdown 已执行过，Drop 不再重复停止队列
+    shut_down: bool,
+    /// 中断路径可以按阈值补充；暂停、调整大小和关闭期间关闭
+    irq_refill: bool,
 }
 
 impl RingInner {
-    fn new(ring: Ring<AdvRxDesc>) -> Result<Self, DError> {
-        Ok(Self { base: ring })
+    fn new(ring: Ring<AdvRxDesc>, config: &RingConfig) -> Result<Self, DError> {
+        let headers = Self::alloc_headers(
+            config.header_mode,
+            config.header_size,
+            ring.count(),
+            ring.dma_mask,
+        )?;
+        Ok(Self {
+            base: ring,
+            next_clean: CachePadded::new(0),
+            oversize_frames: 0,
+            hw_drops: 0,
+            refill: RefillPolicy::default(),
+            pool: Vec::new(),
+            header_mode: config.header_mode,
+            header_size: config.header_size,
+            headers,
+            no_snoop: config.rx_no_snoop,
+            relaxed_ordering: config.rx_relaxed_ordering,
+            low_watermark: None,
+            low_waker: Arc::new(AtomicWaker::new()),
+            shut_down: false,
+            irq_refill: true,
+        })
+    }
+
+    fn alloc_headers(
+        mode: HeaderMode,
+        header_size: usize,
+        count: usize,
+        dma_mask: u64,
+    ) -> Result<Option<DmaMem<u8>>, DError> {
+        if mode == HeaderMode::None {
+            return Ok(None);
+        }
+        let len = count * header_size;
+        let headers = DmaMem::zeros(dma_mask, len, Direction::FromDevice)?;
+        check_dma_range(headers.bus_addr(), len, dma_mask)?;
+        Ok(Some(headers))
+    }
+
+    /// 描述符 `index` 的头部缓冲区总线地址，未启用时为 0
+    fn header_addr(&self, index: usize) -> u64 {
+        self.headers
+            .as_ref()
+            .map_or(0, |h| h.bus_addr() + (index * self.header_size) as u64)
     }
 
     fn init(&mut self) -> Result<(), DError> {
@@ -22,37 +102,55 @@ impl RingInner {
         let size_bytes = self.size_bytes();
 
         // Program the descriptor base address with the address of the region.
-        self.reg_write(RDBAL, (bus_addr & 0xFFFFFFFF) as u32);
-        self.reg_write(RDBAH, (bus_addr >> 32) as u32);
+        self.regs().bal.set((bus_addr & 0xFFFFFFFF) as u32);
+        self.regs().bah.set((bus_addr >> 32) as u32);
 
         // Set the length register to the size of the descriptor ring.
-        self.reg_write(RDLEN, size_bytes as u32);
+        self.regs().len.set(size_bytes as u32);
 
         let pkt_size_kb = self.pkt_size / 1024;
 
         // Program SRRCTL of the queue according to the size of the buffers and the required header handling.
-        self.reg_write(
-            SRRCTL,
-            (SRRCTL::DESCTYPE::AdvancedOneBuffer + SRRCTL::BSIZEPACKET.val(pkt_size_kb as _)).value,
-        );
+        let mut srrctl = self.header_mode.desc_type()
+            + SRRCTL::BSIZEPACKET.val(pkt_size_kb as _)
+            + SRRCTL::RDMTS.val(self.rdmts());
+        if self.header_mode != HeaderMode::None {
+            srrctl += SRRCTL::BSIZEHEADER.val((self.header_size / HEADER_SIZE_UNIT) as _);
+        }
+        self.regs().srrctl.write(srrctl);
 
         // If header split or header replication is required for this queue,
         // program the PSRTYPE register according to the required headers.
-        // 暂时不需要头部分割
+        if self.header_mode != HeaderMode::None {
+            let psrtype =
+                PSRTYPE_L2HDR | PSRTYPE_IPV4HDR | PSRTYPE_IPV6HDR | PSRTYPE_TCPHDR | PSRTYPE_UDPHDR;
+            let reg = PSRTYPE + self.idx * 4;
+            self.global_write(reg, psrtype);
+        }
 
-        self.reg_write(RDH, 0);
-        self.reg_write(RDT, 0);
+        let mut dca_rxctrl = self.regs().dca_ctrl.get()
+            & !(DCA_RXCTRL_DESC_READ_RO | DCA_RXCTRL_DATA_WRITE_RO | DCA_RXCTRL_HEADER_WRITE_RO);
+        if self.relaxed_ordering {
+            dca_rxctrl |=
+                DCA_RXCTRL_DESC_READ_RO | DCA_RXCTRL_DATA_WRITE_RO | DCA_RXCTRL_HEADER_WRITE_RO;
+        }
+        self.regs().dca_ctrl.set(dca_rxctrl);
+
+        self.regs().head.set(0);
+        self.regs().tail.set(0);
+        self.reset_tail();
 
         // Enable the queue by setting RXDCTL.ENABLE. In the case of queue zero,
         // the enable bit is set by default - so the ring parameters should be set before RCTL.RXEN is set.
-        // 使用推荐的阈值：PTHRESH=8, HTHRESH=8, WTHRESH=1
+        // 阈值来自 RingConfig，默认 PTHRESH=8, HTHRESH=8, WTHRESH=1
         self.enable_queue();
 
         // Poll the RXDCTL register until the ENABLE bit is set.
         // The tail should not be bumped before this bit was read as one.
 
         wait_for(
-            || self.reg_read(RXDCTL) & RXDCTL::ENABLE::Enabled.value > 0,
+            "RX queue enable",
+            || self.regs().dctl.get() & RXDCTL::ENABLE::Enabled.value > 0,
             Duration::from_millis(1),
             Some(1000),
         )?;
@@ -68,26 +166,207 @@ impl RingInner {
 
     pub fn enable_queue(&mut self) {
         // 启用队列
-        self.reg_write(
-            RXDCTL,
-            (RXDCTL::PTHRESH.val(8)
-                + RXDCTL::HTHRESH.val(8)
-                + RXDCTL::WTHRESH.val(1)
-                + RXDCTL::ENABLE::Enabled)
-                .value,
-        );
+        let rxdctl = self.thresholds.rxdctl() + RXDCTL::ENABLE::Enabled;
+        self.regs().dctl.set(rxdctl.value);
     }
 
     pub fn disable_queue(&mut self) {
         // 禁用队列
-        self.reg_write(
-            RXDCTL,
-            (RXDCTL::PTHRESH.val(8)
-                + RXDCTL::HTHRESH.val(8)
-                + RXDCTL::WTHRESH.val(1)
-                + RXDCTL::ENABLE::Disabled)
-                .value,
-        );
+        let rxdctl = self.thresholds.rxdctl() + RXDCTL::ENABLE::Disabled;
+        self.regs().dctl.set(rxdctl.value);
+    }
+
+    /// 禁用队列并等待硬件确认
+    fn stop_queue(&mut self) -> Result<(), DError> {
+        self.disable_queue();
+        wait_for(
+            "RX queue disable",
+            || self.regs().dctl.get() & RXDCTL::ENABLE::Enabled.value == 0,
+            Duration::from_millis(1),
+            Some(1000),
+        )
+    }
+
+    /// 停止队列，已投递的缓冲区留在环中
+    fn pause(&mut self) -> Result<(), DError> {
+        if self.paused {
+            return Ok(());
+        }
+        self.paused = true;
+        self.set_irq_refill(false);
+        self.flush_and_stop()
+    }
+
+    /// 与中断路径的补充互斥地打开或关闭它
+    fn set_irq_refill(&mut self, enable: bool) {
+        critical_section(|| self.irq_refill = enable);
+    }
+
+    /// SWFLUSH 写回已收到但未达到 WTHRESH 的描述符，之后仍可取出；再禁用队列
+    fn flush_and_stop(&mut self) -> Result<(), DError> {
+        let rxdctl = self.thresholds.rxdctl() + RXDCTL::ENABLE::Enabled + RXDCTL::SWFLUSH::SET;
+        self.regs().dctl.set(rxdctl.value);
+        self.stop_queue()
+    }
+
+    /// 停止队列并按接收顺序取回所有缓冲区，包括备用池
+    fn shutdown(&mut self) -> Result<Vec<Request>, DError> {
+        self.set_irq_refill(false);
+        self.stop_queue()?;
+        let oldest = *self.next_clean;
+        let mut requests = self.take_requests(oldest);
+        requests.append(&mut self.pool);
+        Ok(requests)
+    }
+
+    fn resume(&mut self) -> Result<(), DError> {
+        if !self.paused {
+            return Ok(());
+        }
+        // 不依赖停用期间的 RDH，从第一个未写回的描述符继续接收
+        let mut head = *self.next_clean;
+        while head != *self.tail
+            && self.meta_ls[head].request.is_some()
+            && unsafe { self.descriptors[head].write.is_done() }
+        {
+            head = self.wrap(head + 1);
+        }
+        self.regs().head.set(head as u32);
+        self.enable_queue();
+        wait_for(
+            "RX queue enable",
+            || self.regs().dctl.get() & RXDCTL::ENABLE::Enabled.value > 0,
+            Duration::from_millis(1),
+            Some(1000),
+        )?;
+        // 队列使能后再写尾指针，包括暂停期间提交的缓冲区
+        self.paused = false;
+        self.doorbell_pending = true;
+        self.ring_doorbell();
+        self.set_irq_refill(true);
+        Ok(())
+    }
+
+    /// 硬件可用的描述符数：从 next_clean 到尾部
+    fn posted(&self) -> usize {
+        let count = self.count();
+        self.wrap(*self.tail + count - *self.next_clean)
+    }
+
+    /// RDMTS 以 16 个描述符为单位，向下取整
+    fn rdmts(&self) -> u32 {
+        self.low_watermark
+            .map_or(0, |n| (n / RDMTS_UNIT).min(RDMTS_MAX) as u32)
+    }
+
+    /// 硬件还能写入的描述符数：已提交但未完成的部分
+    fn available(&self) -> usize {
+        self.posted() - self.pending_count()
+    }
+
+    /// 从 next_clean 开始连续设置了 DD 的描述符数
+    fn pending_count(&self) -> usize {
+        let posted = self.posted();
+        let mut pending = 0;
+        while pending < posted {
+            let desc = unsafe { self.descriptors[self.wrap(*self.next_clean + pending)].write };
+            if !desc.is_done() {
+                break;
+            }
+            pending += 1;
+        }
+        pending
+    }
+
+    /// 还能补充的描述符数，环中需空出一个以区分满和空
+    fn free_slots(&self) -> usize {
+        if self.meta_ls[*self.tail].request.is_some() {
+            return 0;
+        }
+        self.count() - 1 - self.posted()
+    }
+
+    fn submit(&mut self, request: Request) -> Result<(), DError> {
+        // 与中断上下文中的回收互斥
+        critical_section(|| self.post(request))
+    }
+
+    fn flush_doorbell(&mut self) {
+        critical_section(|| self.ring_doorbell())
+    }
+
+    fn post(&mut self, mut request: Request) -> Result<(), DError> {
+        request.len = request.buff.len();
+        // 硬件按 SRRCTL 中的长度写入，缓冲区不能更小
+        if request.buff.len() < self.pkt_size {
+            return Err(DError::InvalidParameter);
+        }
+        check_dma_range(request.bus_addr(), request.buff.len(), self.dma_mask)?;
+        let index = *self.tail;
+        if self.wrap(index + 1) == *self.next_clean || self.meta_ls[index].request.is_some() {
+            error!("RxRing: submit no available buffer at index: {index}");
+            return Err(DError::NoMemory); // 没有可用的缓冲区
+        }
+
+        // 交给硬件前丢弃缓冲区的缓存行，避免脏行覆盖 DMA 写入的数据
+        request.buff.sync_for_device();
+
+        // 更新描述符
+        let desc = AdvRxDesc {
+            read: AdvRxDescRead::new(request.bus_addr(), self.header_addr(index), self.no_snoop),
+        };
+        self.descriptors.set(index, desc);
+        self.meta_ls[index].request = Some(request);
+
+        // 描述符写入完成后再更新尾部指针
+        wmb();
+        self.advance_tail(index + 1);
+
+        Ok(())
+    }
+
+    /// 从备用池补充至多 `max` 个描述符
+    fn refill_from_pool(&mut self, max: usize) -> usize {
+        let mut done = 0;
+        while done < max && self.free_slots() > 0 {
+            let Some(request) = self.pool.pop() else {
+                break;
+            };
+            if let Err(e) = self.submit(request) {
+                error!("RxRing: refill failed: {e:?}");
+                break;
+            }
+            done += 1;
+        }
+        telemetry!(self.telemetry.refills += done as u64);
+        done
+    }
+
+    /// 按阈值策略批量补充，由 RX 中断路径调用，调用方已进入临界区
+    fn refill_on_threshold(&mut self) {
+        if let RefillPolicy::Threshold(threshold) = self.refill
+            && self.irq_refill
+            && self.free_slots() >= threshold.max(1)
+        {
+            self.refill_from_pool(usize::MAX);
+        }
+    }
+
+    fn resize(&mut self, size: usize) -> Result<Vec<Request>, DError> {
+        // 先分配，失败时队列继续运行
+        let descriptors = self.alloc_resized(size)?;
+        let headers = Self::alloc_headers(self.header_mode, self.header_size, size, self.dma_mask)?;
+        self.set_irq_refill(false);
+        if let Err(e) = self.flush_and_stop() {
+            self.set_irq_refill(!self.paused);
+            return Err(e);
+        }
+        let requests = self.replace_descriptors(descriptors);
+        self.headers = headers;
+        *self.next_clean = 0;
+        let result = self.init();
+        self.set_irq_refill(!self.paused);
+        result.map(|_| requests)
     }
 
     // pub fn flush_descriptors(&mut self) {
@@ -105,22 +384,135 @@ impl RingInner {
 
     /// 获取当前头部指针值
     pub fn get_head(&self) -> u32 {
-        self.reg_read(RDH)
+        self.regs().head.get()
+    }
+
+    /// 查看下一个完整的帧（直到 EOP），但不取出
+    ///
+    /// 返回帧占用的描述符个数和元数据，元数据中的长度为整个帧的长度。
+    fn peek_completed(&self) -> Option<(usize, RxMeta)> {
+        let mut index = *self.next_clean;
+        if self.get_head() as usize == index {
+            return None; // 没有可用的缓冲区
+        }
+
+        let mut segments = 0;
+        let mut total_len = 0;
+        while segments < self.count() {
+            self.meta_ls[index].request.as_ref()?;
+            unsafe {
+                let desc = &self.descriptors[index];
+                // 检查描述符是否已完成
+                if !desc.write.is_done() {
+                    datapath_trace!("RxRing: next_pkt descriptor not done at index: {index}");
+                    return None; // 描述符未完成，无法获取数据
+                }
+                segments += 1;
+                total_len += desc.write.packet_length() as usize;
+                if desc.write.is_end_of_packet() {
+                    let mut meta = RxMeta::from_desc(&desc.write);
+                    meta.len = total_len;
+                    return Some((segments, meta));
+                }
+            }
+            index = self.wrap(index + 1);
+        }
+        None
+    }
+
+    /// 取出下一个完整的帧
+    ///
+    /// 临界区内只确定帧占用的描述符并取走缓冲区，同步、拼接和日志都放在外面。
+    fn next_completed(&mut self) -> Option<RxFrame> {
+        let (start, segments, meta) = critical_section(|| self.claim())?;
+        datapath_trace!("RxRing: next_pkt index: {start}");
+
+        // 描述符在 next_clean 前进之前不会被重新投递，可以在临界区外读取
+        let mut assembled = (segments > 1).then(|| Vec::with_capacity(meta.len));
+        for i in 0..segments {
+            let index = self.wrap(start + i);
+            let request = self.meta_ls[index].request.as_ref()?;
+            request.buff.sync_for_cpu();
+            if let Some(buf) = assembled.as_mut() {
+                let seg_len = unsafe { self.descriptors[index].write.packet_length() } as usize;
+                buf.extend_from_slice(&request[..seg_len]);
+            }
+        }
+        if assembled.is_some() {
+            self.oversize_frames += 1;
+        }
+        // next_clean 前进后头部槽位可能被重新投递，要在 take 之前拷出
+        let header = self.copy_header(start, meta.header_len);
+        let mut chain = Vec::with_capacity(segments - 1);
+        let request = critical_section(|| self.take(segments, &mut chain))?;
+
+        Some(RxFrame {
+            request,
+            chain,
+            assembled,
+            header,
+            meta,
+        })
+    }
+
+    fn copy_header(&self, index: usize, len: usize) -> Option<Vec<u8>> {
+        let headers = self.headers.as_ref()?;
+        if len == 0 {
+            return None;
+        }
+        headers.sync_for_cpu();
+        let start = index * self.header_size;
+        Some(headers.as_ref()[start..start + len].to_vec())
     }
 
-    /// 获取当前尾部指针值
-    pub fn get_tail(&self) -> u32 {
-        self.reg_read(RDT)
+    /// 确定下一个完整帧的起始下标、描述符个数和元数据，不移动 next_clean
+    fn claim(&mut self) -> Option<(usize, usize, RxMeta)> {
+        let (segments, mut meta) = self.peek_completed()?;
+        telemetry!(self.telemetry.record_harvest(segments as u64));
+        // 看到 DD 之后再读描述符其余字段和缓冲区
+        rmb();
+
+        let start = *self.next_clean;
+        if self.headers.is_some() {
+            // 头部只写在帧的第一个描述符对应的头部缓冲区
+            let desc = unsafe { self.descriptors[start].write };
+            meta.header_len = (desc.header_length() as usize).min(self.header_size);
+        }
+        let last = unsafe { self.descriptors[self.wrap(start + segments - 1)].write };
+        if last.is_timestamped() {
+            // 读 RXSTMPH 后硬件才能锁存下一个时间戳
+            let low = self.global_read(ptp::RXSTMPL);
+            meta.timestamp = Some(ptp::to_ns(self.family, low, self.global_read(ptp::RXSTMPH)));
+        }
+        Some((start, segments, meta))
     }
 
-    /// 更新尾部指针
-    pub fn update_tail(&mut self, mut tail: usize) {
-        if tail == self.descriptors.len() {
-            tail = 0;
+    /// 取走 claim 确定的帧的缓冲区并移动 next_clean，后续段放入 `chain`
+    fn take(&mut self, segments: usize, chain: &mut Vec<Request>) -> Option<Request> {
+        let mut first = None;
+        for _ in 0..segments {
+            let index = *self.next_clean;
+            let request = self.meta_ls[index].request.take()?;
+            *self.next_clean = self.wrap(index + 1);
+            if first.is_none() {
+                first = Some(request);
+            } else {
+                chain.push(request);
+            }
         }
-        self.reg_write(RDT, tail as u32);
+        first
     }
 }
+
+/// 从环中取出的一个完整帧
+struct RxFrame {
+    request: Request,
+    chain: Vec<Request>,
+    assembled: Option<Vec<u8>>,
+    header: Option<Vec<u8>>,
+    meta: RxMeta,
+}
+
 impl Deref for RingInner {
     type Target = super::Ring<AdvRxDesc>;
 
@@ -135,18 +527,36 @@ impl DerefMut for RingInner {
     }
 }
 
-pub struct RxRing(Arc<UnsafeCell<RingInner>>);
+pub struct RxRing<L: RxLayout = OneBuffer>(Arc<UnsafeCell<RingInner>>, PhantomData<L>);
 
-unsafe impl Send for RxRing {}
+unsafe impl<L: RxLayout> Send for RxRing<L> {}
 
-impl RxRing {
+impl<L: RxLayout> RxRing<L> {
     #[allow(clippy::arc_with_non_send_sync)]
-    pub(crate) fn new(idx: usize, mmio_base: NonNull<u8>, size: usize) -> Result<Self, DError> {
-        let base = Ring::new(idx, mmio_base, size, PACKET_SIZE as usize)?;
-        let mut ring_inner = RingInner::new(base)?;
+    pub(crate) fn new(
+        idx: usize,
+        mmio_base: NonNull<u8>,
+        config: &RingConfig,
+        family: DeviceFamily,
+        region: Option<&'static mut [u8]>,
+        max_writeback: u8,
+    ) -> Result<Self, DError> {
+        if L::HEADERS != (config.header_mode != HeaderMode::None) {
+            return Err(DError::InvalidParameter);
+        }
+        let base = Ring::new(
+            idx,
+            mmio_base,
+            region,
+            config,
+            config.rx_thresholds,
+            family,
+            max_writeback,
+        )?;
+        let mut ring_inner = RingInner::new(base, config)?;
         ring_inner.init()?;
         let ring = Arc::new(UnsafeCell::new(ring_inner));
-        Ok(Self(ring))
+        Ok(Self(ring, PhantomData))
     }
 
     fn this(&self) -> &RingInner {
@@ -160,90 +570,556 @@ impl RxRing {
         self.this().pkt_size
     }
 
-    pub fn next_pkt(&mut self) -> Option<RxPacket<'_>> {
-        let index = self.next_index();
-        let head = self.this().get_head() as usize;
-        if head == index {
-            return None; // 没有可用的缓冲区
-        }
-        let len;
-        unsafe {
-            let desc = &self.this().descriptors[index];
-            // 检查描述符是否已完成
-            if !desc.write.is_done() {
-                trace!("RxRing: next_pkt descriptor not done at index: {index}");
-                return None; // 描述符未完成，无法获取数据
-            }
-            len = desc.write.packet_length() as usize;
-        }
+    /// DMA mask requests must satisfy, for [`Request::new_rx_with_mask`].
+    pub fn dma_mask(&self) -> u64 {
+        self.this().dma_mask
+    }
 
-        trace!("RxRing: next_pkt index: {index}");
-        let request = self.this_mut().meta_ls[index]
-            .request
-            .take()
-            .expect("Request should be set");
+    pub fn next_pkt(&mut self) -> Option<RxPacket<'_, L>> {
+        let frame = self.this_mut().next_completed()?;
 
         Some(RxPacket {
             ring: self,
-            request,
-            len,
+            request: Some(frame.request),
+            chain: frame.chain,
+            assembled: frame.assembled,
+            header: frame.header,
+            meta: frame.meta,
+        })
+    }
+
+    /// Waits for one packet, copies it into `buf` and re-submits the DMA buffer.
+    ///
+    /// Returns `DError::InvalidParameter` if `buf` is shorter than the packet;
+    /// the packet is dropped in that case.
+    pub async fn recv_into(&mut self, buf: &mut [u8]) -> Result<RxMeta, DError> {
+        let _guard = WakerGuard(self.this().waker.clone());
+        poll_fn(|cx| {
+            self.this().waker.register(cx.waker());
+            let result = self.copy_next(buf);
+            telemetry!(self.this_mut().telemetry.record_poll());
+            match result {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    self.this_mut().rearm_irq();
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// [`RxRing::recv_into`] giving up with [`DError::TimedOut`] after
+    /// `timeout`, measured with [`Kernel::now`](crate::osal::Kernel::now).
+    ///
+    /// Without a timer interrupt the future wakes itself while it waits, so
+    /// the executor keeps polling it. A packet is never lost to the timeout.
+    pub async fn recv_into_timeout(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<RxMeta, DError> {
+        with_timeout("RX packet", self.recv_into(buf), timeout).await
+    }
+
+    /// Non-blocking [`RxRing::recv_into`] for callers of the old
+    /// buffer-oriented API: `None` when no packet is ready.
+    pub fn try_recv_into(&mut self, buf: &mut [u8]) -> Option<Result<RxMeta, DError>> {
+        let result = self.copy_next(buf);
+        telemetry!(self.this_mut().telemetry.record_poll());
+        result
+    }
+
+    /// 取出一个包拷贝到 `buf`，DMA 缓冲区在 pkt 释放时重新提交
+    fn copy_next(&mut self, buf: &mut [u8]) -> Option<Result<RxMeta, DError>> {
+        let pkt = self.next_pkt()?;
+        let meta = pkt.meta;
+        if buf.len() < meta.len {
+            return Some(Err(DError::InvalidParameter));
+        }
+        buf[..meta.len].copy_from_slice(&pkt);
+        Some(Ok(meta))
+    }
+
+    /// Waits for packets and copies as many complete frames as fit into `buf`.
+    ///
+    /// Frames are packed back to back; each entry of the returned list is
+    /// `(offset, len, meta)` of one frame within `buf`. A frame that does not
+    /// fit is left in the ring for the next call, unless it is the first one,
+    /// in which case it is dropped and `DError::InvalidParameter` is returned.
+    pub async fn recv(&mut self, buf: &mut [u8]) -> Result<Vec<(usize, usize, RxMeta)>, DError> {
+        let _guard = WakerGuard(self.this().waker.clone());
+        poll_fn(|cx| {
+            self.this().waker.register(cx.waker());
+            let mut frames = Vec::new();
+            let mut offset = 0;
+            while let Some((_, meta)) = self.this().peek_completed() {
+                let end = offset + meta.len;
+                if end > buf.len() {
+                    if frames.is_empty() {
+                        drop(self.next_pkt());
+                        return Poll::Ready(Err(DError::InvalidParameter));
+                    }
+                    break;
+                }
+                let Some(pkt) = self.next_pkt() else {
+                    break;
+                };
+                buf[offset..end].copy_from_slice(&pkt);
+                frames.push((offset, meta.len, meta));
+                offset = end;
+            }
+            telemetry!(self.this_mut().telemetry.record_poll());
+            if frames.is_empty() {
+                self.this_mut().rearm_irq();
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(frames))
+            }
         })
+        .await
+    }
+
+    /// [`RxRing::recv`] giving up with [`DError::TimedOut`] after `timeout`,
+    /// like [`RxRing::recv_into_timeout`].
+    pub async fn recv_timeout(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<Vec<(usize, usize, RxMeta)>, DError> {
+        with_timeout("RX packet", self.recv(buf), timeout).await
+    }
+
+    /// Harvests up to `budget` completed packets in one pass.
+    ///
+    /// Each packet is handed to `f`; packets that `f` drops without calling
+    /// [`RxPacket::into_request`] are re-submitted to the ring automatically.
+    /// Doorbell batching is on for the burst, so the re-submitted buffers
+    /// reach the hardware with one RDT write at its end. When the ring is
+    /// drained before the budget runs out, the ring's interrupt vector is
+    /// re-armed after that write. Returns the number of packets harvested.
+    pub fn poll_burst(&mut self, budget: usize, mut f: impl FnMut(RxPacket<'_, L>)) -> usize {
+        let ring = self.this_mut();
+        let batch = critical_section(|| core::mem::replace(&mut ring.batch_doorbell, true));
+        let mut done = 0;
+        while done < budget {
+            let Some(pkt) = self.next_pkt() else {
+                break;
+            };
+            f(pkt);
+            done += 1;
+        }
+        telemetry!(self.this_mut().telemetry.record_poll());
+        let ring = self.this_mut();
+        critical_section(|| {
+            ring.batch_doorbell = batch;
+            ring.ring_doorbell();
+        });
+        if done < budget {
+            self.this_mut().rearm_irq();
+        }
+        done
     }
 
     pub fn submit(&mut self, request: Request) -> Result<(), DError> {
-        let index = self.this_mut().get_tail() as usize;
+        self.this_mut().submit(request)
+    }
+
+    /// Only update the software tail in [`RxRing::submit`] and refills,
+    /// leaving the RDT write to [`RxRing::flush_doorbell`].
+    ///
+    /// Lets an event loop post a whole burst with one MMIO write. Disabling
+    /// batching flushes anything still pending.
+    pub fn set_doorbell_batching(&mut self, enable: bool) {
         let ring = self.this_mut();
-        if index + 1 == ring.get_head() as usize {
-            error!("RxRing: submit no available buffer at index: {index}");
-            return Err(DError::NoMemory); // 没有可用的缓冲区
+        ring.batch_doorbell = enable;
+        if !enable {
+            ring.flush_doorbell();
         }
+    }
 
-        // 更新描述符
-        let desc = AdvRxDesc {
-            read: AdvRxDescRead::new(request.bus_addr(), 0, false),
-        };
-        ring.descriptors.set(index, desc);
-        ring.meta_ls[index].request = Some(request);
+    /// Write the software tail to RDT if descriptors were posted since the
+    /// last doorbell.
+    pub fn flush_doorbell(&mut self) {
+        self.this_mut().flush_doorbell();
+    }
 
-        // 更新尾部指针
-        ring.update_tail(index + 1);
+    /// Choose how descriptors consumed by [`RxPacket::into_request`] are
+    /// replaced from the buffer pool, see [`RefillPolicy`].
+    pub fn set_refill_policy(&mut self, policy: RefillPolicy) {
+        let ring = self.this_mut();
+        critical_section(|| ring.refill = policy);
+    }
 
+    /// Add a spare buffer to the pool used by automatic refill.
+    pub fn add_pool_buffer(&mut self, request: Request) -> Result<(), DError> {
+        let ring = self.this_mut();
+        if request.buff.len() < ring.pkt_size {
+            return Err(DError::InvalidParameter);
+        }
+        check_dma_range(request.bus_addr(), request.buff.len(), ring.dma_mask)?;
+        critical_section(|| ring.pool.push(request));
         Ok(())
     }
 
-    fn next_index(&self) -> usize {
-        let ring = self.this();
-        (ring.get_tail() as usize + 1) % ring.count()
+    /// Number of spare buffers left in the pool.
+    pub fn pool_len(&self) -> usize {
+        self.this().pool.len()
+    }
+
+    /// Post pool buffers to every free descriptor, regardless of the policy.
+    ///
+    /// Cheap enough to call from the task woken by the RX interrupt. Returns
+    /// the number of buffers posted.
+    pub fn refill(&mut self) -> usize {
+        let ring = self.this_mut();
+        critical_section(|| ring.refill_from_pool(usize::MAX))
     }
 
     pub fn request_max_count(&self) -> usize {
         self.this().count() - 1
     }
+
+    /// Wake [`RxRing::low_watermark`] when fewer than `threshold` buffers are
+    /// left for the hardware to receive into; `None` disables it.
+    ///
+    /// The threshold is also programmed into SRRCTL.RDMTS in units of 16
+    /// descriptors, so with MSI or MSI-X the RXDMT0 interrupt wakes the
+    /// waiter without polling.
+    pub fn set_low_watermark(&mut self, threshold: Option<usize>) {
+        let ring = self.this_mut();
+        ring.low_watermark = threshold;
+        let rdmts = ring.rdmts();
+        ring.regs().srrctl.modify(SRRCTL::RDMTS.val(rdmts));
+    }
+
+    /// `Ready` with the number of available buffers once it is below the
+    /// low watermark.
+    pub fn poll_low_watermark(&self, cx: &mut Context<'_>) -> Poll<usize> {
+        let ring = self.this();
+        let Some(threshold) = ring.low_watermark else {
+            return Poll::Pending;
+        };
+        ring.low_waker.register(cx.waker());
+        let available = ring.available();
+        if available < threshold {
+            Poll::Ready(available)
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Wait until the available buffers drop below the low watermark, so
+    /// they can be refilled before frames are dropped.
+    pub async fn low_watermark(&self) -> usize {
+        let _guard = WakerGuard(self.this().low_waker.clone());
+        poll_fn(|cx| self.poll_low_watermark(cx)).await
+    }
+
+    /// Descriptors the hardware has written back and [`RxRing::next_pkt`]
+    /// has not consumed yet. A frame spanning several buffers counts once
+    /// per descriptor.
+    pub fn pending_count(&self) -> usize {
+        self.this().pending_count()
+    }
+
+    /// Buffers posted to the hardware, received into or not.
+    pub fn posted_count(&self) -> usize {
+        self.this().posted()
+    }
+
+    /// Program the RXDCTL thresholds of `profile`, see
+    /// [`crate::Igb::set_rx_latency_profile`] for the interrupt side.
+    ///
+    /// The thresholds are checked like those of [`RingConfig`], so this
+    /// fails with [`DError::InvalidParameter`] for a write-back batch the
+    /// ring can't use, e.g. on 82576 in MSI-X mode.
+    pub fn set_latency_profile(&mut self, profile: RxLatencyProfile) -> Result<(), DError> {
+        let thresholds = profile.rx_thresholds();
+        let ring = self.this_mut();
+        thresholds.validate(ring.count(), ring.max_writeback)?;
+        ring.thresholds = thresholds;
+        let enable = ring.regs().dctl.get() & RXDCTL::ENABLE::Enabled.value;
+        ring.regs().dctl.set(thresholds.rxdctl().value | enable);
+        Ok(())
+    }
+
+    /// Stop this queue without touching the others, e.g. to reconfigure it.
+    ///
+    /// Frames for the queue are dropped by the hardware meanwhile. Posted
+    /// buffers stay in the ring, frames received before the pause can still
+    /// be taken with [`RxRing::next_pkt`], and buffers submitted while paused
+    /// are handed to the hardware on [`RxRing::resume`].
+    pub fn pause(&mut self) -> Result<(), DError> {
+        self.this_mut().pause()
+    }
+
+    /// Disable the queue, wait until the hardware has stopped writing to it
+    /// and return every buffer the ring holds: posted, not yet harvested and
+    /// from the pool.
+    ///
+    /// Dropping the ring does the same but frees the buffers. If the queue
+    /// does not stop, the descriptors and buffers the ring still holds are
+    /// leaked rather than freed under the hardware.
+    pub fn shutdown(mut self) -> Result<Vec<Request>, DError> {
+        let result = self.this_mut().shutdown();
+        if result.is_err() {
+            self.leak();
+        }
+        self.this_mut().shut_down = true;
+        result
+    }
+
+    /// 硬件可能仍在写入描述符和缓冲区，宁可泄漏整个环也不释放
+    fn leak(&self) {
+        core::mem::forget(self.0.clone());
+    }
+
+    /// Re-enable the queue after [`RxRing::pause`].
+    pub fn resume(&mut self) -> Result<(), DError> {
+        self.this_mut().resume()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.this().paused
+    }
+
+    /// Change the number of descriptors without resetting the device.
+    ///
+    /// The queue is stopped, the descriptor array reallocated and the queue
+    /// re-enabled empty. Frames received but not yet taken are discarded;
+    /// every buffer that was posted is returned so it can be submitted again.
+    /// An invalid size or failed allocation leaves the ring running as is.
+    pub fn resize(&mut self, new_len: usize) -> Result<Vec<Request>, DError> {
+        self.this_mut().resize(new_len)
+    }
+
+    /// Number of frames that spanned more than one receive descriptor.
+    pub fn oversize_frames(&self) -> u64 {
+        self.this().oversize_frames
+    }
+
+    /// Packets the hardware dropped on this queue because no descriptor was
+    /// available, accumulated since the ring was created.
+    pub fn hw_drops(&mut self) -> u64 {
+        let ring = self.this_mut();
+        ring.hw_drops += ring.regs().rqdpc.get() as u64;
+        ring.hw_drops
+    }
+
+    /// Doorbell, poll, harvest and refill counters of this ring.
+    #[cfg(feature = "telemetry")]
+    pub fn telemetry(&self) -> crate::telemetry::Telemetry {
+        self.this().telemetry
+    }
+
+    /// Hardware queue index of this ring, e.g. for [`crate::Igb::queue_stats`].
+    pub fn queue_index(&self) -> usize {
+        self.this().idx
+    }
+
+    /// Interrupt vector, and EICR bit, this ring signals on.
+    pub fn vector(&self) -> u8 {
+        self.this().vector
+    }
+
+    /// Unmask the ring's vector (EIMS) again after [`RxRing::disable_irq`].
+    ///
+    /// In [`IrqMode::Legacy`](crate::IrqMode::Legacy) all queues share the
+    /// TXDW, TXQE and RXT0 causes in IMS, which stay masked until every
+    /// ring has re-enabled its interrupt.
+    pub fn enable_irq(&mut self) {
+        self.this_mut().set_irq_enabled(true);
+    }
+
+    /// Mask the ring's vector (EIMC), e.g. to busy-poll this queue while
+    /// the others stay interrupt-driven. The vector is shared with the TX
+    /// ring of the queue. Waiting on the ring no longer re-arms it, and
+    /// neither do [`crate::Igb::open`] or [`crate::Igb::rearm_interrupts`].
+    /// In legacy mode this masks the queue causes of all queues (IMC).
+    pub fn disable_irq(&mut self) {
+        self.this_mut().set_irq_enabled(false);
+    }
+
+    /// 设备处于传统中断模式
+    pub(crate) fn legacy_irq(&self) -> bool {
+        self.this().legacy_irq.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_irq_state(&mut self, masked: Arc<AtomicU32>, legacy: Arc<AtomicBool>) {
+        let ring = self.this_mut();
+        ring.masked_vectors = masked;
+        ring.legacy_irq = legacy;
+    }
+
+    pub(crate) fn waker(&self) -> Arc<AtomicWaker> {
+        self.this().waker.clone()
+    }
+
+    pub(crate) fn low_waker(&self) -> Arc<AtomicWaker> {
+        self.this().low_waker.clone()
+    }
+
+    pub(crate) fn refill_handle(&self) -> RxRefill {
+        RxRefill(Arc::downgrade(&self.0))
+    }
+}
+
+/// 中断处理程序持有的补充句柄，环释放后失效
+pub(crate) struct RxRefill(Weak<UnsafeCell<RingInner>>);
+
+unsafe impl Send for RxRefill {}
+
+impl RxRefill {
+    /// 环还没有释放
+    pub fn is_live(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+
+    /// 按 [`RefillPolicy::Threshold`] 补充描述符
+    pub fn run(&self) {
+        let Some(ring) = self.0.upgrade() else {
+            return;
+        };
+        critical_section(|| unsafe { &mut *ring.get() }.refill_on_threshold());
+    }
 }
 
-impl Drop for RxRing {
+impl<L: RxLayout> Drop for RxRing<L> {
     fn drop(&mut self) {
-        // 在释放时禁用队列
-        self.this_mut().disable_queue();
+        if self.this().shut_down {
+            return;
+        }
+        // 在释放时停止队列并释放缓冲区
+        if let Err(e) = self.this_mut().shutdown() {
+            error!("RxRing: queue did not stop on drop: {e:?}");
+            self.leak();
+        }
     }
 }
 
-pub struct RxPacket<'a> {
-    pub request: Request,
-    ring: &'a mut RxRing,
-    len: usize,
+/// Metadata reported by the hardware for a received packet.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RxMeta {
+    pub len: usize,
+    pub vlan_tag: Option<u16>,
+    pub rss_hash: u32,
+    pub packet_type: u16,
+    pub ip_checksum_valid: bool,
+    pub l4_checksum_valid: bool,
+    pub has_errors: bool,
+    /// SYSTIM at reception in nanoseconds, when RX timestamping is enabled.
+    pub timestamp: Option<u64>,
+    /// Bytes in the header buffer, see [`RxPacket::header`].
+    pub header_len: usize,
+    /// The packet matched a low latency interrupt filter, see
+    /// [`crate::Igb::set_lli_filter`].
+    pub llint: bool,
+    /// Index of the EtherType filter that matched, see
+    /// [`crate::Igb::set_etype_filter`].
+    pub etype_filter: Option<u8>,
+    /// The destination address only passed the multicast/unicast hash
+    /// table, not an exact address filter.
+    pub inexact_match: bool,
 }
 
-impl<'a> RxPacket<'a> {
-    pub fn re_submit(self) -> Result<(), DError> {
-        self.ring.submit(self.request)
+impl RxMeta {
+    fn from_desc(desc: &AdvRxDescWB) -> Self {
+        Self {
+            len: desc.packet_length() as usize,
+            vlan_tag: desc.is_vlan_packet().then(|| desc.vlan_tag()),
+            rss_hash: desc.rss_hash(),
+            packet_type: desc.packet_type(),
+            ip_checksum_valid: desc.ip_checksum_valid(),
+            l4_checksum_valid: desc.l4_checksum_valid(),
+            has_errors: desc.has_errors(),
+            timestamp: None,
+            header_len: 0,
+            llint: desc.is_llint(),
+            etype_filter: desc.etype_filter(),
+            inexact_match: desc.passed_inexact_filter(),
+        }
     }
 }
 
-impl Deref for RxPacket<'_> {
+/// A received frame.
+///
+/// Frames that span several descriptors are copied into one contiguous
+/// buffer; all of their DMA buffers are returned to the ring together.
+pub struct RxPacket<'a, L: RxLayout = OneBuffer> {
+    request: Option<Request>,
+    chain: Vec<Request>,
+    assembled: Option<Vec<u8>>,
+    header: Option<Vec<u8>>,
+    ring: &'a mut RxRing<L>,
+    meta: RxMeta,
+}
+
+impl<'a, L: RxLayout> RxPacket<'a, L> {
+    pub fn meta(&self) -> &RxMeta {
+        &self.meta
+    }
+
+    pub fn request(&self) -> &Request {
+        self.request.as_ref().expect("Request should be set")
+    }
+
+    /// Number of receive descriptors this frame occupied.
+    pub fn segments(&self) -> usize {
+        1 + self.chain.len()
+    }
+
+    pub fn re_submit(mut self) -> Result<(), DError> {
+        let request = self.request.take().expect("Request should be set");
+        self.ring.submit(request)?;
+        for request in core::mem::take(&mut self.chain) {
+            self.ring.submit(request)?;
+        }
+        Ok(())
+    }
+
+    /// Takes the first buffer out of the packet; the ring will not reuse it.
+    ///
+    /// Buffers of further segments are still returned to the ring.
+    pub fn into_request(mut self) -> Request {
+        let request = self.request.take().expect("Request should be set");
+        let ring = self.ring.this_mut();
+        if ring.refill == RefillPolicy::Immediate {
+            critical_section(|| ring.refill_from_pool(1));
+        }
+        request
+    }
+}
+
+impl RxPacket<'_, HeaderSplit> {
+    /// Headers the hardware replicated into the header buffer, `None` when
+    /// the frame did not qualify under the ring's [`HeaderMode`].
+    ///
+    /// They are copied out when the packet is harvested, since the header
+    /// slot goes back to the hardware with the descriptor's next refill.
+    pub fn header(&self) -> Option<&[u8]> {
+        self.header.as_deref()
+    }
+}
+
+impl<L: RxLayout> Deref for RxPacket<'_, L> {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        &self.request.deref()[..self.len]
+        match &self.assembled {
+            Some(buf) => buf,
+            None => &self.request().deref()[..self.meta.len],
+        }
+    }
+}
+
+impl<L: RxLayout> Drop for RxPacket<'_, L> {
+    fn drop(&mut self) {
+        if let Some(request) = self.request.take() {
+            let _ = self.ring.submit(request);
+        }
+        for request in core::mem::take(&mut self.chain) {
+            let _ = self.ring.submit(request);
+        }
     }
 }
@@ -1,14 +1,42 @@
-use core::ops::{Deref, DerefMut};
+use core::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    sync::atomic::{AtomicU64, AtomicUsize},
+    task::{Context, Poll},
+};
 
-use alloc::sync::Arc;
-use log::trace;
+use alloc::{sync::Arc, vec};
+use futures::Sink;
 
-use crate::descriptor::{TxAdvDescCmd, TxAdvDescType};
+use crate::{
+    descriptor::{TxAdvDescCmd, TxAdvDescType},
+    osal::critical_section,
+    ptp,
+};
+
+use tock_registers::interfaces::{Readable, Writeable};
 
 use super::*;
 struct RingInner {
     base: Ring<AdvTxDesc>,
-    finished: usize,
+    /// 消费者（回收）索引，与生产者路径的字段分开缓存行
+    finished: CachePadded<usize>,
+    /// 已确认完成（RS 描述符 DD 置位）的边界，不含
+    clean_until: usize,
+    rs_interval: usize,
+    /// 上一个 RS 描述符之后入队的描述符数
+    since_rs: usize,
+    /// 每个槽位是否设置了 RS，只有这些槽位会被硬件写回
+    rs_slots: Vec<bool>,
+    ts_next_id: u32,
+    ts_pending: Option<u32>,
+    /// 入队的请求总数，作为 [`TxCompletion`] 的序号
+    sent: u64,
+    progress: Arc<Progress>,
+    waiters: Arc<WaitList>,
+    /// 按 MTU 允许的最长请求，不含 FCS
+    max_len: usize,
 }
 
 impl Deref for RingInner {
@@ -26,8 +54,22 @@ impl DerefMut for RingInner {
 }
 
 impl RingInner {
-    fn new(base: Ring<AdvTxDesc>) -> Self {
-        Self { base, finished: 0 }
+    fn new(base: Ring<AdvTxDesc>, rs_interval: usize, max_len: usize) -> Self {
+        let count = base.count();
+        Self {
+            base,
+            finished: CachePadded::new(0),
+            clean_until: 0,
+            rs_interval,
+            since_rs: 0,
+            rs_slots: vec![false; count],
+            ts_next_id: 0,
+            ts_pending: None,
+            sent: 0,
+            progress: Arc::new(Progress::default()),
+            waiters: Arc::new(WaitList::default()),
+            max_len,
+        }
     }
 
     pub fn init(&mut self) -> Result<(), DError> {
@@ -37,32 +79,34 @@ impl RingInner {
         let bus_addr = self.base.bus_addr();
 
         // Step 2: Program the descriptor base address with the address of the region
-        self.reg_write(TDBAL, (bus_addr & 0xFFFFFFFF) as u32);
-        self.reg_write(TDBAH, (bus_addr >> 32) as u32);
+        self.regs().bal.set((bus_addr & 0xFFFFFFFF) as u32);
+        self.regs().bah.set((bus_addr >> 32) as u32);
 
         // Step 3: Set the length register to the size of the descriptor ring
         let size_bytes = self.base.size_bytes();
-        self.reg_write(TDLEN, size_bytes as u32);
+        self.regs().len.set(size_bytes as u32);
 
         // Step 4: Program the TXDCTL register with the desired TX descriptor write back policy
         // Suggested values: WTHRESH = 1, all other fields 0
-        self.reg_write(TXDCTL, TXDCTL::WTHRESH.val(1).value);
+        let txdctl = self.thresholds.txdctl();
+        self.regs().dctl.set(txdctl.value);
 
-        self.reg_write(TDH, 0);
-        self.reg_write(TDT, 0);
+        self.regs().head.set(0);
+        self.regs().tail.set(0);
+        self.reset_tail();
 
         // Step 5: If needed, set the TDWBAL/TWDBAH to enable head write back
         // (Not implemented in this basic version)
 
         // Step 6: Enable the queue using TXDCTL.ENABLE (queue zero is enabled by default)
-        self.reg_write(
-            TXDCTL,
-            (TXDCTL::WTHRESH.val(1) + TXDCTL::ENABLE::Enabled).value,
-        );
+        self.regs()
+            .dctl
+            .set((txdctl + TXDCTL::ENABLE::Enabled).value);
 
         // Step 7: Poll the TXDCTL register until the ENABLE bit is set
         wait_for(
-            || self.reg_read(TXDCTL) & TXDCTL::ENABLE::Enabled.value > 0,
+            "TX queue enable",
+            || self.regs().dctl.get() & TXDCTL::ENABLE::Enabled.value > 0,
             Duration::from_millis(1),
             Some(1000),
         )?;
@@ -74,80 +118,243 @@ impl RingInner {
         Ok(())
     }
 
+    /// 禁用队列并等待硬件确认
+    fn stop_queue(&mut self) -> Result<(), DError> {
+        let txdctl = self.thresholds.txdctl() + TXDCTL::ENABLE::Disabled;
+        self.regs().dctl.set(txdctl.value);
+        wait_for(
+            "TX queue disable",
+            || self.regs().dctl.get() & TXDCTL::ENABLE::Enabled.value == 0,
+            Duration::from_millis(1),
+            Some(1000),
+        )
+    }
+
+    fn flush(&mut self) -> Result<Vec<Request>, DError> {
+        let requests = self.drain()?;
+        self.init()?;
+        Ok(requests)
+    }
+
+    /// 尽量发完已入队的请求后停止队列，返回所有未回收的请求
+    fn drain(&mut self) -> Result<Vec<Request>, DError> {
+        // 批量模式下尚未敲门铃的请求也交给硬件
+        self.ring_doorbell();
+        // SWFLUSH 强制写回尚未达到 WTHRESH 的描述符
+        let txdctl = self.thresholds.txdctl() + TXDCTL::ENABLE::Enabled + TXDCTL::SWFLUSH::SET;
+        self.regs().dctl.set(txdctl.value);
+        // 链路断开时硬件不会再发送，超时后直接回收
+        if wait_for(
+            "TX queue flush",
+            || self.get_tx_head() == self.get_tx_tail(),
+            Duration::from_millis(1),
+            Some(100),
+        )
+        .is_err()
+        {
+            debug!("TX queue {} did not drain before flush", self.idx);
+        }
+        self.stop_queue()?;
+        let oldest = *self.finished;
+        let requests = self.take_requests(oldest);
+        self.reset_completion();
+        Ok(requests)
+    }
+
+    fn pause(&mut self) -> Result<Vec<Request>, DError> {
+        if self.paused {
+            return Ok(Vec::new());
+        }
+        let requests = self.drain()?;
+        // 队列已停止，可以复位头尾指针；暂停期间入队的请求从 0 开始
+        self.regs().head.set(0);
+        self.regs().tail.set(0);
+        self.reset_tail();
+        self.paused = true;
+        Ok(requests)
+    }
+
+    fn resume(&mut self) -> Result<(), DError> {
+        if !self.paused {
+            return Ok(());
+        }
+        let txdctl = self.thresholds.txdctl() + TXDCTL::ENABLE::Enabled;
+        self.regs().dctl.set(txdctl.value);
+        wait_for(
+            "TX queue enable",
+            || self.regs().dctl.get() & TXDCTL::ENABLE::Enabled.value > 0,
+            Duration::from_millis(1),
+            Some(1000),
+        )?;
+        // 队列使能后再写尾指针，交出暂停期间入队的请求
+        self.paused = false;
+        self.ring_doorbell();
+        Ok(())
+    }
+
+    fn resize(&mut self, size: usize) -> Result<Vec<Request>, DError> {
+        validate_rs_interval(self.rs_interval, size)?;
+        let descriptors = self.alloc_resized(size)?;
+        self.stop_queue()?;
+        let requests = self.replace_descriptors(descriptors);
+        self.reset_completion();
+        self.init()?;
+        Ok(requests)
+    }
+
+    /// 环清空后复位回收和 RS 状态
+    fn reset_completion(&mut self) {
+        *self.finished = 0;
+        // 取回的请求不会再完成，等待者全部结束
+        self.progress.publish(self.sent, 0);
+        self.clean_until = 0;
+        self.since_rs = 0;
+        let count = self.count();
+        self.rs_slots.clear();
+        self.rs_slots.resize(count, false);
+        self.ts_pending = None;
+        self.waiters.wake();
+    }
+
     /// 获取当前头部指针值
     pub fn get_tx_head(&self) -> u32 {
-        self.reg_read(TDH)
+        self.regs().head.get()
     }
 
     /// 获取当前尾部指针值
     pub fn get_tx_tail(&self) -> u32 {
-        self.reg_read(TDT)
+        self.regs().tail.get()
     }
 
     /// 发送单个数据包
-    pub fn send_packet(&mut self, request: Request) -> Result<(), DError> {
-        if request.buff.len() > PACKET_SIZE as usize {
-            return Err(DError::InvalidParameter);
-        }
-        trace!("send {}", request.buff.len());
-        request.buff.confirm_write_all();
-        let tail = self.get_tx_tail() as usize;
-        let next_tail = (tail + 1) % self.count();
+    pub fn send_packet(&mut self, request: Request, timestamp: bool) -> Result<(), DError> {
+        // 与中断上下文中的回收互斥
+        critical_section(|| self.enqueue(request, timestamp, timestamp))
+    }
+
+    /// `rs` 强制设置 RS，让硬件尽快写回这个描述符
+    fn enqueue(&mut self, request: Request, timestamp: bool, rs: bool) -> Result<(), DError> {
+        // 超长帧会被硬件截断或丢弃，不报告错误
+        if request.len > self.max_len {
+            return Err(DError::FrameTooLong {
+                len: request.len,
+                max: self.max_len,
+            });
+        }
+        check_dma_range(request.bus_addr(), request.len, self.dma_mask)?;
+        datapath_trace!("send {}", request.len);
+        request.buff.sync_for_device();
+        let tail = *self.tail;
+        let next_tail = self.wrap(tail + 1);
         let head = self.get_tx_head() as usize;
 
-        // 检查是否有空间
-        if next_tail == head {
+        // 检查是否有空间；头指针可能越过尚未回收的槽位
+        if next_tail == head || self.meta_ls[tail].request.is_some() {
             return Err(DError::NoMemory); // 环形缓冲区已满
         }
 
+        // 每 rs_interval 个描述符设置一次 RS；时间戳包总是设置
+        self.since_rs += 1;
+        let rs = rs || self.since_rs >= self.rs_interval;
+        if rs {
+            self.since_rs = 0;
+        }
+
         // 设置描述符
+        let cmd = [
+            TxAdvDescCmd::EOP,
+            TxAdvDescCmd::IFCS,
+            TxAdvDescCmd::DEXT,
+            TxAdvDescCmd::RS,
+            TxAdvDescCmd::TSTAMP,
+        ];
+        let cmd_len = if timestamp {
+            5
+        } else if rs {
+            4
+        } else {
+            3
+        };
         let desc = AdvTxDesc::new(
             request.bus_addr(),
-            request.buff.len(),
+            request.len,
             TxAdvDescType::Data,
-            &[
-                TxAdvDescCmd::EOP,
-                TxAdvDescCmd::RS,
-                TxAdvDescCmd::IFCS,
-                TxAdvDescCmd::DEXT,
-            ],
+            &cmd[..cmd_len],
         );
 
         self.descriptors.set(tail, desc);
         self.meta_ls[tail].request = Some(request);
+        self.rs_slots[tail] = rs;
 
         // 内存屏障确保描述符写入完成
         mb();
 
         // 更新尾部指针
-        self.reg_write(TDT, next_tail as u32);
+        self.advance_tail(next_tail);
+        self.sent += 1;
 
         Ok(())
     }
 
+    fn flush_doorbell(&mut self) {
+        critical_section(|| self.ring_doorbell())
+    }
+
+    fn send_timestamped(&mut self, request: Request) -> Result<u32, DError> {
+        // 硬件只有一个 TX 时间戳寄存器
+        if self.ts_pending.is_some() {
+            return Err(DError::NoMemory);
+        }
+        self.send_packet(request, true)?;
+        let id = self.ts_next_id;
+        self.ts_next_id = self.ts_next_id.wrapping_add(1);
+        self.ts_pending = Some(id);
+        Ok(id)
+    }
+
+    fn poll_tx_timestamp(&mut self) -> Option<(u32, u64)> {
+        let id = self.ts_pending?;
+        if self.global_read(ptp::TSYNCTXCTL) & ptp::TSYNCTXCTL_VALID == 0 {
+            return None;
+        }
+        // 读 TXSTMPH 会清除 VALID
+        let low = self.global_read(ptp::TXSTMPL);
+        let ts = ptp::to_ns(self.family, low, self.global_read(ptp::TXSTMPH));
+        self.ts_pending = None;
+        Some((id, ts))
+    }
+
     fn next_finished(&mut self) -> Option<Request> {
+        critical_section(|| self.take_finished())
+    }
+
+    fn take_finished(&mut self) -> Option<Request> {
         let head = self.get_tx_head() as usize;
-        if self.finished == head {
+        if *self.finished == head {
             return None; // 没有新的完成描述符
         }
-        let index = self.finished;
+        let index = *self.finished;
 
-        trace!("next_finished index: {index}");
+        datapath_trace!("next_finished index: {index}");
 
-        // 检查描述符是否已完成
-        unsafe {
-            let desc = &self.descriptors[index];
-            if !desc.write.is_done() {
-                trace!("TxRing: next_finished descriptor not done at index: {index}");
+        // RS 描述符完成意味着它之前的描述符都已完成
+        if index == self.clean_until {
+            let rs = self.next_rs_slot(index)?;
+            if !self.is_done(rs) {
+                datapath_trace!("TxRing: next_finished descriptor not done at index: {rs}");
                 return None; // 描述符未完成，无法获取数据
             }
+            self.clean_until = self.wrap(rs + 1);
         }
         let request = self.meta_ls[index]
             .request
             .take()
             .expect("Request should be set");
 
-        self.finished = (self.finished + 1) % self.count();
+        *self.finished = self.wrap(*self.finished + 1);
+        let reclaimed = self.progress.reclaimed.load(Ordering::Relaxed) + 1;
+        self.progress.publish(reclaimed, *self.finished);
+        telemetry!(self.telemetry.harvested += 1);
         Some(request)
     }
 }
@@ -158,8 +365,27 @@ unsafe impl Send for TxRing {}
 
 impl TxRing {
     #[allow(clippy::arc_with_non_send_sync)]
-    pub(crate) fn new(idx: usize, mmio_base: NonNull<u8>, size: usize) -> Result<Self, DError> {
-        let mut ring_inner = RingInner::new(Ring::new(idx, mmio_base, size, PACKET_SIZE as usize)?);
+    pub(crate) fn new(
+        idx: usize,
+        mmio_base: NonNull<u8>,
+        config: &RingConfig,
+        family: DeviceFamily,
+        region: Option<&'static mut [u8]>,
+        max_writeback: u8,
+    ) -> Result<Self, DError> {
+        let mut ring_inner = RingInner::new(
+            Ring::new(
+                idx,
+                mmio_base,
+                region,
+                config,
+                config.tx_thresholds,
+                family,
+                max_writeback,
+            )?,
+            config.tx_rs_interval,
+            config.max_tx_len(),
+        );
 
         ring_inner.init()?;
         let ring = Arc::new(UnsafeCell::new(ring_inner));
@@ -174,21 +400,389 @@ impl TxRing {
         unsafe { &mut *self.0.get() }
     }
 
+    /// Longest request [`TxRing::send`] accepts: the ring's MTU plus
+    /// Ethernet header and one VLAN tag, without FCS. Longer requests fail
+    /// with [`DError::FrameTooLong`].
+    pub fn max_frame_len(&self) -> usize {
+        self.this().max_len
+    }
+
     pub fn send(&mut self, request: Request) -> Result<(), DError> {
-        self.this_mut().send_packet(request)
+        self.this_mut().send_packet(request, false)
+    }
+
+    /// Copy `data` into a new DMA buffer and send it, for callers of the old
+    /// buffer-oriented API.
+    ///
+    /// The buffer comes back from [`TxRing::next_finished`] like any other
+    /// request once sent.
+    pub fn send_slice(&mut self, data: &[u8]) -> Result<(), DError> {
+        let request = Request::new_tx_with_mask(data.to_vec(), self.dma_mask())?;
+        self.send(request)
+    }
+
+    /// Only update the software tail in [`TxRing::send`], leaving the TDT
+    /// write to [`TxRing::flush_doorbell`].
+    ///
+    /// Lets an event loop queue a whole burst with one MMIO write. Disabling
+    /// batching flushes anything still pending.
+    pub fn set_doorbell_batching(&mut self, enable: bool) {
+        let ring = self.this_mut();
+        ring.batch_doorbell = enable;
+        if !enable {
+            ring.flush_doorbell();
+        }
+    }
+
+    /// Write the software tail to TDT if requests were queued since the
+    /// last doorbell. [`Sink::poll_flush`] does this before waiting.
+    pub fn flush_doorbell(&mut self) {
+        self.this_mut().flush_doorbell();
+    }
+
+    /// Send `request` and ask the MAC to capture its transmit time.
+    ///
+    /// Returns an id that [`TxRing::poll_tx_timestamp`] reports together
+    /// with the timestamp. The device holds a single TX timestamp, so this
+    /// fails with [`DError::NoMemory`] until the previous one was polled.
+    /// Requires TX timestamping enabled with [`crate::Igb::set_timestamping`].
+    pub fn send_timestamped(&mut self, request: Request) -> Result<u32, DError> {
+        self.this_mut().send_timestamped(request)
+    }
+
+    /// The captured transmit time, in SYSTIM nanoseconds, of the last
+    /// [`TxRing::send_timestamped`] request.
+    pub fn poll_tx_timestamp(&mut self) -> Option<(u32, u64)> {
+        self.this_mut().poll_tx_timestamp()
     }
 
     pub fn request_max_count(&self) -> usize {
         self.this().count() - 1
     }
 
+    /// Requests the hardware has sent that [`TxRing::next_finished`] would
+    /// return, based on the write-back of RS descriptors.
+    pub fn completed_count(&self) -> usize {
+        self.this().completed_count()
+    }
+
+    /// Requests handed to the hardware and not yet sent.
+    pub fn in_flight_count(&self) -> usize {
+        let ring = self.this();
+        let count = ring.count();
+        ring.wrap(ring.get_tx_tail() as usize + count - ring.get_tx_head() as usize)
+    }
+
+    /// DMA mask requests must satisfy, for [`Request::new_tx_with_mask`].
+    pub fn dma_mask(&self) -> u64 {
+        self.this().dma_mask
+    }
+
+    /// Let the hardware finish what it can, then stop the queue and return
+    /// every request not yet reclaimed with [`TxRing::next_finished`], oldest
+    /// first.
+    ///
+    /// Requests still queued when the link is down are returned unsent. The
+    /// ring is re-enabled empty afterwards.
+    pub fn flush(&mut self) -> Result<Vec<Request>, DError> {
+        self.this_mut().flush()
+    }
+
+    /// Stop this queue without touching the others, e.g. to reconfigure it.
+    ///
+    /// Like [`TxRing::flush`], queued requests get a chance to go out first
+    /// and every request not yet reclaimed is returned. Requests sent while
+    /// paused are held in the ring until [`TxRing::resume`].
+    pub fn pause(&mut self) -> Result<Vec<Request>, DError> {
+        self.this_mut().pause()
+    }
+
+    /// Re-enable the queue after [`TxRing::pause`] and start sending the
+    /// requests held meanwhile.
+    pub fn resume(&mut self) -> Result<(), DError> {
+        self.this_mut().resume()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.this().paused
+    }
+
+    /// Change the number of descriptors without resetting the device.
+    ///
+    /// The queue is stopped, the descriptor array reallocated and the queue
+    /// re-enabled empty. Requests not yet reclaimed with
+    /// [`TxRing::next_finished`] are returned, whether or not they were sent.
+    /// An invalid size or failed allocation leaves the ring running as is.
+    pub fn resize(&mut self, new_len: usize) -> Result<Vec<Request>, DError> {
+        self.this_mut().resize(new_len)
+    }
+
     pub fn is_queue_full(&self) -> bool {
-        let head = self.this().get_tx_head() as usize;
-        let tail = self.this().get_tx_tail() as usize;
-        (tail + 1) % self.this().count() == head
+        self.this().is_full()
     }
 
     pub fn next_finished(&mut self) -> Option<Request> {
         self.this_mut().next_finished()
     }
+
+    /// Doorbell and completion counters of this ring.
+    #[cfg(feature = "telemetry")]
+    pub fn telemetry(&self) -> crate::telemetry::Telemetry {
+        self.this().telemetry
+    }
+
+    /// Hardware queue index of this ring, e.g. for [`crate::Igb::queue_stats`].
+    pub fn queue_index(&self) -> usize {
+        self.this().idx
+    }
+
+    /// Interrupt vector, and EICR bit, this ring signals on.
+    pub fn vector(&self) -> u8 {
+        self.this().vector
+    }
+
+    /// Unmask the ring's vector (EIMS) again after [`TxRing::disable_irq`].
+    ///
+    /// In [`IrqMode::Legacy`](crate::IrqMode::Legacy) all queues share the
+    /// TXDW, TXQE and RXT0 causes in IMS, which stay masked until every
+    /// ring has re-enabled its interrupt.
+    pub fn enable_irq(&mut self) {
+        self.this_mut().set_irq_enabled(true);
+    }
+
+    /// Mask the ring's vector (EIMC), e.g. to busy-poll this queue while
+    /// the others stay interrupt-driven. The vector is shared with the RX
+    /// ring of the queue. Waiting on the ring no longer re-arms it, and
+    /// neither do [`crate::Igb::open`] or [`crate::Igb::rearm_interrupts`].
+    /// In legacy mode this masks the queue causes of all queues (IMC).
+    pub fn disable_irq(&mut self) {
+        self.this_mut().set_irq_enabled(false);
+    }
+
+    pub(crate) fn set_irq_state(&mut self, masked: Arc<AtomicU32>, legacy: Arc<AtomicBool>) {
+        let ring = self.this_mut();
+        ring.masked_vectors = masked;
+        ring.legacy_irq = legacy;
+    }
+
+    pub(crate) fn waker(&self) -> Arc<AtomicWaker> {
+        self.this().waker.clone()
+    }
+
+    pub(crate) fn waiters(&self) -> Arc<WaitList> {
+        self.this().waiters.clone()
+    }
+
+    /// Send `request` and return a future that completes once the hardware
+    /// has sent it.
+    ///
+    /// Up to [`MAX_TX_WAITERS`] completions can be awaited at once, from
+    /// different tasks; beyond that this fails with [`DError::NoMemory`]
+    /// without queueing the request. The queue interrupt wakes them, and
+    /// [`Igb`](crate::Igb) keeps an auto-masked queue vector armed while any
+    /// are pending. The request itself still comes back from
+    /// [`TxRing::next_finished`]. Requests returned by [`TxRing::flush`],
+    /// [`TxRing::pause`] or [`TxRing::resize`] complete their futures unsent.
+    pub fn send_wait(&mut self, request: Request) -> Result<TxCompletion, DError> {
+        let ring = self.this_mut();
+        let slot = ring.waiters.acquire().ok_or(DError::NoMemory)?;
+        let index = *ring.tail;
+        if let Err(e) = critical_section(|| ring.enqueue(request, false, true)) {
+            ring.waiters.release(slot);
+            return Err(e);
+        }
+        Ok(TxCompletion {
+            progress: ring.progress.clone(),
+            waiters: ring.waiters.clone(),
+            regs: ring.regs,
+            seq: ring.sent - 1,
+            index,
+            count: ring.count(),
+            slot,
+        })
+    }
+
+    /// [`TxRing::send_wait`] whose future gives up with
+    /// [`DError::TimedOut`] once `timeout` has passed since this call,
+    /// measured with [`Kernel::now`](crate::osal::Kernel::now).
+    ///
+    /// Without a timer interrupt the future wakes itself while it waits, so
+    /// the executor keeps polling it. A request that times out stays queued
+    /// and still comes back from [`TxRing::next_finished`].
+    pub fn send_wait_timeout(
+        &mut self,
+        request: Request,
+        timeout: Duration,
+    ) -> Result<impl Future<Output = Result<(), DError>> + Send + use<>, DError> {
+        let completion = self.send_wait(request)?;
+        Ok(with_timeout(
+            "TX completion",
+            async move {
+                completion.await;
+                Ok(())
+            },
+            timeout,
+        ))
+    }
+}
+
+/// 回收进度，由 TxRing 发布给 [`TxCompletion`]
+#[derive(Default)]
+pub(crate) struct Progress {
+    /// 已回收的请求数，序号小于它的请求都已结束
+    reclaimed: AtomicU64,
+    /// 下一个待回收的描述符
+    finished: AtomicUsize,
+}
+
+impl Progress {
+    /// 先发布 reclaimed：读者看到 finished 越过某个槽位时，它的请求必已计入
+    fn publish(&self, reclaimed: u64, finished: usize) {
+        self.reclaimed.store(reclaimed, Ordering::Release);
+        self.finished.store(finished, Ordering::Release);
+    }
+}
+
+/// Completion of one request sent with [`TxRing::send_wait`].
+pub struct TxCompletion {
+    progress: Arc<Progress>,
+    waiters: Arc<WaitList>,
+    regs: NonNull<QueueRegs>,
+    seq: u64,
+    /// 请求所在的描述符和当时的环长度
+    index: usize,
+    count: usize,
+    slot: usize,
+}
+
+// regs 只用于读 TDH
+unsafe impl Send for TxCompletion {}
+
+impl TxCompletion {
+    fn reclaimed(&self) -> bool {
+        self.progress.reclaimed.load(Ordering::Acquire) > self.seq
+    }
+
+    /// 已回收，或硬件头指针已越过请求的描述符。槽位被回收复用后位置比较
+    /// 不再有意义，所以最后再查一次 reclaimed
+    fn is_done(&self) -> bool {
+        if self.reclaimed() {
+            return true;
+        }
+        let finished = self.progress.finished.load(Ordering::Acquire);
+        let head = unsafe { self.regs.as_ref() }.head.get() as usize;
+        let distance = |index: usize| (index + self.count - finished) % self.count;
+        distance(self.index) < distance(head) || self.reclaimed()
+    }
+}
+
+impl Future for TxCompletion {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // 先登记再检查，避免错过两者之间的中断
+        self.waiters.register(self.slot, cx.waker());
+        if self.is_done() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for TxCompletion {
+    fn drop(&mut self) {
+        self.waiters.release(self.slot);
+    }
+}
+
+impl Drop for RingInner {
+    fn drop(&mut self) {
+        // 环释放后请求不会再完成
+        self.progress.publish(u64::MAX, 0);
+        self.waiters.wake();
+    }
+}
+
+impl RingInner {
+    /// 回收已完成的请求；Sink 模式下由驱动直接释放
+    fn reclaim(&mut self) {
+        while self.next_finished().is_some() {}
+    }
+
+    fn completed_count(&self) -> usize {
+        let count = self.count();
+        let mut until = self.clean_until;
+        let mut index = until;
+        while index != *self.tail {
+            if self.rs_slots[index] {
+                if !self.is_done(index) {
+                    break;
+                }
+                until = self.wrap(index + 1);
+            }
+            index = self.wrap(index + 1);
+        }
+        self.wrap(until + count - *self.finished)
+    }
+
+    /// 从 `start` 到尾部之间第一个设置了 RS 的槽位
+    fn next_rs_slot(&self, start: usize) -> Option<usize> {
+        let mut index = start;
+        while index != *self.tail {
+            if self.rs_slots[index] {
+                return Some(index);
+            }
+            index = self.wrap(index + 1);
+        }
+        None
+    }
+
+    fn is_done(&self, index: usize) -> bool {
+        unsafe { self.descriptors[index].write.is_done() }
+    }
+
+    fn is_full(&self) -> bool {
+        let head = self.get_tx_head() as usize;
+        self.wrap(*self.tail + 1) == head || self.meta_ls[*self.tail].request.is_some()
+    }
+}
+
+/// Sends requests with backpressure from the free descriptors.
+///
+/// Completed requests are reclaimed and dropped by the sink, so don't mix it
+/// with [`TxRing::next_finished`].
+impl Sink<Request> for TxRing {
+    type Error = DError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), DError>> {
+        let ring = self.this_mut();
+        ring.waker.register(cx.waker());
+        ring.reclaim();
+        if ring.is_full() {
+            ring.rearm_irq();
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Request) -> Result<(), DError> {
+        self.send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), DError>> {
+        let ring = self.this_mut();
+        ring.waker.register(cx.waker());
+        ring.flush_doorbell();
+        ring.reclaim();
+        // 硬件头指针追上尾指针即全部发送完成
+        if ring.get_tx_head() != ring.get_tx_tail() {
+            ring.rearm_irq();
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), DError>> {
+        self.poll_flush(cx)
+    }
 }
@@ -0,0 +1,143 @@
+use crate::{DError, family::DeviceFamily, mac::Mac};
+
+const TQAVCTRL: usize = 0x3570; // I210 Transmit Qav Control
+const TQAVCC: usize = 0x3004; // Transmit Qav Credit Control, 0x40 per queue
+const TQAVHC: usize = 0x300C; // Transmit Qav High Credit, 0x40 per queue
+const TXPBS: usize = 0x3404; // TX Packet Buffer Size
+const RXPBS: usize = 0x2404; // RX Packet Buffer Size
+const DTXMXPKTSZ: usize = 0x355C; // DMA TX Max Packet Size
+
+const TQAVCTRL_XMIT_MODE: u32 = 1 << 0; // Qav 模式
+const TQAVCTRL_DATAFETCHARB: u32 = 1 << 4;
+const TQAVCTRL_DATATRANARB: u32 = 1 << 8; // 按信用整形仲裁
+const TQAVCTRL_SP_WAIT_SR: u32 = 1 << 10;
+
+const TQAVCC_IDLESLOPE_MASK: u32 = 0xFFFF;
+const TQAVCC_QUEUEMODE_SR: u32 = 1 << 31; // Stream Reservation 队列
+
+// 参考 igb_setup_tx_mode：Qav 模式下 4 个 6 KB 发送缓冲区，接收缓冲区 30 KB
+const TXPBS_MASK: u32 = 0xC0FF_FFFF;
+const TXPBS_QAV: u32 = 6 | 6 << 6 | 6 << 12 | 6 << 18;
+const TXPBS_DEFAULT: u32 = 0x0400_0014;
+const RXPBS_MASK: u32 = 0x3F;
+const RXPBS_QAV: u32 = 30;
+const RXPBS_DEFAULT: u32 = 0xA2;
+// 最大包不超过最小的发送缓冲区，64 字节为单位
+const DTXMXPKTSZ_QAV: u32 = (4096 - 1) / 64;
+const DTXMXPKTSZ_DEFAULT: u32 = 0x98;
+
+/// Idle slope of a queue owning the whole 1000 Mb/s link.
+const IDLESLOPE_FULL: u32 = 61034;
+/// hiCredit 允许攒够一个最大以太网帧
+const HICREDIT_BASE: u32 = 0x8000_0000;
+const HICREDIT_PER_BYTE: u32 = 0x7735;
+const MAX_FRAME: u32 = 1514;
+
+/// Number of queues the credit based shaper can limit.
+pub const SHAPED_QUEUES: usize = 2;
+
+/// How TX queues share the link, see [`crate::Igb::set_tx_scheduling`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TxScheduling {
+    /// Queues take turns (the reset default).
+    #[default]
+    RoundRobin,
+    /// I210 only: a queue sends only when all lower numbered queues are idle.
+    StrictPriority,
+    /// I210 only: 802.1Qav credit based shaping. Queue `n` below
+    /// [`SHAPED_QUEUES`] is limited to `percent[n]` of 1000 Mb/s and goes
+    /// before the remaining queues, which share the rest in strict priority.
+    /// 0 leaves queue 0 unlimited and puts queue 1 into strict priority.
+    Shaped { percent: [u8; SHAPED_QUEUES] },
+    /// I210 only: queue `n` below [`SHAPED_QUEUES`] gets a share of the
+    /// link in proportion to `weights[n]` when the queues compete, e.g. to
+    /// keep a control queue responsive next to a bulk queue. Programmed as
+    /// 802.1Qav idle slopes; the remaining queues, and queue 1 with weight
+    /// 0, go after them in strict priority. Queue 0 needs a weight.
+    Weighted { weights: [u8; SHAPED_QUEUES] },
+}
+
+impl TxScheduling {
+    pub(crate) fn validate(&self, family: DeviceFamily) -> Result<(), DError> {
+        match self {
+            Self::RoundRobin => Ok(()),
+            _ if !family.has_qav() => Err(DError::Unsupported),
+            Self::StrictPriority => Ok(()),
+            Self::Shaped { percent } => {
+                let total: u32 = percent.iter().map(|&p| p as u32).sum();
+                if total > 100 {
+                    return Err(DError::InvalidParameter);
+                }
+                Ok(())
+            }
+            Self::Weighted { weights } => {
+                if weights[0] == 0 {
+                    return Err(DError::InvalidParameter);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// 每个整形队列的 idle slope，None 表示严格优先级
+    fn idle_slopes(&self) -> [Option<u32>; SHAPED_QUEUES] {
+        match *self {
+            Self::RoundRobin | Self::StrictPriority => {
+                core::array::from_fn(|queue| (queue == 0).then_some(IDLESLOPE_FULL))
+            }
+            // Qav 模式下队列 0 不能是严格优先级，不限速时给它全部信用
+            Self::Shaped { percent } => {
+                core::array::from_fn(|queue| match (queue, percent[queue]) {
+                    (0, 0) => Some(IDLESLOPE_FULL),
+                    (_, 0) => None,
+                    (_, p) => Some((IDLESLOPE_FULL * p as u32).div_ceil(100)),
+                })
+            }
+            Self::Weighted { weights } => {
+                let total: u32 = weights.iter().map(|&w| w as u32).sum();
+                weights.map(|w| (w != 0).then(|| (IDLESLOPE_FULL * w as u32).div_ceil(total)))
+            }
+        }
+    }
+}
+
+/// 参考 igb_setup_tx_mode / igb_config_tx_modes，须在收发使能前调用
+pub(crate) fn apply(mac: &mut Mac, family: DeviceFamily, sched: &TxScheduling) {
+    if !family.has_qav() {
+        return;
+    }
+    if *sched == TxScheduling::RoundRobin {
+        mac.write_reg(RXPBS, RXPBS_DEFAULT);
+        mac.write_reg(TXPBS, TXPBS_DEFAULT);
+        mac.write_reg(DTXMXPKTSZ, DTXMXPKTSZ_DEFAULT);
+        let ctrl = mac.read_reg(TQAVCTRL) & !TQAVCTRL_XMIT_MODE;
+        mac.write_reg(TQAVCTRL, ctrl);
+        return;
+    }
+
+    let ctrl = mac.read_reg(TQAVCTRL) & !TQAVCTRL_DATAFETCHARB;
+    mac.write_reg(
+        TQAVCTRL,
+        ctrl | TQAVCTRL_XMIT_MODE | TQAVCTRL_SP_WAIT_SR | TQAVCTRL_DATATRANARB,
+    );
+    let txpbs = mac.read_reg(TXPBS) & !TXPBS_MASK;
+    mac.write_reg(TXPBS, txpbs | TXPBS_QAV);
+    let rxpbs = mac.read_reg(RXPBS) & !RXPBS_MASK;
+    mac.write_reg(RXPBS, rxpbs | RXPBS_QAV);
+    mac.write_reg(DTXMXPKTSZ, DTXMXPKTSZ_QAV);
+
+    for (queue, slope) in sched.idle_slopes().into_iter().enumerate() {
+        let cc = TQAVCC + queue * 0x40;
+        let hc = TQAVHC + queue * 0x40;
+        let Some(slope) = slope else {
+            let tqavcc = mac.read_reg(cc) & !(TQAVCC_QUEUEMODE_SR | TQAVCC_IDLESLOPE_MASK);
+            mac.write_reg(cc, tqavcc);
+            mac.write_reg(hc, 0);
+            continue;
+        };
+        let tqavcc = mac.read_reg(cc) & !TQAVCC_IDLESLOPE_MASK;
+        mac.write_reg(cc, tqavcc | TQAVCC_QUEUEMODE_SR | slope);
+        mac.write_reg(hc, HICREDIT_BASE + MAX_FRAME * HICREDIT_PER_BYTE);
+    }
+}
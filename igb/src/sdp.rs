@@ -0,0 +1,109 @@
+use crate::{DError, mac::Mac};
+
+const CTRL: usize = 0x0;
+const CTRL_EXT: usize = 0x18;
+const TSSDP: usize = 0x3C; // Time Sync SDP Configuration (I210)
+
+/// Number of software-definable pins.
+pub const SDP_COUNT: u8 = 4;
+
+/// Time sync signal that can drive an SDP pin (I210).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdpFunction {
+    /// Plain GPIO controlled by [`Sdp::set`].
+    Gpio,
+    /// Target time 0 output.
+    TargetTime0,
+    /// Target time 1 output.
+    TargetTime1,
+    /// Frequency clock 0 output.
+    ClockOut0,
+    /// Frequency clock 1 output.
+    ClockOut1,
+}
+
+/// Handle to one SDP pin, see [`crate::Igb::sdp`].
+pub struct Sdp<'a> {
+    mac: &'a mut Mac,
+    pin: u8,
+}
+
+impl<'a> Sdp<'a> {
+    pub(crate) fn new(mac: &'a mut Mac, pin: u8) -> Result<Self, DError> {
+        if pin >= SDP_COUNT {
+            return Err(DError::InvalidParameter);
+        }
+        Ok(Self { mac, pin })
+    }
+
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+
+    // SDP0/1 在 CTRL，SDP2/3 在 CTRL_EXT
+    fn bits(&self) -> (usize, u32, u32) {
+        match self.pin {
+            0 => (CTRL, 1 << 18, 1 << 22),
+            1 => (CTRL, 1 << 19, 1 << 23),
+            2 => (CTRL_EXT, 1 << 6, 1 << 10),
+            _ => (CTRL_EXT, 1 << 7, 1 << 11),
+        }
+    }
+
+    /// Configure the pin as output driving `high`.
+    pub fn set_output(&mut self, high: bool) {
+        self.set(high);
+        let (reg, _, dir) = self.bits();
+        let val = self.mac.read_reg(reg);
+        self.mac.write_reg(reg, val | dir);
+    }
+
+    pub fn set_input(&mut self) {
+        let (reg, _, dir) = self.bits();
+        let val = self.mac.read_reg(reg);
+        self.mac.write_reg(reg, val & !dir);
+    }
+
+    pub fn is_output(&self) -> bool {
+        let (reg, _, dir) = self.bits();
+        self.mac.read_reg(reg) & dir != 0
+    }
+
+    /// Level of the pin; for outputs this is the driven value.
+    pub fn get(&self) -> bool {
+        let (reg, data, _) = self.bits();
+        self.mac.read_reg(reg) & data != 0
+    }
+
+    /// Change the value of a pin already configured as output.
+    pub fn set(&mut self, high: bool) {
+        let (reg, data, _) = self.bits();
+        let val = self.mac.read_reg(reg);
+        self.mac
+            .write_reg(reg, if high { val | data } else { val & !data });
+    }
+
+    /// Route a time sync signal to this pin (I210 only).
+    ///
+    /// The pin is switched to output; [`SdpFunction::Gpio`] hands it back to
+    /// software control.
+    pub fn route(&mut self, function: SdpFunction) {
+        let sel_shift = 6 + self.pin as u32 * 3;
+        let en = 1 << (sel_shift + 2);
+        let mut tssdp = self.mac.read_reg(TSSDP) & !((0x3 << sel_shift) | en);
+        let sel = match function {
+            SdpFunction::Gpio => None,
+            SdpFunction::TargetTime0 => Some(0),
+            SdpFunction::TargetTime1 => Some(1),
+            SdpFunction::ClockOut0 => Some(2),
+            SdpFunction::ClockOut1 => Some(3),
+        };
+        if let Some(sel) = sel {
+            tssdp |= (sel << sel_shift) | en;
+            let (reg, _, dir) = self.bits();
+            let val = self.mac.read_reg(reg);
+            self.mac.write_reg(reg, val | dir);
+        }
+        self.mac.write_reg(TSSDP, tssdp);
+    }
+}
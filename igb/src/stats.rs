@@ -0,0 +1,264 @@
+use core::time::Duration;
+
+use crate::{
+    mac::Mac,
+    ring::{RxLayout, RxRing, TxRing},
+};
+
+// Per-queue good packet/octet counters, 0x100 apart per queue
+const PQGPRC: usize = 0x10010; // Per Queue Good Packets Received Count
+const PQGPTC: usize = 0x10014; // Per Queue Good Packets Transmitted Count
+const PQGORC: usize = 0x10018; // Per Queue Good Octets Received Count
+const PQGOTC: usize = 0x10034; // Per Queue Good Octets Transmitted Count
+
+/// Most queues with per-queue hardware counters; devices with fewer queues
+/// (I210 has 4) only count those, see [`crate::Igb::queue_stats`].
+pub const QUEUE_STATS_COUNT: usize = 8;
+
+/// Longest safe period between [`crate::Igb::stats_tick`] calls.
+///
+/// The 32-bit per-queue octet counters wrap after about 34 s at 1 Gb/s;
+/// this leaves a wide margin for a late timer.
+pub const STATS_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Per-queue traffic counters maintained by the hardware.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+#[cfg(feature = "display")]
+impl core::fmt::Display for QueueStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "     rx_packets: {}", self.rx_packets)?;
+        writeln!(f, "     rx_bytes: {}", self.rx_bytes)?;
+        writeln!(f, "     tx_packets: {}", self.tx_packets)?;
+        writeln!(f, "     tx_bytes: {}", self.tx_bytes)
+    }
+}
+
+/// Software state of an RX/TX ring pair, see [`RingStats::collect`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RingStats {
+    /// Number of RX descriptors.
+    pub rx_descriptors: usize,
+    /// RX buffers posted to the hardware, received into or not.
+    pub rx_posted: usize,
+    /// RX descriptors written back and not taken yet.
+    pub rx_pending: usize,
+    /// Spare buffers in the RX refill pool.
+    pub rx_pool: usize,
+    /// Frames that spanned more than one RX descriptor.
+    pub rx_oversize_frames: u64,
+    /// Packets the hardware dropped for lack of RX descriptors.
+    pub rx_hw_drops: u64,
+    /// Number of TX descriptors.
+    pub tx_descriptors: usize,
+    /// TX requests handed to the hardware and not yet sent.
+    pub tx_in_flight: usize,
+    /// TX requests sent and not yet reclaimed.
+    pub tx_completed: usize,
+}
+
+impl RingStats {
+    /// Snapshot the counters of `rx` and `tx`; reading the RX drop counter
+    /// accumulates it like [`RxRing::hw_drops`].
+    pub fn collect<L: RxLayout>(rx: &mut RxRing<L>, tx: &TxRing) -> Self {
+        Self {
+            rx_descriptors: rx.request_max_count() + 1,
+            rx_posted: rx.posted_count(),
+            rx_pending: rx.pending_count(),
+            rx_pool: rx.pool_len(),
+            rx_oversize_frames: rx.oversize_frames(),
+            rx_hw_drops: rx.hw_drops(),
+            tx_descriptors: tx.request_max_count() + 1,
+            tx_in_flight: tx.in_flight_count(),
+            tx_completed: tx.completed_count(),
+        }
+    }
+}
+
+#[cfg(feature = "display")]
+impl core::fmt::Display for RingStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "Ring statistics:")?;
+        writeln!(f, "     rx_descriptors: {}", self.rx_descriptors)?;
+        writeln!(f, "     rx_posted: {}", self.rx_posted)?;
+        writeln!(f, "     rx_pending: {}", self.rx_pending)?;
+        writeln!(f, "     rx_pool: {}", self.rx_pool)?;
+        writeln!(f, "     rx_oversize_frames: {}", self.rx_oversize_frames)?;
+        writeln!(f, "     rx_hw_drops: {}", self.rx_hw_drops)?;
+        writeln!(f, "     tx_descriptors: {}", self.tx_descriptors)?;
+        writeln!(f, "     tx_in_flight: {}", self.tx_in_flight)?;
+        writeln!(f, "     tx_completed: {}", self.tx_completed)
+    }
+}
+
+/// 每队列计数器是 32 位自由计数（不会读清零），这里按差值累加到 64 位
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct QueueCounters {
+    last: [u32; 4],
+    total: QueueStats,
+}
+
+impl QueueCounters {
+    pub fn update(&mut self, mac: &Mac, queue: usize) -> QueueStats {
+        let base = queue * 0x100;
+        let raw = [PQGPRC, PQGPTC, PQGORC, PQGOTC].map(|reg| mac.read_reg(reg + base));
+        let delta = |i: usize| raw[i].wrapping_sub(self.last[i]) as u64;
+
+        self.total.rx_packets += delta(0);
+        self.total.tx_packets += delta(1);
+        self.total.rx_bytes += delta(2);
+        self.total.tx_bytes += delta(3);
+        self.last = raw;
+        self.total
+    }
+
+    /// 以当前硬件值为基准重新从零开始累加
+    pub fn reset(&mut self, mac: &Mac, queue: usize) {
+        let base = queue * 0x100;
+        self.last = [PQGPRC, PQGPTC, PQGORC, PQGOTC].map(|reg| mac.read_reg(reg + base));
+        self.total = QueueStats::default();
+    }
+}
+
+macro_rules! hw_stats {
+    ($($(#[$doc:meta])* $field:ident: $reg:expr,)*) => {
+        /// Global MAC statistics, accumulated to 64 bits in software.
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct HwStats {
+            $($(#[$doc])* pub $field: u64,)*
+            /// Good octets received.
+            pub rx_bytes: u64,
+            /// Good octets transmitted.
+            pub tx_bytes: u64,
+        }
+
+        impl HwStats {
+            /// 全局计数器读清零，直接读出本次增量
+            fn read_hw(mac: &Mac) -> Self {
+                // 64 位字节计数先读低位，读高位时整体清零
+                let octets = |lo: usize| {
+                    let low = mac.read_reg(lo) as u64;
+                    low | (mac.read_reg(lo + 4) as u64) << 32
+                };
+                Self {
+                    $($field: mac.read_reg($reg) as u64,)*
+                    rx_bytes: octets(GORCL),
+                    tx_bytes: octets(GOTCL),
+                }
+            }
+
+            fn accumulate(&mut self, other: &Self) {
+                $(self.$field += other.$field;)*
+                self.rx_bytes += other.rx_bytes;
+                self.tx_bytes += other.tx_bytes;
+            }
+
+            /// Counter increase from `prev` to `self`.
+            pub fn delta(&self, prev: &Self) -> Self {
+                Self {
+                    $($field: self.$field.saturating_sub(prev.$field),)*
+                    rx_bytes: self.rx_bytes.saturating_sub(prev.rx_bytes),
+                    tx_bytes: self.tx_bytes.saturating_sub(prev.tx_bytes),
+                }
+            }
+
+            /// Scale a [`HwStats::delta`] taken over `elapsed` to per-second rates.
+            pub fn per_second(&self, elapsed: Duration) -> Self {
+                let nanos = elapsed.as_nanos().max(1);
+                let rate = |v: u64| (v as u128 * 1_000_000_000 / nanos) as u64;
+                Self {
+                    $($field: rate(self.$field),)*
+                    rx_bytes: rate(self.rx_bytes),
+                    tx_bytes: rate(self.tx_bytes),
+                }
+            }
+        }
+
+        #[cfg(feature = "display")]
+        impl core::fmt::Display for HwStats {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                writeln!(f, "NIC statistics:")?;
+                writeln!(f, "     rx_bytes: {}", self.rx_bytes)?;
+                writeln!(f, "     tx_bytes: {}", self.tx_bytes)?;
+                $(writeln!(f, "     {}: {}", stringify!($field), self.$field)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+// Global statistics registers, all clear-on-read
+const GORCL: usize = 0x4088; // Good Octets Received Count Low
+const GOTCL: usize = 0x4090; // Good Octets Transmitted Count Low
+
+hw_stats! {
+    /// CRC errors.
+    crc_errors: 0x4000,
+    /// Alignment errors.
+    align_errors: 0x4004,
+    /// Symbol/receive errors.
+    rx_errors: 0x400C,
+    /// Frames missed because the packet buffer was full.
+    missed_packets: 0x4010,
+    /// Collisions.
+    collisions: 0x4028,
+    /// Receive length errors.
+    length_errors: 0x4040,
+    /// XON frames received.
+    xon_rx: 0x4048,
+    /// XON frames transmitted.
+    xon_tx: 0x404C,
+    /// XOFF frames received.
+    xoff_rx: 0x4050,
+    /// XOFF frames transmitted.
+    xoff_tx: 0x4054,
+    /// MAC control frames other than PAUSE received.
+    rx_fc_unsupported: 0x4058,
+    /// Good packets received.
+    rx_packets: 0x4074,
+    /// Broadcast packets received.
+    rx_broadcast: 0x4078,
+    /// Multicast packets received.
+    rx_multicast: 0x407C,
+    /// Good packets transmitted.
+    tx_packets: 0x4080,
+    /// Frames dropped for lack of receive descriptors.
+    rx_no_buffer: 0x40A0,
+    /// Undersized frames received.
+    rx_undersize: 0x40A4,
+    /// Fragmented frames received.
+    rx_fragments: 0x40A8,
+    /// Oversized frames received.
+    rx_oversize: 0x40AC,
+    /// Jabber frames received.
+    rx_jabber: 0x40B0,
+    /// Multicast packets transmitted.
+    tx_multicast: 0x40F0,
+    /// Broadcast packets transmitted.
+    tx_broadcast: 0x40F4,
+}
+
+/// 软件累加的全局计数器
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct HwCounters {
+    total: HwStats,
+}
+
+impl HwCounters {
+    pub fn update(&mut self, mac: &Mac) -> HwStats {
+        self.total.accumulate(&HwStats::read_hw(mac));
+        self.total
+    }
+
+    /// Clear the hardware counters by reading them and drop the totals.
+    pub fn reset(&mut self, mac: &Mac) {
+        HwStats::read_hw(mac);
+        self.total = HwStats::default();
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{DError, family::DeviceFamily, mac::Mac};
+
+const ETQF: usize = 0x5CB0; // EType Queue Filter, one per filter
+const TTQF: usize = 0x59E0; // 2-Tuple Queue Filter (FTQF on 82576)
+const IMIR: usize = 0x5A80; // 端口条件与 LLI 过滤器共用
+const IMIREXT: usize = 0x5AA0;
+
+const ETQF_QUEUE_SHIFT: u32 = 16;
+const ETQF_QUEUE_MASK: u32 = 0x7;
+const ETQF_FILTER_ENABLE: u32 = 1 << 26;
+const ETQF_IMMEDIATE: u32 = 1 << 29;
+const ETQF_QUEUE_ENABLE: u32 = 1 << 31;
+
+const TTQF_QUEUE_ENABLE: u32 = 1 << 8;
+const TTQF_QUEUE_SHIFT: u32 = 16;
+const TTQF_POOL_BP: u32 = 1 << 15;
+const TTQF_PROTOCOL_BP: u32 = 1 << 28;
+// 82576 的 FTQF 还比较源/目的地址和源端口，全部跳过
+const TTQF_ADDR_PORT_BP: u32 = 0b111 << 29;
+
+const IMIR_PORT_IM_EN: u32 = 1 << 16;
+const IMIR_PORT_BP: u32 = 1 << 17;
+const IMIREXT_SIZE_BP: u32 = 1 << 12;
+const IMIREXT_CTRL_BP: u32 = 1 << 19;
+
+/// Number of EtherType queue filters.
+pub const ETYPE_FILTER_COUNT: usize = 8;
+/// Number of 2-tuple queue filters. They share their slots with the
+/// [`crate::LliFilter`]s of the same index.
+pub const TUPLE_FILTER_COUNT: usize = 8;
+
+/// Steer frames of one EtherType, see [`crate::Igb::set_etype_filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EtherTypeFilter {
+    pub ether_type: u16,
+    /// Receive queue for matching frames, below 8. `None` leaves the queue
+    /// to the other filters and RSS.
+    pub queue: Option<u8>,
+    /// Raise the queue's interrupt immediately, bypassing throttling.
+    pub immediate: bool,
+}
+
+/// Steer IP packets by protocol and destination port, see
+/// [`crate::Igb::set_tuple_filter`]. Conditions left `None` match any packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TupleFilter {
+    /// IP protocol number, e.g. 6 for TCP or 17 for UDP.
+    pub protocol: Option<u8>,
+    /// TCP/UDP destination port.
+    pub port: Option<u16>,
+    /// Receive queue for matching packets.
+    pub queue: u8,
+    /// Raise the queue's interrupt immediately, bypassing throttling.
+    pub immediate: bool,
+}
+
+fn check_queue(queue: u8, limit: usize) -> Result<(), DError> {
+    if queue as usize >= limit {
+        return Err(DError::InvalidParameter);
+    }
+    Ok(())
+}
+
+/// 只检查过滤器本身，不访问寄存器
+pub(crate) fn check_etype_filter(
+    family: DeviceFamily,
+    filter: &EtherTypeFilter,
+) -> Result<(), DError> {
+    match filter.queue {
+        Some(queue) => check_queue(
+            queue,
+            family.queue_count().min(ETQF_QUEUE_MASK as usize + 1),
+        ),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn check_tuple_filter(family: DeviceFamily, filter: &TupleFilter) -> Result<(), DError> {
+    check_queue(filter.queue, family.queue_count())
+}
+
+pub(crate) fn write_etype_filter(
+    mac: &mut Mac,
+    family: DeviceFamily,
+    index: usize,
+    filter: Option<&EtherTypeFilter>,
+) -> Result<(), DError> {
+    if index >= ETYPE_FILTER_COUNT {
+        return Err(DError::InvalidParameter);
+    }
+    let Some(filter) = filter else {
+        mac.write_reg(ETQF + index * 4, 0);
+        return Ok(());
+    };
+    check_etype_filter(family, filter)?;
+    let mut etqf = filter.ether_type as u32 | ETQF_FILTER_ENABLE;
+    if let Some(queue) = filter.queue {
+        etqf |= (queue as u32) << ETQF_QUEUE_SHIFT | ETQF_QUEUE_ENABLE;
+    }
+    if filter.immediate {
+        etqf |= ETQF_IMMEDIATE;
+    }
+    mac.write_reg(ETQF + index * 4, etqf);
+    Ok(())
+}
+
+pub(crate) fn write_tuple_filter(
+    mac: &mut Mac,
+    family: DeviceFamily,
+    index: usize,
+    filter: Option<&TupleFilter>,
+) -> Result<(), DError> {
+    if index >= TUPLE_FILTER_COUNT {
+        return Err(DError::InvalidParameter);
+    }
+    let Some(filter) = filter else {
+        mac.write_reg(TTQF + index * 4, 0);
+        mac.write_reg(IMIR + index * 4, 0);
+        mac.write_reg(IMIREXT + index * 4, 0);
+        return Ok(());
+    };
+    check_tuple_filter(family, filter)?;
+
+    let mut imir = match filter.port {
+        // 端口按网络字节序比较
+        Some(port) => port.to_be() as u32,
+        None => IMIR_PORT_BP,
+    };
+    if filter.immediate {
+        imir |= IMIR_PORT_IM_EN;
+    }
+    let mut ttqf = TTQF_QUEUE_ENABLE
+        | (filter.queue as u32) << TTQF_QUEUE_SHIFT
+        | TTQF_POOL_BP
+        | TTQF_ADDR_PORT_BP;
+    match filter.protocol {
+        Some(protocol) => ttqf |= protocol as u32,
+        None => ttqf |= TTQF_PROTOCOL_BP,
+    }
+
+    // 先写匹配条件，最后使能队列分配
+    mac.write_reg(IMIREXT + index * 4, IMIREXT_SIZE_BP | IMIREXT_CTRL_BP);
+    mac.write_reg(IMIR + index * 4, imir);
+    mac.write_reg(TTQF + index * 4, ttqf);
+    Ok(())
+}
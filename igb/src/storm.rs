@@ -0,0 +1,174 @@
+use core::time::Duration;
+
+use crate::{mac::Mac, stats::HwStats};
+
+/// Broadcast/multicast storm control, see [`crate::Igb::set_storm_control`].
+///
+/// Rates are measured from the BPRC/MPRC statistics counters between calls
+/// of [`crate::Igb::storm_watchdog`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StormPolicy {
+    /// Broadcast frames per second above which a storm is reported.
+    pub broadcast_pps: Option<u64>,
+    /// Multicast frames per second above which a storm is reported.
+    pub multicast_pps: Option<u64>,
+    /// What to do once a threshold is exceeded.
+    pub action: StormAction,
+}
+
+/// What [`crate::Igb::storm_watchdog`] does when a storm starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StormAction {
+    /// Only report it.
+    #[default]
+    Report,
+    /// Also tighten the receive filters for `hold`: broadcast frames are
+    /// dropped (RCTL.BAM), multicast frames outside the multicast table are
+    /// dropped (RCTL.MPE). Then the frames are accepted again to measure
+    /// the rate, since the counters don't see filtered frames.
+    ///
+    /// Dropping broadcasts also drops ARP, and multicast neighbour discovery
+    /// needs its groups in the multicast table.
+    Drop { hold: Duration },
+}
+
+/// Storm state changes found by [`crate::Igb::storm_watchdog`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StormEvents {
+    /// A broadcast storm started.
+    pub broadcast_started: bool,
+    /// The broadcast rate is back below the threshold.
+    pub broadcast_ended: bool,
+    /// A multicast storm started.
+    pub multicast_started: bool,
+    /// The multicast rate is back below the threshold.
+    pub multicast_ended: bool,
+}
+
+impl StormEvents {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 一类帧的风暴状态
+#[derive(Debug, Clone, Copy, Default)]
+struct Tracker {
+    active: bool,
+    /// 剩余的屏蔽时间，None 表示正在接收
+    blocked: Option<Duration>,
+    /// 屏蔽前 RCTL 中的接收位
+    accepted: bool,
+}
+
+impl Tracker {
+    /// `accept` 写入 RCTL 接收位并返回原值；返回 (开始, 结束)
+    fn update(
+        &mut self,
+        limit: Option<u64>,
+        frames: u64,
+        elapsed: Duration,
+        action: StormAction,
+        accept: &mut dyn FnMut(bool) -> bool,
+    ) -> (bool, bool) {
+        let Some(limit) = limit else {
+            let ended = self.active;
+            self.clear(accept);
+            return (false, ended);
+        };
+        if let Some(left) = self.blocked {
+            // 屏蔽期间计数器看不到被过滤的帧，到期后恢复接收再测量
+            match left.checked_sub(elapsed) {
+                Some(left) if !left.is_zero() => self.blocked = Some(left),
+                _ => {
+                    accept(self.accepted);
+                    self.blocked = None;
+                }
+            }
+            return (false, false);
+        }
+        let nanos = elapsed.as_nanos().max(1);
+        let rate = (frames as u128 * 1_000_000_000 / nanos) as u64;
+        if rate <= limit {
+            let ended = self.active;
+            self.active = false;
+            return (false, ended);
+        }
+        let started = !self.active;
+        self.active = true;
+        if let StormAction::Drop { hold } = action {
+            self.accepted = accept(false);
+            self.blocked = Some(hold);
+        }
+        (started, false)
+    }
+
+    /// 撤销屏蔽并回到初始状态
+    fn clear(&mut self, accept: &mut dyn FnMut(bool) -> bool) {
+        if self.blocked.is_some() {
+            accept(self.accepted);
+        }
+        *self = Self::default();
+    }
+}
+
+/// 风暴控制的软件状态
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct StormControl {
+    pub policy: StormPolicy,
+    /// 上次检查时的累计计数
+    last: (u64, u64),
+    broadcast: Tracker,
+    multicast: Tracker,
+}
+
+impl StormControl {
+    /// 以当前计数为基准重新开始，撤销所有屏蔽
+    pub fn reset(&mut self, mac: &mut Mac, stats: &HwStats) {
+        self.broadcast.clear(&mut |on| mac.set_broadcast_accept(on));
+        self.multicast
+            .clear(&mut |on| mac.set_multicast_promisc(on));
+        self.last = (stats.rx_broadcast, stats.rx_multicast);
+    }
+
+    /// 复位后 RCTL 和累计计数都已清零，只丢弃软件状态
+    pub fn forget(&mut self) {
+        self.broadcast = Tracker::default();
+        self.multicast = Tracker::default();
+        self.rebase();
+    }
+
+    /// 累计计数清零后从零开始比较
+    pub fn rebase(&mut self) {
+        self.last = (0, 0);
+    }
+
+    pub fn update(&mut self, mac: &mut Mac, stats: &HwStats, elapsed: Duration) -> StormEvents {
+        let broadcast = stats.rx_broadcast.saturating_sub(self.last.0);
+        let multicast = stats.rx_multicast.saturating_sub(self.last.1);
+        self.last = (stats.rx_broadcast, stats.rx_multicast);
+
+        let (broadcast_started, broadcast_ended) = self.broadcast.update(
+            self.policy.broadcast_pps,
+            broadcast,
+            elapsed,
+            self.policy.action,
+            &mut |on| mac.set_broadcast_accept(on),
+        );
+        let (multicast_started, multicast_ended) = self.multicast.update(
+            self.policy.multicast_pps,
+            multicast,
+            elapsed,
+            self.policy.action,
+            &mut |on| mac.set_multicast_promisc(on),
+        );
+        StormEvents {
+            broadcast_started,
+            broadcast_ended,
+            multicast_started,
+            multicast_ended,
+        }
+    }
+}
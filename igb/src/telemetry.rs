@@ -0,0 +1,80 @@
+/// 只在启用 `telemetry` 特性时执行的计数语句
+macro_rules! telemetry {
+    ($($t:tt)*) => {
+        #[cfg(feature = "telemetry")]
+        {
+            $($t)*
+        }
+    };
+}
+
+/// 收发和中断路径上的日志，启用 `no-datapath-log` 特性时优化器连同格式化一起去掉
+macro_rules! datapath_trace {
+    ($($t:tt)*) => {
+        if cfg!(not(feature = "no-datapath-log")) {
+            log::trace!($($t)*);
+        }
+    };
+}
+
+macro_rules! datapath_debug {
+    ($($t:tt)*) => {
+        if cfg!(not(feature = "no-datapath-log")) {
+            log::debug!($($t)*);
+        }
+    };
+}
+
+/// Datapath counters, available with the `telemetry` feature.
+///
+/// [`crate::Igb::telemetry`] fills the interrupt counters, the rings fill the
+/// rest; add them up with `+=` for a device-wide view.
+#[cfg(feature = "telemetry")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Telemetry {
+    /// Calls to [`crate::Igb::handle_interrupt`].
+    pub irqs: u64,
+    /// Calls to [`crate::Igb::handle_vector_interrupt`].
+    pub vector_irqs: u64,
+    /// Tail register writes.
+    pub doorbells: u64,
+    /// RX poll passes: `poll_burst` calls and `recv` wakeups.
+    pub polls: u64,
+    /// Descriptors reclaimed: RX descriptors of harvested frames, or TX
+    /// descriptors returned by `next_finished`.
+    pub harvested: u64,
+    /// Most descriptors harvested in a single RX poll pass.
+    pub max_harvest_per_poll: u64,
+    /// Buffers posted from the RX pool by the refill policy.
+    pub refills: u64,
+    /// 当前轮询中已回收的描述符数
+    poll_harvest: u64,
+}
+
+#[cfg(feature = "telemetry")]
+impl Telemetry {
+    pub(crate) fn record_harvest(&mut self, descriptors: u64) {
+        self.harvested += descriptors;
+        self.poll_harvest += descriptors;
+    }
+
+    /// 结束一次轮询
+    pub(crate) fn record_poll(&mut self) {
+        self.polls += 1;
+        self.max_harvest_per_poll = self.max_harvest_per_poll.max(self.poll_harvest);
+        self.poll_harvest = 0;
+    }
+}
+
+#[cfg(feature = "telemetry")]
+impl core::ops::AddAssign for Telemetry {
+    fn add_assign(&mut self, rhs: Self) {
+        self.irqs += rhs.irqs;
+        self.vector_irqs += rhs.vector_irqs;
+        self.doorbells += rhs.doorbells;
+        self.polls += rhs.polls;
+        self.harvested += rhs.harvested;
+        self.max_harvest_per_poll = self.max_harvest_per_poll.max(rhs.max_harvest_per_poll);
+        self.refills += rhs.refills;
+    }
+}
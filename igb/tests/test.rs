@@ -4,7 +4,7 @@
 
 use core::time::Duration;
 
-use bare_test::time::spin_delay;
+use bare_test::time::{since_boot, spin_delay};
 use eth_igb::{impl_trait, osal::Kernel};
 
 extern crate alloc;
@@ -14,6 +14,7 @@ mod tests {
     use core::{
         cell::UnsafeCell,
         ops::{Deref, DerefMut},
+        sync::atomic::{AtomicU64, Ordering},
         time::Duration,
     };
 
@@ -26,7 +27,7 @@ mod tests {
         println,
         time::spin_delay,
     };
-    use eth_igb::{Igb, RxPacket};
+    use eth_igb::{FlowControl, Igb, RxPacket};
     use log::*;
     use pcie::{CommandRegister, PciCapability, RootComplexGeneric, SimpleBarAllocator};
     use smoltcp::socket::icmp::{self, Socket as IcmpSocket};
@@ -193,10 +194,8 @@ mod tests {
         info!("mac: {mac:#?}");
 
         info!("waiting for link up...");
-        while !igb.status().link_up {
-            spin_delay(Duration::from_secs(1));
-            info!("status: {:#?}", igb.status());
-        }
+        let status = igb.wait_link_up_timeout(Duration::from_secs(30)).unwrap();
+        info!("status: {status:#?}");
 
         let (tx_ring, rx_ring) = igb.new_ring().unwrap();
 
@@ -245,6 +244,186 @@ mod tests {
         println!("ping test completed!");
     }
 
+    /// 两端按各自的配置通告 PAUSE，协商出的方向要互相匹配：
+    /// 一端发送 PAUSE 时另一端必须响应，且不超出本端配置
+    #[test]
+    fn flow_control_autoneg_round_trip() {
+        let modes = [(false, false), (true, false), (false, true), (true, true)];
+        let config = |(rx_pause, tx_pause)| FlowControl {
+            rx_pause,
+            tx_pause,
+            ..Default::default()
+        };
+        for (rx, tx) in modes {
+            let local = config((rx, tx));
+            let (pause, asym_pause) = local.advertisement();
+            for mode in modes {
+                let partner = config(mode);
+                let (partner_pause, partner_asym) = partner.advertisement();
+                let (local_rx, local_tx) = local.resolve(partner_pause, partner_asym);
+                let (partner_rx, partner_tx) = partner.resolve(pause, asym_pause);
+                assert!(!local_tx || partner_rx, "{:?} -> {mode:?}", (rx, tx));
+                assert!(!partner_tx || local_rx, "{mode:?} -> {:?}", (rx, tx));
+                assert!((!local_rx || rx) && (!local_tx || tx));
+            }
+            // 对端两种 PAUSE 都支持时结果就是配置本身
+            assert_eq!(local.resolve(true, true), (rx, tx));
+        }
+        println!("flow control round trip passed");
+    }
+
+    const BENCH_FRAME_LEN: usize = 1514;
+    const BENCH_DURATION: Duration = Duration::from_secs(5);
+
+    static BENCH_IRQS: AtomicU64 = AtomicU64::new(0);
+
+    /// 通过 MAC 回环让 TX 环跑满，统计 RX 吞吐
+    #[test]
+    fn loopback_bench() {
+        let (igb, irq) = get_igb().unwrap();
+        let mut igb = Driver::new(igb);
+        let igb_ptr = igb.0.get();
+
+        for one in &irq.cfgs {
+            IrqParam {
+                intc: irq.irq_parent,
+                cfg: one.clone(),
+            }
+            .register_builder({
+                move |_irq| {
+                    BENCH_IRQS.fetch_add(1, Ordering::Relaxed);
+                    unsafe {
+                        (*igb_ptr).handle_interrupt();
+                    }
+                    IrqHandleResult::Handled
+                }
+            })
+            .register();
+        }
+        igb.irq_mode_legacy();
+        igb.open().unwrap();
+        // 回环不依赖链路状态
+        igb.enable_loopback();
+
+        let mac = igb.read_mac().bytes();
+        // 中断补充缓冲区时任务和中断并发推进同一个环的索引，需要 irq-safe
+        let irq_refill: &[bool] = if cfg!(feature = "irq-safe") {
+            &[false, true]
+        } else {
+            &[false]
+        };
+        // 对比不同环长度下的吞吐
+        for size in [256, 1024] {
+            for &irq_refill in irq_refill {
+                let config = eth_igb::RingConfig {
+                    size,
+                    ..Default::default()
+                };
+                let (tx_ring, rx_ring) = igb.new_ring_with(0, config).unwrap();
+                bench_rings(mac, tx_ring, rx_ring, irq_refill);
+            }
+        }
+        igb.disable_loopback();
+    }
+
+    /// `irq_refill` 时接收的缓冲区交回缓冲池，由中断补充到环里，
+    /// 衡量尾指针和 next_clean 分处不同缓存行的效果
+    fn bench_rings(
+        mac: [u8; 6],
+        mut tx_ring: eth_igb::TxRing,
+        mut rx_ring: eth_igb::RxRing,
+        irq_refill: bool,
+    ) {
+        for _ in 0..rx_ring.request_max_count() {
+            let buff = alloc::vec![0u8; rx_ring.packet_size()];
+            rx_ring.submit(eth_igb::Request::new_rx(buff)).unwrap();
+            if irq_refill {
+                let buff = alloc::vec![0u8; rx_ring.packet_size()];
+                rx_ring
+                    .add_pool_buffer(eth_igb::Request::new_rx(buff))
+                    .unwrap();
+            }
+        }
+        if irq_refill {
+            rx_ring.set_refill_policy(eth_igb::RefillPolicy::Threshold(32));
+        }
+
+        // 目的和源都是本机 MAC，本地实验用 EtherType 0x88B5
+        let mut frame = alloc::vec![0u8; BENCH_FRAME_LEN];
+        frame[0..6].copy_from_slice(&mac);
+        frame[6..12].copy_from_slice(&mac);
+        frame[12..14].copy_from_slice(&0x88B5u16.to_be_bytes());
+        let mut free: alloc::vec::Vec<_> = (0..tx_ring.request_max_count())
+            .map(|_| eth_igb::Request::new_tx(frame.clone()))
+            .collect();
+
+        let mut rx_packets = 0u64;
+        let mut rx_bytes = 0u64;
+        let irqs_start = BENCH_IRQS.load(Ordering::Relaxed);
+        let cycles_start = cycles();
+        let start = bare_test::time::since_boot();
+
+        while bare_test::time::since_boot() - start < BENCH_DURATION {
+            while let Some(mut request) = tx_ring.next_finished() {
+                request.set_len(BENCH_FRAME_LEN);
+                free.push(request);
+            }
+            while !tx_ring.is_queue_full() {
+                let Some(request) = free.pop() else {
+                    break;
+                };
+                tx_ring.send(request).unwrap();
+            }
+            while let Some(pkt) = rx_ring.next_pkt() {
+                rx_packets += 1;
+                rx_bytes += pkt.len() as u64;
+                if irq_refill {
+                    let request = pkt.into_request();
+                    rx_ring.add_pool_buffer(request).unwrap();
+                } else {
+                    pkt.re_submit().unwrap();
+                }
+            }
+        }
+
+        let elapsed = bare_test::time::since_boot() - start;
+        let cycles = cycles() - cycles_start;
+        let irqs = BENCH_IRQS.load(Ordering::Relaxed) - irqs_start;
+
+        let secs = elapsed.as_secs_f64();
+        println!(
+            "loopback bench ({} descriptors, {} refill): {} packets in {:?}, {:.0} pps, {:.1} Mbps, {:.0} irq/s, {} cycles/pkt",
+            rx_ring.request_max_count() + 1,
+            if irq_refill { "irq" } else { "task" },
+            rx_packets,
+            elapsed,
+            rx_packets as f64 / secs,
+            (rx_bytes * 8) as f64 / secs / 1_000_000.0,
+            irqs as f64 / secs,
+            cycles.checked_div(rx_packets).unwrap_or(0),
+        );
+        assert!(rx_packets > 0, "no looped back frame received");
+    }
+
+    /// PMU 周期计数，其他架构返回 0
+    fn cycles() -> u64 {
+        #[cfg(target_arch = "aarch64")]
+        unsafe {
+            use core::arch::asm;
+            // 打开 PMU 并使能周期计数器
+            let mut pmcr: u64;
+            asm!("mrs {}, pmcr_el0", out(reg) pmcr);
+            pmcr |= 1;
+            asm!("msr pmcr_el0, {}", in(reg) pmcr);
+            asm!("msr pmcntenset_el0, {}", in(reg) 1u64 << 31);
+            let cycles: u64;
+            asm!("isb", "mrs {}, pmccntr_el0", out(reg) cycles);
+            cycles
+        }
+        #[cfg(not(target_arch = "aarch64"))]
+        0
+    }
+
     fn ping_127_0_0_1(
         iface: &mut Interface,
         device: &mut IgbDevice,
@@ -393,7 +572,7 @@ mod tests {
 
                 let addr = iomap(bar_addr.into(), bar_size);
 
-                let igb = Igb::new(addr).unwrap();
+                let igb = Igb::new_with_bar(addr, bar_size).unwrap();
 
                 let irq = pcie
                     .child_irq_info(
@@ -410,6 +589,9 @@ mod tests {
     }
 }
 
+#[cfg(feature = "irq-safe")]
+use eth_igb::osal::CriticalSection;
+
 struct KernelImpl;
 
 impl_trait! {
@@ -417,5 +599,38 @@ impl_trait! {
         fn sleep(duration: Duration) {
             spin_delay(duration);
         }
+
+        fn now() -> Duration {
+            since_boot()
+        }
+    }
+}
+
+#[cfg(feature = "irq-safe")]
+struct CriticalSectionImpl;
+
+// 屏蔽本 CPU 的 IRQ，返回之前的 DAIF；其他架构上测试是单上下文的
+#[cfg(feature = "irq-safe")]
+impl_trait! {
+    impl CriticalSection for CriticalSectionImpl {
+        fn acquire() -> usize {
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                let daif: usize;
+                core::arch::asm!("mrs {}, daif", "msr daifset, #2", out(reg) daif);
+                daif
+            }
+            #[cfg(not(target_arch = "aarch64"))]
+            0
+        }
+
+        fn release(state: usize) {
+            #[cfg(target_arch = "aarch64")]
+            unsafe {
+                core::arch::asm!("msr daif, {}", in(reg) state);
+            }
+            #[cfg(not(target_arch = "aarch64"))]
+            let _ = state;
+        }
     }
 }